@@ -0,0 +1,75 @@
+use criterion::{criterion_group, criterion_main, Criterion, BenchmarkId};
+use std::hint::black_box;
+use report_dispatcher::arena::ConditionArena;
+use report_dispatcher::ast::Condition;
+use report_dispatcher::lexer::Lexer;
+use report_dispatcher::parser::Parser;
+
+// 生成嵌套 depth 层分组的条件，例如 depth = 2 时为 `x[(("v0" OR "v1") AND "v2")]`
+fn nested_dsl(depth: usize) -> String {
+    let mut condition = r#""v0""#.to_string();
+    for i in 1..=depth {
+        let op = if i % 2 == 0 { "AND" } else { "OR" };
+        condition = format!(r#"({} {} "v{}")"#, condition, op, i);
+    }
+    format!("Filter: x[{}]", condition)
+}
+
+// 解析 DSL 并取出全部基础Filter的条件树
+fn parse_conditions(dsl: &str) -> Vec<Condition> {
+    let tokens: Vec<_> = Lexer::new(dsl).collect();
+    let ast = Parser::new(&tokens).parse().expect("解析应该成功");
+    ast.base_filters.into_iter().map(|filter| filter.condition).collect()
+}
+
+// 基准测试：Box 与 arena 两种形式构建条件树的开销
+fn benchmark_condition_tree(c: &mut Criterion) {
+    let test_cases = vec![
+        ("complex", r#"Filter: title["Release Plan" AND ("Version 1" OR "Version 2")];dueDate[>today];assignee[!=current_user];CrossFilter: <Test-Run>run-id[1]"#.to_string()),
+        ("nested_32", nested_dsl(32)),
+    ];
+
+    let mut group = c.benchmark_group("condition_tree");
+
+    for (name, dsl) in test_cases {
+        let conditions = parse_conditions(&dsl);
+
+        group.bench_with_input(BenchmarkId::new("boxed", name), &conditions, |b, conditions| {
+            b.iter(|| {
+                let trees: Vec<Condition> = black_box(conditions).to_vec();
+                black_box(trees)
+            })
+        });
+
+        // arena 在迭代之间复用，只有第一次迭代需要分配
+        let mut arena = ConditionArena::new();
+        group.bench_with_input(BenchmarkId::new("arena", name), &conditions, |b, conditions| {
+            b.iter(|| {
+                arena.clear();
+                for condition in black_box(conditions) {
+                    black_box(arena.lower(condition));
+                }
+            })
+        });
+
+        // 端到端对比：解析得到 Box 形式后再转换为 arena 的额外开销
+        group.bench_with_input(BenchmarkId::new("parse_boxed", name), &dsl, |b, dsl| {
+            b.iter(|| black_box(parse_conditions(black_box(dsl))))
+        });
+
+        let mut arena = ConditionArena::new();
+        group.bench_with_input(BenchmarkId::new("parse_into_arena", name), &dsl, |b, dsl| {
+            b.iter(|| {
+                arena.clear();
+                for condition in parse_conditions(black_box(dsl)) {
+                    black_box(arena.lower(&condition));
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_condition_tree);
+criterion_main!(benches);
@@ -2,18 +2,23 @@ use criterion::{criterion_group, criterion_main, Criterion, BenchmarkId};
 use std::hint::black_box;
 use report_dispatcher::lexer::Lexer;
 use report_dispatcher::parser::Parser;
-use report_dispatcher::sql_compiler::{SqlCompiler, BatchConfig, QueryCompiler, BatchQueryCompiler, TableMappingProvider};
+use report_dispatcher::sql_compiler::{SqlCompiler, BatchConfig, CacheConfig, CompilerConfig, QueryCompiler, BatchQueryCompiler, TableMappingProvider};
 use std::collections::HashMap;
 
-// 创建一个编译器实例并设置表映射
-fn create_compiler() -> SqlCompiler {
-    let mut compiler = SqlCompiler::new();
+// 基准测试使用的表映射
+fn table_mapping() -> HashMap<String, String> {
     let mut table_mapping = HashMap::new();
     table_mapping.insert("Test".to_string(), "tests".to_string());
     table_mapping.insert("Run".to_string(), "test_runs".to_string());
     table_mapping.insert("Project".to_string(), "projects".to_string());
     table_mapping.insert("Task".to_string(), "tasks".to_string());
-    compiler.table_mapper_mut().set_table_mapping(table_mapping);
+    table_mapping
+}
+
+// 创建一个编译器实例并设置表映射
+fn create_compiler() -> SqlCompiler {
+    let mut compiler = SqlCompiler::new();
+    compiler.table_mapper_mut().set_table_mapping(table_mapping());
     compiler
 }
 
@@ -135,9 +140,9 @@ fn benchmark_batch_compilation(c: &mut Criterion) {
     let ast = parser.parse().expect("解析应该成功");
     
     let batch_configs = vec![
-        ("small_batch", BatchConfig { max_batch_size: 100, enable_batch_processing: true }),
-        ("medium_batch", BatchConfig { max_batch_size: 500, enable_batch_processing: true }),
-        ("large_batch", BatchConfig { max_batch_size: 1000, enable_batch_processing: true }),
+        ("small_batch", BatchConfig { max_batch_size: 100, enable_batch_processing: true, ..Default::default() }),
+        ("medium_batch", BatchConfig { max_batch_size: 500, enable_batch_processing: true, ..Default::default() }),
+        ("large_batch", BatchConfig { max_batch_size: 1000, enable_batch_processing: true, ..Default::default() }),
     ];
 
     let mut group = c.benchmark_group("batch_compilation");
@@ -157,12 +162,54 @@ fn benchmark_batch_compilation(c: &mut Criterion) {
     group.finish();
 }
 
+// 基准测试：编译缓存命中与未启用缓存的对比
+fn benchmark_compile_cache(c: &mut Criterion) {
+    let dsl = r#"Filter: title["Release Plan" AND ("Version 1" OR "Version 2")];dueDate[>today];assignee[!=current_user];CrossFilter: <Test-Run>run-id[1]"#;
+
+    let tokens: Vec<_> = Lexer::new(dsl).collect();
+    let mut parser = Parser::new(&tokens);
+    let ast = parser.parse().expect("解析应该成功");
+
+    let uncached = create_compiler();
+    let cached = SqlCompiler::from_config(CompilerConfig {
+        cache_config: CacheConfig {
+            enable_compile_cache: true,
+            ..Default::default()
+        },
+        table_mapping: table_mapping(),
+        ..Default::default()
+    });
+
+    let mut group = c.benchmark_group("compile_cache");
+
+    group.bench_with_input(BenchmarkId::new("compile", "uncached"), &ast, |b, ast| {
+        b.iter(|| {
+            match uncached.compile(black_box(ast.clone()), "Task") {
+                Ok(result) => black_box(result),
+                Err(_) => panic!("编译失败"),
+            }
+        })
+    });
+
+    group.bench_with_input(BenchmarkId::new("compile", "cached"), &ast, |b, ast| {
+        b.iter(|| {
+            match cached.compile(black_box(ast.clone()), "Task") {
+                Ok(result) => black_box(result),
+                Err(_) => panic!("编译失败"),
+            }
+        })
+    });
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     benchmark_lexer,
     benchmark_parser,
     benchmark_sql_compiler,
     benchmark_end_to_end,
-    benchmark_batch_compilation
+    benchmark_batch_compilation,
+    benchmark_compile_cache
 );
 criterion_main!(benches); 
\ No newline at end of file
@@ -0,0 +1,151 @@
+//! 条件树的索引式存储，需启用 `condition-arena` feature
+//!
+//! [`Condition`] 用 `Box` 连接 AND/OR/NOT/分组节点，深度嵌套的条件在构建时会产生大量小块堆分配。
+//! [`ConditionArena`] 把全部节点存放在同一个 `Vec` 中，子节点以 `u32` 下标引用：
+//! 清空后可以复用已分配的容量，节点在内存中连续存放。
+//!
+//! 代价是节点只能追加、不能单独释放，下标只在所属的 arena 内有效，改写树的结构也不如 `Box` 方便。
+//! 解析器和编译器仍然使用 [`Condition`]，两者通过 [`ConditionArena::lower`] 和
+//! [`ConditionArena::lift`] 互相转换。
+
+use crate::ast::Condition;
+
+/// arena 中节点的下标，只在创建它的 arena 内有效
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConditionId(u32);
+
+impl ConditionId {
+    /// 节点在 arena 中的位置
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// arena 中的条件节点，子条件以下标引用
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConditionNode {
+    And(ConditionId, ConditionId),
+    Or(ConditionId, ConditionId),
+    Not(ConditionId),
+    Grouped(ConditionId),
+    /// 不含子条件的叶子节点，例如比较、IN 列表、空值检查
+    Leaf(Condition),
+}
+
+/// 以连续 `Vec` 存放的条件树集合
+#[derive(Debug, Clone, Default)]
+pub struct ConditionArena {
+    nodes: Vec<ConditionNode>,
+}
+
+impl ConditionArena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 预先分配可容纳 `capacity` 个节点的空间
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self { nodes: Vec::with_capacity(capacity) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// 移除全部节点并保留已分配的容量，之前返回的下标全部失效
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+    }
+
+    /// 返回下标对应的节点
+    ///
+    /// # Panics
+    /// 下标不属于这个 arena 时 panic
+    pub fn get(&self, id: ConditionId) -> &ConditionNode {
+        &self.nodes[id.index()]
+    }
+
+    /// 将 `Box` 形式的条件树存入 arena，返回根节点的下标
+    ///
+    /// 子节点先于父节点存入，叶子节点中的值会被克隆
+    pub fn lower(&mut self, condition: &Condition) -> ConditionId {
+        let node = match condition {
+            Condition::And(left, right) => {
+                let (left, right) = (self.lower(left), self.lower(right));
+                ConditionNode::And(left, right)
+            }
+            Condition::Or(left, right) => {
+                let (left, right) = (self.lower(left), self.lower(right));
+                ConditionNode::Or(left, right)
+            }
+            Condition::Not(inner) => ConditionNode::Not(self.lower(inner)),
+            Condition::Grouped(inner) => ConditionNode::Grouped(self.lower(inner)),
+            leaf => ConditionNode::Leaf(leaf.clone()),
+        };
+        self.push(node)
+    }
+
+    /// 以 `id` 为根重新构建 `Box` 形式的条件树
+    pub fn lift(&self, id: ConditionId) -> Condition {
+        match self.get(id) {
+            ConditionNode::And(left, right) => {
+                Condition::And(Box::new(self.lift(*left)), Box::new(self.lift(*right)))
+            }
+            ConditionNode::Or(left, right) => {
+                Condition::Or(Box::new(self.lift(*left)), Box::new(self.lift(*right)))
+            }
+            ConditionNode::Not(inner) => Condition::Not(Box::new(self.lift(*inner))),
+            ConditionNode::Grouped(inner) => Condition::Grouped(Box::new(self.lift(*inner))),
+            ConditionNode::Leaf(condition) => condition.clone(),
+        }
+    }
+
+    fn push(&mut self, node: ConditionNode) -> ConditionId {
+        let id = u32::try_from(self.nodes.len()).expect("条件节点数量超过 u32 上限");
+        self.nodes.push(node);
+        ConditionId(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Query;
+
+    #[test]
+    fn test_lower_and_lift_round_trip() {
+        let query: Query = r#"Filter: title["Release Plan" AND ("Version 1" OR "Version 2")]; status[NOT ("Open" OR IS NULL)]"#
+            .parse()
+            .unwrap();
+        let mut arena = ConditionArena::new();
+
+        let roots: Vec<_> = query.base_filters.iter()
+            .map(|filter| arena.lower(&filter.condition))
+            .collect();
+        for (filter, root) in query.base_filters.iter().zip(&roots) {
+            assert_eq!(arena.lift(*root), filter.condition);
+        }
+
+        // 子节点先于父节点存入，根节点位于各自子树的末尾
+        assert!(matches!(arena.get(roots[0]), ConditionNode::And(left, right) if left.index() < right.index()));
+        assert_eq!(roots[1].index(), arena.len() - 1);
+    }
+
+    #[test]
+    fn test_clear_keeps_capacity() {
+        let query: Query = r#"Filter: a[1 OR 2 OR 3]"#.parse().unwrap();
+        let mut arena = ConditionArena::with_capacity(4);
+        arena.lower(&query.base_filters[0].condition);
+        let capacity = arena.nodes.capacity();
+
+        arena.clear();
+        assert!(arena.is_empty());
+        let root = arena.lower(&query.base_filters[0].condition);
+        assert_eq!(arena.nodes.capacity(), capacity);
+        assert_eq!(arena.lift(root), query.base_filters[0].condition);
+    }
+}
@@ -1,33 +1,766 @@
+use crate::token::Span;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
 /// AST 的根节点, 代表一个完整的查询语句
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
 pub struct Query {
     /// 针对主实体的过滤条件列表
     pub base_filters: Vec<FieldFilter>,
+    /// 针对主实体、组内以 OR 连接的过滤条件分组，例如 `(status["Open"] OR priority[>5])`
+    ///
+    /// 各分组之间以及分组与 `base_filters` 之间仍以 AND 连接
+    pub base_or_groups: Vec<Vec<FieldFilter>>,
     /// 针对关联实体的过滤条件列表
     pub cross_filters: Vec<CrossFilter>,
+    /// 以段落级 OR 连接的其他分支, 例如：`Filter: a[1]; b[2] OR CrossFilter: <Test-Run> c[3]`
+    ///
+    /// 段落之间的 `;` 优先级高于 `OR`，查询整体为 `(自身的段落) OR 分支1 OR 分支2 ...`，
+    /// 每个分支内部的段落仍以 AND 连接。分支不再嵌套，其 `or_branches`、`sample_percent` 与 `sort` 不使用
+    pub or_branches: Vec<Query>,
+    /// 采样百分比 (1-100), 例如：`Sample: 10%`
+    pub sample_percent: Option<u32>,
+    /// 排序键, 例如：`Sort: priority * 2 DESC, name`
+    pub sort: Vec<SortKey>,
+    /// 针对主实体、编译时按实体配置展开的过滤宏, 例如：`Filter: MINE_RECENTLY`
+    pub macros: Vec<FilterMacro>,
+    /// 每个分支内主实体的条件与关联Filter之间的连接方式，默认以 AND 连接
+    pub cross_combinator: CrossCombinator,
+}
+
+/// 主实体的条件与关联Filter之间的连接方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CrossCombinator {
+    #[default]
+    And,
+    /// 以 OR 连接，例如 "指派给我的，或者关联了失败 Run 的"，主实体的条件和全部关联Filter分别作为一组
+    Or,
+}
+
+impl Query {
+    /// 以 AND 语义合并两个查询，例如将用户保存的Filter与强制的租户范围Filter组合
+    ///
+    /// 两个查询的基础Filter与关联Filter分别拼接，完全相同的Filter只保留一份。
+    /// 两边对同一字段的不同条件会同时保留，编译后取交集 (`a AND b`)，不会互相覆盖。
+    /// 采样百分比和排序优先使用 `self` 的设置。
+    /// 任一边带有段落级 OR 分支时按分配律展开，例如 `(A OR B) AND C` 合并为 `(A AND C) OR (B AND C)`
+    pub fn and_merge(self, other: Query) -> Query {
+        let (this, other) = (self.expand_cross_combinator(), other.expand_cross_combinator());
+        if this.or_branches.is_empty() && other.or_branches.is_empty() {
+            return this.and_merge_branch(other);
+        }
+
+        let sample_percent = this.sample_percent.or(other.sample_percent);
+        let sort = if this.sort.is_empty() { other.sort.clone() } else { this.sort.clone() };
+        let right = other.into_branches();
+        let mut merged = Vec::new();
+        for left in this.into_branches() {
+            for right in &right {
+                let branch = left.clone().and_merge_branch(right.clone());
+                if !merged.contains(&branch) {
+                    merged.push(branch);
+                }
+            }
+        }
+
+        let mut branches = merged.into_iter();
+        let mut query = branches.next().unwrap_or_default();
+        query.or_branches = branches.collect();
+        query.sample_percent = sample_percent;
+        query.sort = sort;
+        query
+    }
+
+    /// 将 `cross_combinator` 为 OR 的查询改写为等价的段落级 OR 分支，改写后的查询只使用 AND 连接
+    ///
+    /// 每个分支拆成只含主实体条件和只含关联Filter的两个分支，例如
+    /// `(a AND b) OR CrossFilter` 中主实体的条件整体作为一组
+    pub fn expand_cross_combinator(self) -> Query {
+        if self.cross_combinator == CrossCombinator::And {
+            return self;
+        }
+
+        let sample_percent = self.sample_percent;
+        let sort = self.sort.clone();
+        let mut branches = Vec::new();
+        for mut branch in self.into_branches() {
+            branch.cross_combinator = CrossCombinator::And;
+            let cross_filters = std::mem::take(&mut branch.cross_filters);
+            if !branch.base_filters.is_empty() || !branch.base_or_groups.is_empty() || !branch.macros.is_empty() {
+                branches.push(branch);
+            }
+            if !cross_filters.is_empty() {
+                branches.push(Query { cross_filters, ..Default::default() });
+            }
+        }
+
+        let mut branches = branches.into_iter();
+        let mut query = branches.next().unwrap_or_default();
+        query.or_branches = branches.collect();
+        query.sample_percent = sample_percent;
+        query.sort = sort;
+        query
+    }
+
+    /// 拆分为以 OR 连接的各个分支，第一个分支为查询自身的段落
+    fn into_branches(mut self) -> Vec<Query> {
+        let or_branches = std::mem::take(&mut self.or_branches);
+        self.sample_percent = None;
+        self.sort.clear();
+        std::iter::once(self).chain(or_branches).collect()
+    }
+
+    /// 按顺序遍历以 OR 连接的各个分支，第一个分支为查询自身
+    pub fn branches(&self) -> impl Iterator<Item = &Query> {
+        std::iter::once(self).chain(self.or_branches.iter())
+    }
+
+    /// 合并两个不含 OR 分支的查询
+    fn and_merge_branch(mut self, other: Query) -> Query {
+        for filter in other.base_filters {
+            if !self.base_filters.contains(&filter) {
+                self.base_filters.push(filter);
+            }
+        }
+        for group in other.base_or_groups {
+            if !self.base_or_groups.contains(&group) {
+                self.base_or_groups.push(group);
+            }
+        }
+        for cross_filter in other.cross_filters {
+            if !self.cross_filters.contains(&cross_filter) {
+                self.cross_filters.push(cross_filter);
+            }
+        }
+        for filter_macro in other.macros {
+            if !self.macros.contains(&filter_macro) {
+                self.macros.push(filter_macro);
+            }
+        }
+        self.sample_percent = self.sample_percent.or(other.sample_percent);
+        if self.sort.is_empty() {
+            self.sort = other.sort;
+        }
+        self
+    }
+
+    /// 遍历全部字段Filter及其所属实体：基础Filter和 OR 分组归属于 `base_entity`，关联Filter归属于其目标实体
+    ///
+    /// 包括段落级 OR 分支中的Filter
+    pub fn filters_by_entity<'a>(&'a self, base_entity: &'a str) -> impl Iterator<Item = (&'a str, &'a FieldFilter)> + 'a {
+        self.branches().flat_map(move |branch| {
+            let base = branch.base_filters.iter()
+                .chain(branch.base_or_groups.iter().flatten())
+                .map(move |filter| (base_entity, filter));
+            let cross = branch.cross_filters.iter()
+                .flat_map(|cf| cf.filters.iter().map(move |filter| (cf.target_entity.0.as_str(), filter)));
+            base.chain(cross)
+        })
+    }
+
+    /// 遍历全部字段Filter (基础Filter、OR 分组、关联Filter，包括段落级 OR 分支)
+    pub fn field_filters(&self) -> impl Iterator<Item = &FieldFilter> {
+        self.branches().flat_map(|branch| {
+            branch.base_filters.iter()
+                .chain(branch.base_or_groups.iter().flatten())
+                .chain(branch.cross_filters.iter().flat_map(|cf| cf.filters.iter()))
+        })
+    }
+
+    /// 以可变引用遍历全部字段Filter，用于原地改写条件
+    pub fn field_filters_mut(&mut self) -> impl Iterator<Item = &mut FieldFilter> {
+        let branches = self.or_branches.iter_mut().flat_map(|branch| {
+            branch.base_filters.iter_mut()
+                .chain(branch.base_or_groups.iter_mut().flatten())
+                .chain(branch.cross_filters.iter_mut().flat_map(|cf| cf.filters.iter_mut()))
+        });
+        self.base_filters.iter_mut()
+            .chain(self.base_or_groups.iter_mut().flatten())
+            .chain(self.cross_filters.iter_mut().flat_map(|cf| cf.filters.iter_mut()))
+            .chain(branches)
+    }
+
+    /// 返回查询引用的全部 `(实体, 字段)`，包括 `@column` 引用的列、排序键中的字段和嵌套子查询中的字段，
+    /// 按首次出现的顺序去重
+    ///
+    /// 过滤宏展开的字段由编译器按实体配置，这里不包括；需要时使用 [`Query::referenced_fields_with`]
+    /// 或 `SqlCompiler::referenced_fields`
+    pub fn referenced_fields(&self, base_entity: &str) -> Vec<(String, String)> {
+        self.referenced_fields_with(base_entity, &|_, _| Vec::new())
+    }
+
+    /// 同 [`Query::referenced_fields`]，并包括 `expand_macro(宏, 实体)` 展开得到的Filter引用的字段
+    ///
+    /// 子查询中的过滤宏按子查询的实体展开
+    pub fn referenced_fields_with(
+        &self,
+        base_entity: &str,
+        expand_macro: &dyn Fn(FilterMacro, &str) -> Vec<FieldFilter>,
+    ) -> Vec<(String, String)> {
+        let mut fields = Vec::new();
+        let mut add = |entity: &str, field: &Identifier| {
+            let reference = (entity.to_string(), field.0.clone());
+            if !fields.contains(&reference) {
+                fields.push(reference);
+            }
+        };
+
+        let macro_filters: Vec<FieldFilter> = self.branches()
+            .flat_map(|branch| &branch.macros)
+            .flat_map(|filter_macro| expand_macro(*filter_macro, base_entity))
+            .collect();
+        let filters = self.filters_by_entity(base_entity)
+            .chain(macro_filters.iter().map(|filter| (base_entity, filter)));
+        for (entity, filter) in filters {
+            for column in filter.columns().into_iter().chain(filter.condition.column_references()) {
+                add(entity, column);
+            }
+            for (sub_entity, sub_query) in filter.condition.subqueries() {
+                for (entity, field) in sub_query.referenced_fields_with(&sub_entity.0, expand_macro) {
+                    add(&entity, &Identifier(field));
+                }
+            }
+        }
+        for key in &self.sort {
+            for field in key.expr.fields() {
+                add(base_entity, field);
+            }
+        }
+        fields
+    }
+
+    /// 返回查询涉及的全部实体 (包括关联链上的中间实体与子查询实体)，按首次出现的顺序去重
+    pub fn referenced_entities(&self, base_entity: &str) -> Vec<String> {
+        let mut entities = vec![base_entity.to_string()];
+        let mut add = |entity: &str| {
+            if !entities.iter().any(|e| e == entity) {
+                entities.push(entity.to_string());
+            }
+        };
+
+        for cross_filter in self.branches().flat_map(|branch| &branch.cross_filters) {
+            let path = std::iter::once(&cross_filter.source_entity).chain(cross_filter.join_path());
+            for entity in path {
+                add(&entity.0);
+            }
+        }
+        for (_, filter) in self.filters_by_entity(base_entity) {
+            for (sub_entity, sub_query) in filter.condition.subqueries() {
+                for entity in sub_query.referenced_entities(&sub_entity.0) {
+                    add(&entity);
+                }
+            }
+        }
+        entities
+    }
+
+    /// 统计查询中全部字面量的数量，包括 OR 分组、关联Filter、段落级 OR 分支和嵌套子查询
+    ///
+    /// 用于在编译前检查驱动的参数数量上限 (PostgreSQL 为 65535)。日期关键字、`@column` 等字面量
+    /// 编译为 SQL 表达式而不是绑定参数，因此结果是参数数量的上界
+    pub fn value_count(&self) -> usize {
+        self.field_filters().map(|filter| filter.condition.value_count()).sum()
+    }
+
+    /// 按出现顺序列出查询中的全部字面量，包括 OR 分组、关联Filter、段落级 OR 分支和嵌套子查询
+    ///
+    /// `LIKE` 的模式不是 [`Literal`]，不在结果中
+    pub fn literals(&self) -> Vec<&Literal> {
+        let mut literals = Vec::new();
+        for filter in self.field_filters() {
+            filter.condition.collect_literals(&mut literals);
+        }
+        literals
+    }
+
+    /// 将字符串、数字字面量和 `LIKE` 模式替换为占位值，保留查询结构，用于记录日志时隐藏用户数据
+    ///
+    /// 字符串替换为 [`REDACTED_STRING`]，数字替换为 0；日期关键字、`current_user`、`@column` 不包含用户数据，保持不变
+    pub fn redact(&mut self) {
+        struct Redactor;
+
+        impl QueryRewriter for Redactor {
+            fn rewrite_literal(&mut self, literal: &mut Literal) {
+                match literal {
+                    Literal::String(s) => *s = REDACTED_STRING.to_string(),
+                    Literal::Number(n) => *n = 0,
+                    _ => {}
+                }
+            }
+
+            fn rewrite_condition(&mut self, condition: &mut Condition) {
+                if let Condition::Like { pattern, .. } = condition {
+                    *pattern = REDACTED_STRING.to_string();
+                }
+            }
+        }
+
+        self.rewrite(&mut Redactor);
+    }
+
+    /// 拆分为互相独立的查询，便于并行执行后在应用侧对主实体的 id 取交集
+    ///
+    /// 第一个查询只包含主实体的基础Filter、OR 分组和过滤宏，返回主实体的行；之后按 `cross_filters` 的顺序，
+    /// 每个关联Filter拆成一个以其目标实体为主实体的查询，返回的是目标实体的行。
+    /// 取交集前，调用方需要沿 [`DecomposedCrossFilter::path`] 将目标实体的行逐跳映射回主实体的 id
+    /// (与编译器 JOIN 时使用的关联键相同)，不能直接用目标实体的 id 与主实体的 id 求交。
+    ///
+    /// 采样和排序不会保留。带段落级 OR 分支或 `cross_combinator` 为 OR 的查询无法用交集表示，返回 `None`
+    pub fn decompose(&self) -> Option<(Query, Vec<DecomposedCrossFilter>)> {
+        if !self.or_branches.is_empty() || self.cross_combinator == CrossCombinator::Or {
+            return None;
+        }
+
+        let base = Query {
+            base_filters: self.base_filters.clone(),
+            base_or_groups: self.base_or_groups.clone(),
+            macros: self.macros.clone(),
+            ..Default::default()
+        };
+        let cross = self.cross_filters.iter()
+            .map(|cross_filter| DecomposedCrossFilter {
+                entity: cross_filter.target_entity.clone(),
+                path: std::iter::once(&cross_filter.source_entity)
+                    .chain(cross_filter.join_path())
+                    .cloned()
+                    .collect(),
+                query: Query {
+                    base_filters: cross_filter.filters.clone(),
+                    ..Default::default()
+                },
+            })
+            .collect();
+        Some((base, cross))
+    }
+
+    /// 比较两个查询的谓词，列出只在 `self` 中和只在 `other` 中出现的字段Filter
+    ///
+    /// 字段Filter按结构比较 (忽略源码位置)，并区分其作用范围：主实体上的Filter与关联Filter中同样写法的Filter不相等。
+    /// 用于审计新保存的Filter相对旧Filter增加或去掉了哪些条件
+    pub fn predicate_diff(&self, other: &Query) -> PredicateDiff {
+        let ours = self.scoped_filters();
+        let theirs = other.scoped_filters();
+        let only_in = |left: &[(FilterScope, &FieldFilter)], right: &[(FilterScope, &FieldFilter)]| {
+            left.iter()
+                .filter(|filter| !right.contains(filter))
+                .map(|(scope, filter)| (scope.clone(), (*filter).clone()))
+                .collect()
+        };
+
+        PredicateDiff {
+            only_in_self: only_in(&ours, &theirs),
+            only_in_other: only_in(&theirs, &ours),
+        }
+    }
+
+    /// 遍历全部字段Filter及其作用范围，包括段落级 OR 分支
+    fn scoped_filters(&self) -> Vec<(FilterScope, &FieldFilter)> {
+        self.branches()
+            .flat_map(|branch| {
+                let base = branch.base_filters.iter()
+                    .chain(branch.base_or_groups.iter().flatten())
+                    .map(|filter| (FilterScope::Base, filter));
+                let cross = branch.cross_filters.iter().flat_map(|cf| {
+                    cf.filters.iter().map(|filter| (FilterScope::Cross { entity: cf.target_entity.clone() }, filter))
+                });
+                base.chain(cross)
+            })
+            .collect()
+    }
+
+    /// 用 `rewriter` 改写查询中的全部实体名、字段名、条件和字面量，包括关联Filter与嵌套子查询
+    ///
+    /// 条件树按后序遍历：先改写子条件和字面量，再对父条件调用 [`QueryRewriter::rewrite_condition`]
+    pub fn rewrite(&mut self, rewriter: &mut impl QueryRewriter) {
+        for filter in self.base_filters.iter_mut().chain(self.base_or_groups.iter_mut().flatten()) {
+            filter.rewrite(rewriter);
+        }
+        for cross_filter in &mut self.cross_filters {
+            rewriter.rewrite_entity(&mut cross_filter.source_entity);
+            for entity in &mut cross_filter.intermediate_entities {
+                rewriter.rewrite_entity(entity);
+            }
+            rewriter.rewrite_entity(&mut cross_filter.target_entity);
+            for filter in &mut cross_filter.filters {
+                filter.rewrite(rewriter);
+            }
+        }
+        for branch in &mut self.or_branches {
+            branch.rewrite(rewriter);
+        }
+        for key in &mut self.sort {
+            key.expr.rewrite(rewriter);
+        }
+    }
+
+    /// 输出查询的 S 表达式，例如 `status["Open"]; priority[>2]` 输出 `(and (eq status "Open") (gt priority 2))`
+    ///
+    /// 比 `{:?}` 紧凑且稳定，便于在测试中对解析结果做快照比较。字段名写入每个叶子条件，分组括号不单独输出，
+    /// 相邻的同类 AND/OR 合并为一个节点；关联Filter输出为 `(cross Test-Run ...)`，段落级 OR 分支以 `or` 连接。
+    /// 有采样或排序时整体输出为 `(select 条件 (sample 10) (sort (desc priority)))`
+    pub fn to_sexpr(&self) -> String {
+        let expanded = self.clone().expand_cross_combinator();
+        let condition = sexpr_list("or", expanded.branches().map(Query::branch_sexpr).collect());
+        if self.sample_percent.is_none() && self.sort.is_empty() {
+            return condition;
+        }
+
+        let mut parts = vec![condition];
+        if let Some(percent) = self.sample_percent {
+            parts.push(format!("(sample {})", percent));
+        }
+        if !self.sort.is_empty() {
+            let keys: Vec<String> = self.sort.iter()
+                .map(|key| format!("({} {})", if key.descending { "desc" } else { "asc" }, key.expr.sexpr()))
+                .collect();
+            parts.push(format!("(sort {})", keys.join(" ")));
+        }
+        format!("(select {})", parts.join(" "))
+    }
+
+    /// 单个分支内全部段落以 AND 连接的 S 表达式
+    fn branch_sexpr(&self) -> String {
+        let base = self.base_filters.iter().map(FieldFilter::sexpr);
+        let groups = self.base_or_groups.iter()
+            .map(|group| sexpr_list("or", group.iter().map(FieldFilter::sexpr).collect()));
+        let macros = self.macros.iter().map(|filter_macro| format!("(macro {})", filter_macro.keyword()));
+        let cross = self.cross_filters.iter().map(|cross_filter| {
+            let path: Vec<&str> = std::iter::once(&cross_filter.source_entity)
+                .chain(cross_filter.join_path())
+                .map(|entity| entity.0.as_str())
+                .collect();
+            let parts: Vec<String> = std::iter::once(path.join("-"))
+                .chain(cross_filter.filters.iter().map(FieldFilter::sexpr))
+                .collect();
+            format!("(cross {})", parts.join(" "))
+        });
+        sexpr_list("and", base.chain(groups).chain(macros).chain(cross).collect())
+    }
+
+    /// 输出查询条件树的 GraphViz DOT 图，用于调试解析结果
+    ///
+    /// 每个 Filter、`Condition` 和 `Literal` 都是一个节点，例如 `status[>5 OR <2]` 输出
+    /// `Filter status → OR → > → 5` 等节点和边，可以用 `dot -Tpng` 渲染
+    pub fn to_dot(&self) -> String {
+        let mut dot = DotWriter::default();
+        let root = dot.node("Query");
+        self.write_dot(&mut dot, root);
+        format!("digraph Query {{\n    node [shape=box];\n{}}}\n", dot.out)
+    }
+
+    fn write_dot(&self, dot: &mut DotWriter, parent: usize) {
+        for filter in &self.base_filters {
+            filter.write_dot(dot, parent);
+        }
+        for group in &self.base_or_groups {
+            let group_id = dot.child(parent, "OR group");
+            for filter in group {
+                filter.write_dot(dot, group_id);
+            }
+        }
+        for cross_filter in &self.cross_filters {
+            let path: Vec<&str> = std::iter::once(&cross_filter.source_entity)
+                .chain(cross_filter.join_path())
+                .map(|entity| entity.0.as_str())
+                .collect();
+            let cross_id = dot.child(parent, &format!("CrossFilter <{}>", path.join("-")));
+            for filter in &cross_filter.filters {
+                filter.write_dot(dot, cross_id);
+            }
+        }
+        for branch in &self.or_branches {
+            let branch_id = dot.child(parent, "OR branch");
+            branch.write_dot(dot, branch_id);
+        }
+        if let Some(percent) = self.sample_percent {
+            dot.child(parent, &format!("Sample {}%", percent));
+        }
+        for key in &self.sort {
+            dot.child(parent, &format!("Sort {}", key));
+        }
+    }
+}
+
+/// 排序键, 例如：`priority * 2 DESC`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SortKey {
+    pub expr: SortExpr,
+    /// 是否降序，默认升序
+    pub descending: bool,
+}
+
+/// 排序表达式，只支持字段、整数和四则运算, 例如：`priority * 2`、`(estimated - actual) / 2`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SortExpr {
+    /// 主实体的字段
+    Field(Identifier),
+    Number(i64),
+    Binary { op: ArithOp, left: Box<SortExpr>, right: Box<SortExpr> },
+}
+
+/// 排序表达式中的算术运算符
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArithOp {
+    Add, // +
+    Sub, // -
+    Mul, // *
+    Div, // /
+}
+
+impl ArithOp {
+    /// 运算符优先级，乘除高于加减
+    fn precedence(self) -> u8 {
+        match self {
+            ArithOp::Add | ArithOp::Sub => 1,
+            ArithOp::Mul | ArithOp::Div => 2,
+        }
+    }
+}
+
+impl SortExpr {
+    /// 排序表达式中引用的字段
+    pub fn fields(&self) -> Vec<&Identifier> {
+        match self {
+            SortExpr::Field(field) => vec![field],
+            SortExpr::Number(_) => Vec::new(),
+            SortExpr::Binary { left, right, .. } => {
+                let mut fields = left.fields();
+                fields.extend(right.fields());
+                fields
+            }
+        }
+    }
+
+    fn rewrite(&mut self, rewriter: &mut impl QueryRewriter) {
+        match self {
+            SortExpr::Field(field) => rewriter.rewrite_field(field),
+            SortExpr::Number(_) => {}
+            SortExpr::Binary { left, right, .. } => {
+                left.rewrite(rewriter);
+                right.rewrite(rewriter);
+            }
+        }
+    }
+
+    fn sexpr(&self) -> String {
+        match self {
+            SortExpr::Field(field) => sexpr_atom(&field.0),
+            SortExpr::Number(n) => n.to_string(),
+            SortExpr::Binary { op, left, right } => format!("({} {} {})", op, left.sexpr(), right.sexpr()),
+        }
+    }
+
+    /// 作为 `parent` 运算的操作数时是否需要括号，`is_right` 表示右操作数
+    fn needs_parens(&self, parent: ArithOp, is_right: bool) -> bool {
+        match self {
+            SortExpr::Binary { op, .. } => {
+                op.precedence() < parent.precedence()
+                    || (is_right && op.precedence() == parent.precedence() && matches!(parent, ArithOp::Sub | ArithOp::Div))
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for ArithOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            ArithOp::Add => "+",
+            ArithOp::Sub => "-",
+            ArithOp::Mul => "*",
+            ArithOp::Div => "/",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+/// 以 DSL 中的写法输出排序表达式，只在需要时加括号，例如 `(a + b) * 2`
+impl fmt::Display for SortExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SortExpr::Field(field) => write!(f, "{}", field.0),
+            SortExpr::Number(n) => write!(f, "{}", n),
+            SortExpr::Binary { op, left, right } => {
+                let operand = |expr: &SortExpr, is_right| {
+                    if expr.needs_parens(*op, is_right) {
+                        format!("({})", expr)
+                    } else {
+                        expr.to_string()
+                    }
+                };
+                write!(f, "{} {} {}", operand(left, false), op, operand(right, true))
+            }
+        }
+    }
+}
+
+impl fmt::Display for SortKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.expr, if self.descending { " DESC" } else { "" })
+    }
+}
+
+/// 字段Filter的作用范围
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FilterScope {
+    /// 主实体上的基础Filter或 OR 分组
+    Base,
+    /// 关联Filter，`entity` 为关联链末端的目标实体
+    Cross { entity: Identifier },
+}
+
+/// [`Query::decompose`] 从一个关联Filter拆出的独立查询
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecomposedCrossFilter {
+    /// `query` 的主实体，即关联Filter的目标实体
+    pub entity: Identifier,
+    /// 从原查询的主实体到 `entity` 依次经过的实体，例如 `<Test-Run-Step>` 为 `[Test, Run, Step]`
+    pub path: Vec<Identifier>,
+    /// 只包含目标实体条件的查询，应以 `entity` 为主实体编译
+    pub query: Query,
+}
+
+/// 两个查询谓词之间的差异，见 [`Query::predicate_diff`]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PredicateDiff {
+    /// 只在 `self` 中出现的字段Filter
+    pub only_in_self: Vec<(FilterScope, FieldFilter)>,
+    /// 只在 `other` 中出现的字段Filter
+    pub only_in_other: Vec<(FilterScope, FieldFilter)>,
+}
+
+impl PredicateDiff {
+    /// 两个查询的谓词是否相同
+    pub fn is_empty(&self) -> bool {
+        self.only_in_self.is_empty() && self.only_in_other.is_empty()
+    }
+}
+
+/// [`Query::redact`] 替换字符串字面量使用的占位值
+pub const REDACTED_STRING: &str = "***";
+
+/// 查询 AST 的改写器，配合 [`Query::rewrite`] 在编译前进行自定义改写，例如脱敏字段或重命名实体
+///
+/// 所有方法默认不做任何修改，实现者只需覆盖关心的部分
+pub trait QueryRewriter {
+    /// 改写实体名：关联链上的实体以及子查询的实体
+    fn rewrite_entity(&mut self, _entity: &mut Identifier) {}
+
+    /// 改写字段名：字段Filter的字段、元组字段以及 `@column` 列引用
+    fn rewrite_field(&mut self, _field: &mut Identifier) {}
+
+    /// 改写字面量值
+    fn rewrite_literal(&mut self, _literal: &mut Literal) {}
+
+    /// 改写条件节点，调用时其子条件已经改写完毕
+    fn rewrite_condition(&mut self, _condition: &mut Condition) {}
+}
+
+/// 构建 DOT 图的节点与边，节点按创建顺序编号为 `n0, n1, ...`
+#[derive(Default)]
+struct DotWriter {
+    out: String,
+    next_id: usize,
+}
+
+impl DotWriter {
+    fn node(&mut self, label: &str) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        let label = label.replace('\\', "\\\\").replace('"', "\\\"");
+        self.out.push_str(&format!("    n{} [label=\"{}\"];\n", id, label));
+        id
+    }
+
+    fn child(&mut self, parent: usize, label: &str) -> usize {
+        let id = self.node(label);
+        self.out.push_str(&format!("    n{} -> n{};\n", parent, id));
+        id
+    }
 }
 
 /// 代表一个关联实体Filter, 例如：`CrossFilter: <Test-Run>...`
-#[derive(Debug, Clone, PartialEq)]
+///
+/// 多跳关联链写作 `<Test-Run-Step>`，此时 `Run` 为中间实体
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct CrossFilter {
     pub source_entity: Identifier,
+    /// 源实体与目标实体之间依次经过的中间实体
+    pub intermediate_entities: Vec<Identifier>,
     pub target_entity: Identifier,
     /// 应用于目标实体的过滤条件列表
     pub filters: Vec<FieldFilter>,
 }
 
+impl CrossFilter {
+    /// 按关联顺序返回需要 JOIN 的实体 (中间实体在前，目标实体在最后)
+    pub fn join_path(&self) -> impl Iterator<Item = &Identifier> {
+        self.intermediate_entities.iter().chain(std::iter::once(&self.target_entity))
+    }
+}
+
 /// 代表对单个字段的一个或多个过滤条件, 例如：`status[NOT "Open"]`
-#[derive(Debug, Clone, PartialEq)]
+///
+/// `span` 只是源码位置信息，不参与相等比较和哈希
+#[derive(Debug, Clone)]
 pub struct FieldFilter {
     pub field: Identifier,
     pub condition: Condition,
+    /// 该过滤条件在源文本中的位置，手动构造的 AST 可以为 `None`
+    pub span: Option<Span>,
+}
+
+impl FieldFilter {
+    /// 返回该过滤条件实际引用的字段，元组条件返回元组中的全部字段
+    pub fn columns(&self) -> Vec<&Identifier> {
+        match &self.condition {
+            Condition::InTuple { fields, .. } => fields.iter().collect(),
+            _ => vec![&self.field],
+        }
+    }
+}
+
+impl PartialEq for FieldFilter {
+    fn eq(&self, other: &Self) -> bool {
+        self.field == other.field && self.condition == other.condition
+    }
+}
+
+impl Eq for FieldFilter {}
+
+impl Hash for FieldFilter {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.field.hash(state);
+        self.condition.hash(state);
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Identifier(pub String);
 
+/// 编译时展开为若干字段Filter的宏关键字
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FilterMacro {
+    /// `MINE_RECENTLY`：指派给当前用户且最近修改过，展开使用的字段和天数由编译器按实体配置
+    MineRecently,
+}
+
+impl FilterMacro {
+    /// 按关键字查找宏，不区分大小写
+    pub fn from_keyword(keyword: &str) -> Option<Self> {
+        keyword.eq_ignore_ascii_case("MINE_RECENTLY").then_some(FilterMacro::MineRecently)
+    }
+
+    /// DSL 中的关键字写法
+    pub fn keyword(self) -> &'static str {
+        match self {
+            FilterMacro::MineRecently => "MINE_RECENTLY",
+        }
+    }
+}
+
 /// 代表应用于单个字段的条件表达式树
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Condition {
     /// 逻辑与运算 (AND)
     And(Box<Condition>, Box<Condition>),
@@ -41,13 +774,353 @@ pub enum Condition {
     Comparison { op: CompOp, value: Literal },
     /// IN (...) 包含检查
     In(Vec<Literal>),
+    /// 子查询 IN 检查, 例如：`project_id[IN (CrossFilter: <Project> status["Active"])]`
+    ///
+    /// 编译为 `project_id IN (SELECT id FROM projects WHERE ...)`，`query` 的基础Filter作用于 `entity`
+    InSubquery { entity: Identifier, query: Box<Query> },
+    /// 元组 IN 检查, 例如：`(a, b)[IN ((1, "x"), (2, "y"))]`
+    ///
+    /// 此时所属 `FieldFilter` 的 `field` 为 `(a, b)` 形式的展示名称，实际字段见 `fields`
+    InTuple { fields: Vec<Identifier>, rows: Vec<Vec<Literal>> },
+    /// 与列表中任意一个值比较成立, 例如：`tags[= ANY ("a", "b")]`，仅支持 PostgreSQL
+    AnyOf { op: CompOp, values: Vec<Literal> },
+    /// 与列表中全部值比较都成立, 例如：`score[> ALL (1, 2)]`，仅支持 PostgreSQL
+    AllOf { op: CompOp, values: Vec<Literal> },
+    /// 闭区间范围检查, 例如：`age[BETWEEN 18 AND 65]`，`negated` 为 true 时表示 `NOT BETWEEN`
+    Between { low: Literal, high: Literal, negated: bool },
+    /// 模式匹配, 例如：`name[LIKE "bug%"]`，`negated` 为 true 时表示 `NOT LIKE`
+    ///
+    /// 模式中的 `\%`、`\_` 表示字面的 `%`、`_`，编译时附加 `ESCAPE '\'`
+    Like { pattern: String, negated: bool },
     /// 空值检查
     IsNull,
     IsNotNull,
+    /// 空字符串或 NULL, 例如：`note[IS EMPTY]`
+    IsEmpty,
+    /// 既不是空字符串也不是 NULL, 例如：`note[IS NOT EMPTY]`
+    IsNotEmpty,
+}
+
+impl Condition {
+    /// 返回条件中以 `@column` 引用的同一实体的列，不包括嵌套子查询中的引用
+    pub fn column_references(&self) -> Vec<&Identifier> {
+        fn column(literal: &Literal) -> Option<&Identifier> {
+            match literal {
+                Literal::Column(column) => Some(column),
+                _ => None,
+            }
+        }
+        match self {
+            Condition::And(left, right) | Condition::Or(left, right) => {
+                let mut columns = left.column_references();
+                columns.extend(right.column_references());
+                columns
+            }
+            Condition::Not(inner) | Condition::Grouped(inner) => inner.column_references(),
+            Condition::Comparison { value, .. } => column(value).into_iter().collect(),
+            Condition::In(values) | Condition::AnyOf { values, .. } | Condition::AllOf { values, .. } => {
+                values.iter().filter_map(column).collect()
+            }
+            Condition::InTuple { rows, .. } => rows.iter().flatten().filter_map(column).collect(),
+            Condition::Between { low, high, .. } => [low, high].into_iter().filter_map(column).collect(),
+            Condition::InSubquery { .. } | Condition::Like { .. } => Vec::new(),
+            Condition::IsNull | Condition::IsNotNull | Condition::IsEmpty | Condition::IsNotEmpty => Vec::new(),
+        }
+    }
+
+    /// 返回条件树中嵌套的全部子查询 `(实体, 查询)`
+    pub fn subqueries(&self) -> Vec<(&Identifier, &Query)> {
+        match self {
+            Condition::And(left, right) | Condition::Or(left, right) => {
+                let mut subqueries = left.subqueries();
+                subqueries.extend(right.subqueries());
+                subqueries
+            }
+            Condition::Not(inner) | Condition::Grouped(inner) => inner.subqueries(),
+            Condition::InSubquery { entity, query } => vec![(entity, query.as_ref())],
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl FieldFilter {
+    fn rewrite(&mut self, rewriter: &mut impl QueryRewriter) {
+        rewriter.rewrite_field(&mut self.field);
+        self.condition.rewrite(rewriter);
+    }
+
+    fn sexpr(&self) -> String {
+        self.condition.sexpr(&sexpr_atom(&self.field.0))
+    }
+
+    fn write_dot(&self, dot: &mut DotWriter, parent: usize) {
+        let id = dot.child(parent, &format!("Filter {}", self.field.0));
+        self.condition.write_dot(dot, id);
+    }
+}
+
+/// 以 DSL 中的写法输出字段Filter，例如 `status["Open"]`
+impl fmt::Display for FieldFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]", self.field.0, self.condition)
+    }
+}
+
+impl Condition {
+    fn value_count(&self) -> usize {
+        match self {
+            Condition::And(left, right) | Condition::Or(left, right) => left.value_count() + right.value_count(),
+            Condition::Not(inner) | Condition::Grouped(inner) => inner.value_count(),
+            Condition::Comparison { .. } => 1,
+            Condition::In(values) | Condition::AnyOf { values, .. } | Condition::AllOf { values, .. } => values.len(),
+            Condition::InSubquery { query, .. } => query.value_count(),
+            Condition::InTuple { rows, .. } => rows.iter().map(Vec::len).sum(),
+            Condition::Between { .. } => 2,
+            Condition::Like { .. } => 1,
+            Condition::IsNull | Condition::IsNotNull | Condition::IsEmpty | Condition::IsNotEmpty => 0,
+        }
+    }
+
+    /// 输出以 `field` 为字段的 S 表达式，见 [`Query::to_sexpr`]
+    fn sexpr(&self, field: &str) -> String {
+        let values = |values: &[Literal]| values.iter().map(Literal::sexpr).collect::<Vec<_>>().join(" ");
+        match self {
+            Condition::And(..) | Condition::Or(..) => {
+                let is_and = matches!(self, Condition::And(..));
+                let mut operands = Vec::new();
+                self.flatten_operands(is_and, &mut operands);
+                let operands: Vec<String> = operands.iter().map(|operand| operand.sexpr(field)).collect();
+                format!("({} {})", if is_and { "and" } else { "or" }, operands.join(" "))
+            }
+            Condition::Not(inner) => format!("(not {})", inner.sexpr(field)),
+            Condition::Grouped(inner) => inner.sexpr(field),
+            Condition::Comparison { op, value } => format!("({} {} {})", op.sexpr_name(), field, value.sexpr()),
+            Condition::In(list) => format!("(in {} {})", field, values(list)),
+            Condition::InSubquery { entity, query } => {
+                format!("(in {} (subquery {} {}))", field, sexpr_atom(&entity.0), query.to_sexpr())
+            }
+            Condition::InTuple { fields, rows } => {
+                let fields: Vec<String> = fields.iter().map(|field| sexpr_atom(&field.0)).collect();
+                let rows: Vec<String> = rows.iter().map(|row| format!("({})", values(row))).collect();
+                format!("(in ({}) {})", fields.join(" "), rows.join(" "))
+            }
+            Condition::AnyOf { op, values: list } => format!("(any {} {} {})", op.sexpr_name(), field, values(list)),
+            Condition::AllOf { op, values: list } => format!("(all {} {} {})", op.sexpr_name(), field, values(list)),
+            Condition::Between { low, high, negated } => format!(
+                "({} {} {} {})",
+                if *negated { "not-between" } else { "between" }, field, low.sexpr(), high.sexpr()
+            ),
+            Condition::Like { pattern, negated } => {
+                format!("({} {} {:?})", if *negated { "not-like" } else { "like" }, field, pattern)
+            }
+            Condition::IsNull => format!("(is-null {})", field),
+            Condition::IsNotNull => format!("(is-not-null {})", field),
+            Condition::IsEmpty => format!("(is-empty {})", field),
+            Condition::IsNotEmpty => format!("(is-not-empty {})", field),
+        }
+    }
+
+    /// 收集相邻的同类 AND (`is_and`) 或 OR 节点的操作数，跳过分组括号
+    fn flatten_operands<'a>(&'a self, is_and: bool, operands: &mut Vec<&'a Condition>) {
+        match self {
+            Condition::And(left, right) if is_and => {
+                left.flatten_operands(is_and, operands);
+                right.flatten_operands(is_and, operands);
+            }
+            Condition::Or(left, right) if !is_and => {
+                left.flatten_operands(is_and, operands);
+                right.flatten_operands(is_and, operands);
+            }
+            Condition::Grouped(inner) if matches!(**inner, Condition::And(..) | Condition::Or(..)) => {
+                inner.flatten_operands(is_and, operands)
+            }
+            other => operands.push(other),
+        }
+    }
+
+    fn collect_literals<'a>(&'a self, literals: &mut Vec<&'a Literal>) {
+        match self {
+            Condition::And(left, right) | Condition::Or(left, right) => {
+                left.collect_literals(literals);
+                right.collect_literals(literals);
+            }
+            Condition::Not(inner) | Condition::Grouped(inner) => inner.collect_literals(literals),
+            Condition::Comparison { value, .. } => literals.push(value),
+            Condition::In(values) | Condition::AnyOf { values, .. } | Condition::AllOf { values, .. } => {
+                literals.extend(values);
+            }
+            Condition::InSubquery { query, .. } => literals.extend(query.literals()),
+            Condition::InTuple { rows, .. } => literals.extend(rows.iter().flatten()),
+            Condition::Between { low, high, .. } => literals.extend([low, high]),
+            Condition::Like { .. } => {}
+            Condition::IsNull | Condition::IsNotNull | Condition::IsEmpty | Condition::IsNotEmpty => {}
+        }
+    }
+
+    fn rewrite(&mut self, rewriter: &mut impl QueryRewriter) {
+        match self {
+            Condition::And(left, right) | Condition::Or(left, right) => {
+                left.rewrite(rewriter);
+                right.rewrite(rewriter);
+            }
+            Condition::Not(inner) | Condition::Grouped(inner) => inner.rewrite(rewriter),
+            Condition::Comparison { value, .. } => Self::rewrite_literal(value, rewriter),
+            Condition::In(values) | Condition::AnyOf { values, .. } | Condition::AllOf { values, .. } => {
+                for value in values {
+                    Self::rewrite_literal(value, rewriter);
+                }
+            }
+            Condition::InSubquery { entity, query } => {
+                rewriter.rewrite_entity(entity);
+                query.rewrite(rewriter);
+            }
+            Condition::InTuple { fields, rows } => {
+                for field in fields {
+                    rewriter.rewrite_field(field);
+                }
+                for value in rows.iter_mut().flatten() {
+                    Self::rewrite_literal(value, rewriter);
+                }
+            }
+            Condition::Between { low, high, .. } => {
+                Self::rewrite_literal(low, rewriter);
+                Self::rewrite_literal(high, rewriter);
+            }
+            Condition::Like { .. } => {}
+            Condition::IsNull | Condition::IsNotNull | Condition::IsEmpty | Condition::IsNotEmpty => {}
+        }
+        rewriter.rewrite_condition(self);
+    }
+
+    /// `@column` 列引用同时是字段名，先作为字段改写再作为字面量改写
+    fn rewrite_literal(literal: &mut Literal, rewriter: &mut impl QueryRewriter) {
+        if let Literal::Column(column) = literal {
+            rewriter.rewrite_field(column);
+        }
+        rewriter.rewrite_literal(literal);
+    }
+
+    fn write_dot(&self, dot: &mut DotWriter, parent: usize) {
+        match self {
+            Condition::And(left, right) | Condition::Or(left, right) => {
+                let label = if matches!(self, Condition::And(..)) { "AND" } else { "OR" };
+                let id = dot.child(parent, label);
+                left.write_dot(dot, id);
+                right.write_dot(dot, id);
+            }
+            Condition::Not(inner) => {
+                let id = dot.child(parent, "NOT");
+                inner.write_dot(dot, id);
+            }
+            Condition::Grouped(inner) => {
+                let id = dot.child(parent, "( )");
+                inner.write_dot(dot, id);
+            }
+            Condition::Comparison { op, value } => {
+                let id = dot.child(parent, &op.to_string());
+                dot.child(id, &value.to_string());
+            }
+            Condition::In(values) => {
+                let id = dot.child(parent, "IN");
+                for value in values {
+                    dot.child(id, &value.to_string());
+                }
+            }
+            Condition::AnyOf { op, values } | Condition::AllOf { op, values } => {
+                let quantifier = if matches!(self, Condition::AnyOf { .. }) { "ANY" } else { "ALL" };
+                let id = dot.child(parent, &format!("{} {}", op, quantifier));
+                for value in values {
+                    dot.child(id, &value.to_string());
+                }
+            }
+            Condition::InSubquery { entity, query } => {
+                let id = dot.child(parent, &format!("IN <{}>", entity.0));
+                query.write_dot(dot, id);
+            }
+            Condition::InTuple { fields, rows } => {
+                let fields: Vec<&str> = fields.iter().map(|field| field.0.as_str()).collect();
+                let id = dot.child(parent, &format!("IN ({})", fields.join(", ")));
+                for row in rows {
+                    let values: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+                    dot.child(id, &format!("({})", values.join(", ")));
+                }
+            }
+            Condition::Between { low, high, negated } => {
+                let id = dot.child(parent, if *negated { "NOT BETWEEN" } else { "BETWEEN" });
+                dot.child(id, &low.to_string());
+                dot.child(id, &high.to_string());
+            }
+            Condition::Like { pattern, negated } => {
+                let id = dot.child(parent, if *negated { "NOT LIKE" } else { "LIKE" });
+                dot.child(id, &Literal::String(pattern.clone()).to_string());
+            }
+            Condition::IsNull => {
+                dot.child(parent, "IS NULL");
+            }
+            Condition::IsNotNull => {
+                dot.child(parent, "IS NOT NULL");
+            }
+            Condition::IsEmpty => {
+                dot.child(parent, "IS EMPTY");
+            }
+            Condition::IsNotEmpty => {
+                dot.child(parent, "IS NOT EMPTY");
+            }
+        }
+    }
+}
+
+/// 以 DSL 中方括号内的写法输出条件，例如 `>5 AND <10`、`NOT ("Open" OR "Pending")`
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Condition::And(left, right) => write!(f, "{} AND {}", left, right),
+            Condition::Or(left, right) => write!(f, "{} OR {}", left, right),
+            Condition::Not(inner) => write!(f, "NOT {}", inner),
+            Condition::Grouped(inner) => write!(f, "({})", inner),
+            // 相等比较是默认运算符，省略 `=`
+            Condition::Comparison { op: CompOp::Eq, value } => write!(f, "{}", value),
+            Condition::Comparison { op, value } => write!(f, "{}{}", op, value),
+            Condition::In(values) => {
+                let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                write!(f, "IN ({})", values.join(", "))
+            }
+            Condition::AnyOf { op, values } | Condition::AllOf { op, values } => {
+                let quantifier = if matches!(self, Condition::AnyOf { .. }) { "ANY" } else { "ALL" };
+                let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                write!(f, "{} {} ({})", op, quantifier, values.join(", "))
+            }
+            Condition::InSubquery { entity, query } => {
+                let filters: Vec<String> = query.base_filters.iter().map(|f| f.to_string()).collect();
+                write!(f, "IN (CrossFilter: <{}> {})", entity.0, filters.join("; "))
+            }
+            Condition::InTuple { rows, .. } => {
+                let rows: Vec<String> = rows.iter()
+                    .map(|row| {
+                        let values: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+                        format!("({})", values.join(", "))
+                    })
+                    .collect();
+                write!(f, "IN ({})", rows.join(", "))
+            }
+            Condition::Between { low, high, negated } => {
+                let not = if *negated { "NOT " } else { "" };
+                write!(f, "{}BETWEEN {} AND {}", not, low, high)
+            }
+            Condition::Like { pattern, negated } => {
+                let not = if *negated { "NOT " } else { "" };
+                write!(f, "{}LIKE {}", not, Literal::String(pattern.clone()))
+            }
+            Condition::IsNull => write!(f, "IS NULL"),
+            Condition::IsNotNull => write!(f, "IS NOT NULL"),
+            Condition::IsEmpty => write!(f, "IS EMPTY"),
+            Condition::IsNotEmpty => write!(f, "IS NOT EMPTY"),
+        }
+    }
 }
 
 /// 比较运算符
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum CompOp {
     Eq,      // =
     NotEq,   // !=
@@ -55,13 +1128,303 @@ pub enum CompOp {
     Lt,      // <
     Gte,     // >=
     Lte,     // <=
+    /// NULL 安全的相等比较，两侧都为 NULL 时也视为相等
+    NullSafeEq, // <=>
+}
+
+impl CompOp {
+    /// 返回逻辑取反后的运算符，例如 `>` 取反为 `<=`；没有对应取反运算符时返回 `None`
+    pub fn negate(&self) -> Option<CompOp> {
+        match self {
+            CompOp::Eq => Some(CompOp::NotEq),
+            CompOp::NotEq => Some(CompOp::Eq),
+            CompOp::Gt => Some(CompOp::Lte),
+            CompOp::Lt => Some(CompOp::Gte),
+            CompOp::Gte => Some(CompOp::Lt),
+            CompOp::Lte => Some(CompOp::Gt),
+            CompOp::NullSafeEq => None,
+        }
+    }
+}
+
+impl CompOp {
+    /// S 表达式中的运算符名称
+    fn sexpr_name(&self) -> &'static str {
+        match self {
+            CompOp::Eq => "eq",
+            CompOp::NotEq => "ne",
+            CompOp::Gt => "gt",
+            CompOp::Lt => "lt",
+            CompOp::Gte => "gte",
+            CompOp::Lte => "lte",
+            CompOp::NullSafeEq => "null-safe-eq",
+        }
+    }
+}
+
+impl fmt::Display for CompOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            CompOp::Eq => "=",
+            CompOp::NotEq => "!=",
+            CompOp::Gt => ">",
+            CompOp::Lt => "<",
+            CompOp::Gte => ">=",
+            CompOp::Lte => "<=",
+            CompOp::NullSafeEq => "<=>",
+        };
+        f.write_str(symbol)
+    }
 }
 
 /// 字面量值
-#[derive(Debug, Clone, PartialEq)]
+///
+/// 字面量目前不包含浮点数，因此可以直接派生 `Eq`/`Hash`，编译缓存和 `HashSet<Literal>` 去重都依赖于此。
+/// 若引入 `f64` 变体，需要改为手动实现：哈希判别值加上 `f64::to_bits()` 的位模式，
+/// 相等比较同样按位模式进行。此时 `NaN` 与自身相等，而 `0.0` 与 `-0.0` 不相等，与 IEEE 754 语义不同
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Literal {
     String(String),
     Number(i64),
     Date(String), // 例如："2023-12-25" 或解析后的关键字如 "today"
+    /// 相对日期关键字偏移若干天，例如 `today-7` 为 `{ base: "today", offset_days: -7 }`
+    RelativeDate { base: String, offset_days: i64 },
     CurrentUser,
-} 
\ No newline at end of file
+    /// 对同一实体另一列的引用，例如 `actual[>@estimated]` 中的 `@estimated`
+    Column(Identifier),
+    /// IN 列表中的 `null`，例如 `status[IN ("Open", null)]`，编译为额外的 `IS NULL` 检查
+    Null,
+}
+
+impl Literal {
+    /// S 表达式中的写法，字符串按 Rust 字符串字面量转义，其余与 DSL 写法相同
+    fn sexpr(&self) -> String {
+        match self {
+            Literal::String(s) => format!("{:?}", s),
+            other => other.to_string(),
+        }
+    }
+}
+
+/// S 表达式中的名称，包含空白、括号等字符时像 DSL 一样以反引号包围
+fn sexpr_atom(name: &str) -> String {
+    if !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.')) {
+        name.to_string()
+    } else {
+        format!("`{}`", name)
+    }
+}
+
+/// 只有一项时直接返回该项，否则以 `op` 连接为一个列表
+fn sexpr_list(op: &str, mut items: Vec<String>) -> String {
+    if items.len() == 1 {
+        return items.remove(0);
+    }
+    items.insert(0, op.to_string());
+    format!("({})", items.join(" "))
+}
+
+/// 以 DSL 中的写法输出字面量，例如 `"Open"`、`42`、`today`
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::String(s) => write!(f, "\"{}\"", s),
+            Literal::Number(n) => write!(f, "{}", n),
+            Literal::Date(d) => write!(f, "{}", d),
+            Literal::RelativeDate { base, offset_days } => write!(f, "{}{:+}", base, offset_days),
+            Literal::CurrentUser => write!(f, "current_user"),
+            Literal::Column(column) => write!(f, "@{}", column.0),
+            Literal::Null => write!(f, "null"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashSet;
+
+    fn hash_of(literal: &Literal) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        literal.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_literal_hash_and_set_dedup() {
+        let a = Literal::String("Open".to_string());
+        let b = Literal::String("Open".to_string());
+        assert_eq!(a, b);
+        assert_eq!(hash_of(&a), hash_of(&b));
+
+        let set: HashSet<Literal> = vec![a, b, Literal::Number(1), Literal::String("1".to_string())]
+            .into_iter()
+            .collect();
+        assert_eq!(set.len(), 3);
+    }
+
+    #[test]
+    fn test_query_rewrite_renames_field_everywhere() {
+        struct RenameField;
+
+        impl QueryRewriter for RenameField {
+            fn rewrite_field(&mut self, field: &mut Identifier) {
+                if field.0 == "owner" {
+                    field.0 = "assignee".to_string();
+                }
+            }
+        }
+
+        let mut query: Query = r#"Filter: owner["alice"]; (owner[IS NULL] OR due[>@owner]); CrossFilter: <Test-Run> owner[IN (CrossFilter: <User> owner["bob"])]"#
+            .parse()
+            .unwrap();
+        query.rewrite(&mut RenameField);
+
+        let expected: Query = r#"Filter: assignee["alice"]; (assignee[IS NULL] OR due[>@assignee]); CrossFilter: <Test-Run> assignee[IN (CrossFilter: <User> assignee["bob"])]"#
+            .parse()
+            .unwrap();
+        assert_eq!(query, expected);
+    }
+
+    #[test]
+    fn test_query_value_count() {
+        let query: Query = r#"Filter: status["Open"]; id[IN (1, 2, 3, 4, 5, 6, 7, 8, 9, 10)]; CrossFilter: <Test-Run> duration[>60]"#
+            .parse()
+            .unwrap();
+        assert_eq!(query.value_count(), 12);
+
+        let empty: Query = "Filter: owner[IS NULL]".parse().unwrap();
+        assert_eq!(empty.value_count(), 0);
+    }
+
+    #[test]
+    fn test_query_literals_and_redact() {
+        let mut query: Query = r#"Filter: owner["alice"] OR Filter: owner["bob"]; (due[>today] OR id[BETWEEN 3 AND 7]); title[LIKE "secret%"]; CrossFilter: <Test-Run> run_id[IN (CrossFilter: <User> email["a@b.c"]; age[IN (30, 40)])]; (x, y)[IN ((1, "p"))]"#
+            .parse()
+            .unwrap();
+
+        let literals: Vec<String> = query.literals().iter().map(|literal| literal.to_string()).collect();
+        assert_eq!(literals, [r#""alice""#, r#""bob""#, "today", "3", "7", r#""a@b.c""#, "30", "40", "1", r#""p""#]);
+
+        let original = query.clone();
+        query.redact();
+        let expected: Query = r#"Filter: owner["***"] OR Filter: owner["***"]; (due[>today] OR id[BETWEEN 0 AND 0]); title[LIKE "***"]; CrossFilter: <Test-Run> run_id[IN (CrossFilter: <User> email["***"]; age[IN (0, 0)])]; (x, y)[IN ((0, "***"))]"#
+            .parse()
+            .unwrap();
+        assert_eq!(query, expected);
+        assert_eq!(query.literals().len(), original.literals().len());
+        assert!(query.literals().iter().all(|literal| !matches!(literal, Literal::String(s) if s != REDACTED_STRING)));
+    }
+
+    #[test]
+    fn test_query_to_sexpr() {
+        let query: Query = r#"Filter: status["Open"]; priority[>2 AND (<10 OR IS NULL)]; (owner[current_user] OR `Due Date`[<today-7]); CrossFilter: <Test-Run> result[IN ("FAIL", "ERROR")]; note[NOT LIKE "a\_b%"]"#
+            .parse()
+            .unwrap();
+        assert_eq!(
+            query.to_sexpr(),
+            r#"(and (eq status "Open") (and (gt priority 2) (or (lt priority 10) (is-null priority))) (or (eq owner current_user) (lt `Due Date` today-7)) (cross Test-Run (in result "FAIL" "ERROR") (not-like note "a\\_b%")))"#
+        );
+
+        let query: Query = r#"Filter: a[1] OR Filter: b[BETWEEN 1 AND 3]; Sample: 10%; Sort: priority * 2 DESC"#.parse().unwrap();
+        assert_eq!(query.to_sexpr(), "(select (or (eq a 1) (between b 1 3)) (sample 10) (sort (desc (* priority 2))))");
+        assert_eq!(Query::default().to_sexpr(), "(and)");
+    }
+
+    #[test]
+    fn test_predicate_diff() {
+        let old: Query = r#"Filter: status["Open"]; CrossFilter: <Test-Run> result["FAIL"]"#.parse().unwrap();
+        let new: Query = r#"Filter: status["Open"]; priority[>3]; CrossFilter: <Test-Run> result["FAIL"]"#.parse().unwrap();
+
+        let diff = new.predicate_diff(&old);
+        let added: Query = "Filter: priority[>3]".parse().unwrap();
+        assert_eq!(diff.only_in_self, vec![(FilterScope::Base, added.base_filters[0].clone())]);
+        assert!(diff.only_in_other.is_empty());
+
+        let reverse = old.predicate_diff(&new);
+        assert_eq!(reverse.only_in_other, diff.only_in_self);
+        assert!(old.predicate_diff(&old).is_empty());
+
+        // 同样的写法作用于不同实体时视为不同的Filter
+        let cross: Query = r#"CrossFilter: <Test-Run> status["Open"]"#.parse().unwrap();
+        let diff = cross.predicate_diff(&old);
+        assert_eq!(diff.only_in_self.len(), 1);
+        assert_eq!(diff.only_in_self[0].0, FilterScope::Cross { entity: Identifier("Run".to_string()) });
+        assert_eq!(diff.only_in_other.len(), 2);
+    }
+
+    #[test]
+    fn test_decompose() {
+        let query: Query = r#"Filter: status["Open"]; (owner["bob"] OR priority[>3]); CrossFilter: <Test-Run> result["FAIL"]; CrossFilter: <Test-Run-Step> name["setup"]; duration[>60]; Sort: priority"#
+            .parse()
+            .unwrap();
+
+        let (base, cross) = query.decompose().unwrap();
+        let expected: Query = r#"Filter: status["Open"]; (owner["bob"] OR priority[>3])"#.parse().unwrap();
+        assert_eq!(base, expected);
+        let ids = |names: &[&str]| names.iter().map(|name| Identifier(String::from(*name))).collect::<Vec<_>>();
+        assert_eq!(
+            cross,
+            vec![
+                DecomposedCrossFilter {
+                    entity: Identifier(String::from("Run")),
+                    path: ids(&["Test", "Run"]),
+                    query: r#"Filter: result["FAIL"]"#.parse().unwrap(),
+                },
+                DecomposedCrossFilter {
+                    entity: Identifier(String::from("Step")),
+                    path: ids(&["Test", "Run", "Step"]),
+                    query: r#"Filter: name["setup"]; duration[>60]"#.parse().unwrap(),
+                },
+            ]
+        );
+
+        // 主实体条件与关联Filter以 OR 连接时，对拆分结果取交集会得到错误的 AND 语义
+        let mut or_combined = query.clone();
+        or_combined.cross_combinator = CrossCombinator::Or;
+        assert_eq!(or_combined.decompose(), None);
+
+        let with_branches: Query = r#"Filter: status["Open"] OR CrossFilter: <Test-Run> result["FAIL"]"#.parse().unwrap();
+        assert_eq!(with_branches.decompose(), None);
+    }
+
+    #[test]
+    fn test_query_to_dot() {
+        let comparison = |op, value| Box::new(Condition::Comparison { op, value });
+        let query = Query {
+            base_filters: vec![FieldFilter {
+                field: Identifier("priority".to_string()),
+                condition: Condition::And(
+                    Box::new(Condition::Or(
+                        comparison(CompOp::Gt, Literal::Number(5)),
+                        comparison(CompOp::Eq, Literal::String("High".to_string())),
+                    )),
+                    Box::new(Condition::IsNotNull),
+                ),
+                span: None,
+            }],
+            ..Default::default()
+        };
+
+        let dot = query.to_dot();
+        assert!(dot.starts_with("digraph Query {"), "{}", dot);
+        assert!(dot.trim_end().ends_with('}'), "{}", dot);
+        for node in [
+            r#"n0 [label="Query"];"#,
+            r#"n1 [label="Filter priority"];"#,
+            r#"n2 [label="AND"];"#,
+            r#"n3 [label="OR"];"#,
+            r#"n4 [label=">"];"#,
+            r#"n5 [label="5"];"#,
+            r#"n6 [label="="];"#,
+            r#"n7 [label="\"High\""];"#,
+            r#"n8 [label="IS NOT NULL"];"#,
+        ] {
+            assert!(dot.contains(node), "missing {} in {}", node, dot);
+        }
+        for edge in ["n0 -> n1;", "n1 -> n2;", "n2 -> n3;", "n3 -> n4;", "n4 -> n5;", "n3 -> n6;", "n6 -> n7;", "n2 -> n8;"] {
+            assert!(dot.contains(edge), "missing {} in {}", edge, dot);
+        }
+    }
+}
@@ -2,6 +2,7 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::env;
 use std::fs;
 use std::path::Path;
 
@@ -64,6 +65,38 @@ impl TableMappingConfig {
         
         Ok(TableMappingConfig { mappings })
     }
+
+    /// 从环境变量加载表映射配置，变量名去掉 `prefix` 后为实体名
+    ///
+    /// 例如 `prefix` 为 `RD_TABLE_` 时，`RD_TABLE_Test=tests` 将实体 `Test` 映射到表 `tests`。
+    /// 变量名或值不是合法 UTF-8 的变量被忽略
+    pub fn from_env(prefix: &str) -> Self {
+        let vars = env::vars_os()
+            .filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)));
+        Self::from_vars(prefix, vars)
+    }
+
+    /// 从给定的变量加载表映射配置，规则同 [`TableMappingConfig::from_env`]
+    pub fn from_vars(prefix: &str, vars: impl IntoIterator<Item = (String, String)>) -> Self {
+        let mappings = vars
+            .into_iter()
+            .filter_map(|(key, value)| {
+                let entity = key.strip_prefix(prefix)?;
+                if entity.is_empty() {
+                    return None;
+                }
+                Some((entity.to_string(), value))
+            })
+            .collect();
+
+        TableMappingConfig { mappings }
+    }
+
+    /// 合并另一份配置，同一实体以 `other` 的映射为准，例如用环境变量覆盖文件配置
+    pub fn merge(mut self, other: TableMappingConfig) -> Self {
+        self.mappings.extend(other.mappings);
+        self
+    }
     
     /// 获取实体对应的表名，如果不存在则返回小写的实体名
     pub fn get_table_name(&self, entity: &str) -> String {
@@ -77,9 +110,143 @@ impl TableMappingConfig {
     pub fn get_mappings(&self) -> &HashMap<String, String> {
         &self.mappings
     }
-    
+}
+
+/// 未配置显式映射时，由实体名推导表名的策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum NamingStrategy {
+    /// 保持实体名不变，例如 `TestRun` → `TestRun`
+    AsIs,
+    /// 转为小写，例如 `TestRun` → `testrun`
+    #[default]
+    Lowercase,
+    /// 转为蛇形命名，例如 `TestRun` → `test_run`
+    SnakeCase,
+    /// 转为蛇形命名并复数化，例如 `TestRun` → `test_runs`
+    SnakeCasePlural,
+}
+
+impl NamingStrategy {
+    /// 按照策略由实体名推导表名
+    pub fn table_name(&self, entity: &str) -> String {
+        match self {
+            NamingStrategy::AsIs => entity.to_string(),
+            NamingStrategy::Lowercase => entity.to_lowercase(),
+            NamingStrategy::SnakeCase => to_snake_case(entity),
+            NamingStrategy::SnakeCasePlural => pluralize(&to_snake_case(entity)),
+        }
+    }
+}
+
+/// DSL 字段名到数据库列名的转换方式
+///
+/// 生成的列名总是带引号的，PostgreSQL 不会再把 `dueDate` 折叠为 `duedate`；
+/// 数据库列使用蛇形命名时选择 `SnakeCase`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ColumnNaming {
+    /// 保持字段名不变并加引号，例如 `dueDate` → `"dueDate"`
+    #[default]
+    Preserve,
+    /// 转为蛇形命名，例如 `dueDate` → `"due_date"`
+    SnakeCase,
+}
+
+impl ColumnNaming {
+    /// 按照策略由字段名推导列名
+    pub fn column_name(&self, field: &str) -> String {
+        match self {
+            ColumnNaming::Preserve => field.to_string(),
+            ColumnNaming::SnakeCase => to_snake_case(field),
+        }
+    }
+}
+
+/// 关联链中相邻两个实体之间的连接列
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JoinKey {
+    /// 关联链中前一个实体的列
+    pub source_column: String,
+    /// 关联链中后一个实体的列
+    pub target_column: String,
+}
+
+impl JoinKey {
+    pub fn new(source_column: impl Into<String>, target_column: impl Into<String>) -> Self {
+        Self {
+            source_column: source_column.into(),
+            target_column: target_column.into(),
+        }
+    }
+}
+
+/// 未显式配置连接键时推导关联连接列的策略
+#[derive(Debug, Clone, Copy, Default)]
+pub enum JoinKeyStrategy {
+    /// 只使用显式配置的连接键，其余关联退化为 `id = id`
+    #[default]
+    Explicit,
+    /// 按约定推导：前一个实体的 `id` 关联到后一个实体中由函数 `(前一个实体, 后一个实体)` 生成的外键列
+    Convention(fn(&str, &str) -> String),
+}
+
+impl JoinKeyStrategy {
+    /// 常见的外键约定 `{实体}_id`，例如 `Test→Run` 关联为 `tests.id = test_runs.test_id`
+    pub fn foreign_key() -> Self {
+        JoinKeyStrategy::Convention(|source, _target| format!("{}_id", source.to_lowercase()))
+    }
+
+    /// 按策略推导 `source → target` 的连接键，`Explicit` 策略返回 `None`
+    pub fn join_key(&self, source: &str, target: &str) -> Option<JoinKey> {
+        match self {
+            JoinKeyStrategy::Explicit => None,
+            JoinKeyStrategy::Convention(convention) => Some(JoinKey::new("id", convention(source, target))),
+        }
+    }
+}
+
+/// 将驼峰式名称转为蛇形命名，连字符和空格视为单词分隔
+///
+/// 连续的大写字母视为一个缩写词，例如 `HTTPRequest` → `http_request`
+fn to_snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut result = String::with_capacity(name.len() + 4);
+
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '-' || c == ' ' {
+            result.push('_');
+            continue;
+        }
+        if c.is_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if prev.is_lowercase() || prev.is_ascii_digit() || (prev.is_uppercase() && next_is_lower) {
+                result.push('_');
+            }
+        }
+        result.extend(c.to_lowercase());
+    }
+
+    result
+}
+
+/// 英文单词的简单复数化
+fn pluralize(word: &str) -> String {
+    if word.ends_with('s') || word.ends_with('x') || word.ends_with('z')
+        || word.ends_with("ch") || word.ends_with("sh")
+    {
+        return format!("{}es", word);
+    }
+    if let Some(stem) = word.strip_suffix('y') {
+        if !stem.ends_with(['a', 'e', 'i', 'o', 'u']) {
+            return format!("{}ies", stem);
+        }
+    }
+    format!("{}s", word)
+}
+
+impl Default for TableMappingConfig {
     /// 创建默认配置（用于测试或fallback）
-    pub fn default() -> Self {
+    fn default() -> Self {
         let mut mappings = HashMap::new();
         mappings.insert("Test".to_string(), "tests".to_string());
         mappings.insert("Run".to_string(), "test_runs".to_string());
@@ -144,4 +311,51 @@ mod tests {
         assert_eq!(config.get_table_name("Test"), "tests");
         assert_eq!(config.get_table_name("Unknown"), "unknown");
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_env_config_overrides_file() {
+        let temp_file = "test_env_override.json";
+        let mut file = fs::File::create(temp_file).unwrap();
+        writeln!(file, r#"{{"Test": "tests", "Project": "projects"}}"#).unwrap();
+
+        let vars = [
+            ("RD_TABLE_Test", "tests_v2"),
+            ("RD_TABLE_Run", "test_runs"),
+            ("RD_TABLE_", "ignored"),
+            ("PATH", "/usr/bin"),
+        ];
+        let env_config = TableMappingConfig::from_vars(
+            "RD_TABLE_",
+            vars.into_iter().map(|(key, value)| (String::from(key), String::from(value))),
+        );
+        assert_eq!(env_config.mappings.len(), 2);
+        assert_eq!(env_config.get_table_name("Run"), "test_runs");
+
+        let config = TableMappingConfig::from_json_file(temp_file).unwrap().merge(env_config);
+        assert_eq!(config.get_table_name("Test"), "tests_v2");
+        assert_eq!(config.get_table_name("Run"), "test_runs");
+        assert_eq!(config.get_table_name("Project"), "projects");
+
+        fs::remove_file(temp_file).ok();
+    }
+
+    #[test]
+    fn test_naming_strategy() {
+        assert_eq!(NamingStrategy::AsIs.table_name("TestRun"), "TestRun");
+        assert_eq!(NamingStrategy::Lowercase.table_name("TestRun"), "testrun");
+        assert_eq!(NamingStrategy::SnakeCase.table_name("TestRun"), "test_run");
+        assert_eq!(NamingStrategy::SnakeCasePlural.table_name("TestRun"), "test_runs");
+
+        assert_eq!(NamingStrategy::SnakeCase.table_name("HTTPRequest"), "http_request");
+        assert_eq!(NamingStrategy::SnakeCasePlural.table_name("Category"), "categories");
+        assert_eq!(NamingStrategy::SnakeCasePlural.table_name("Test-Case"), "test_cases");
+        assert_eq!(NamingStrategy::SnakeCasePlural.table_name("Box"), "boxes");
+    }
+
+    #[test]
+    fn test_column_naming() {
+        assert_eq!(ColumnNaming::Preserve.column_name("dueDate"), "dueDate");
+        assert_eq!(ColumnNaming::SnakeCase.column_name("dueDate"), "due_date");
+        assert_eq!(ColumnNaming::SnakeCase.column_name("run-id"), "run_id");
+    }
+}
@@ -0,0 +1,433 @@
+//! 将过滤条件编译为 DataFusion 风格的逻辑表达式树，供内存分析引擎直接使用
+//!
+//! 注意：本模块的 [`Expr`] 是本地仿写的类型，不是 `datafusion` crate 的 `datafusion::logical_expr::Expr`，
+//! 本项目也不依赖 `datafusion` crate。它只按 DataFusion 逻辑表达式的结构和命名定义 (`Column`、`Literal`、
+//! `BinaryExpr`、`InList` 等)，调用方需要在自己的引擎一侧逐节点转换为真正的 DataFusion 表达式；
+//! Display 输出与 DataFusion 的写法相近。
+//!
+//! 逻辑表达式只描述单张表上的过滤条件，因此关联Filter、子查询、过滤宏和采样都会编译失败；
+//! 排序不属于过滤条件，不体现在结果中。
+
+use crate::ast::{Query, FieldFilter, Condition, CompOp, Literal};
+use crate::sql_compiler::{QueryCompiler, CompileResult, CompileError, SqlDialect, date_keyword_offset};
+use std::fmt;
+
+/// 仿照 DataFusion 逻辑表达式定义的本地表达式树，不是 `datafusion` crate 的类型
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    /// 列引用，只包含列名，不带表名限定
+    Column(String),
+    Literal(ScalarValue),
+    BinaryExpr(BinaryExpr),
+    Not(Box<Expr>),
+    IsNull(Box<Expr>),
+    IsNotNull(Box<Expr>),
+    InList { expr: Box<Expr>, list: Vec<Expr>, negated: bool },
+    Between { expr: Box<Expr>, negated: bool, low: Box<Expr>, high: Box<Expr> },
+    Like { negated: bool, expr: Box<Expr>, pattern: Box<Expr>, escape_char: Option<char> },
+    Cast { expr: Box<Expr>, data_type: DataType },
+    /// 无参数的标量函数调用，例如 `current_date()`
+    ScalarFunction { name: String, args: Vec<Expr> },
+}
+
+/// 二元运算表达式，例如 `priority > Int64(5)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryExpr {
+    pub left: Box<Expr>,
+    pub op: Operator,
+    pub right: Box<Expr>,
+}
+
+/// 二元运算符
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    Plus,
+    Minus,
+    And,
+    Or,
+    IsNotDistinctFrom,
+}
+
+/// 标量字面量
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScalarValue {
+    Utf8(String),
+    Int64(i64),
+    Boolean(bool),
+    /// 以天为单位的时间间隔，用于相对日期
+    IntervalDays(i64),
+    Null,
+}
+
+/// `CAST` 的目标类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataType {
+    Date32,
+}
+
+impl Expr {
+    /// 构造二元运算表达式
+    pub fn binary(left: Expr, op: Operator, right: Expr) -> Expr {
+        Expr::BinaryExpr(BinaryExpr { left: Box::new(left), op, right: Box::new(right) })
+    }
+
+    /// 以 AND 连接两个表达式
+    pub fn and(self, other: Expr) -> Expr {
+        Expr::binary(self, Operator::And, other)
+    }
+
+    /// 以 OR 连接两个表达式
+    pub fn or(self, other: Expr) -> Expr {
+        Expr::binary(self, Operator::Or, other)
+    }
+}
+
+impl Operator {
+    /// 运算符优先级，用于 Display 时决定子表达式是否加括号
+    fn precedence(self) -> u8 {
+        match self {
+            Operator::Or => 5,
+            Operator::And => 10,
+            Operator::Eq | Operator::NotEq | Operator::Lt | Operator::LtEq | Operator::Gt | Operator::GtEq
+            | Operator::IsNotDistinctFrom => 20,
+            Operator::Plus | Operator::Minus => 30,
+        }
+    }
+}
+
+impl From<&CompOp> for Operator {
+    fn from(op: &CompOp) -> Self {
+        match op {
+            CompOp::Eq => Operator::Eq,
+            CompOp::NotEq => Operator::NotEq,
+            CompOp::Gt => Operator::Gt,
+            CompOp::Lt => Operator::Lt,
+            CompOp::Gte => Operator::GtEq,
+            CompOp::Lte => Operator::LtEq,
+            CompOp::NullSafeEq => Operator::IsNotDistinctFrom,
+        }
+    }
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            Operator::Eq => "=",
+            Operator::NotEq => "!=",
+            Operator::Lt => "<",
+            Operator::LtEq => "<=",
+            Operator::Gt => ">",
+            Operator::GtEq => ">=",
+            Operator::Plus => "+",
+            Operator::Minus => "-",
+            Operator::And => "AND",
+            Operator::Or => "OR",
+            Operator::IsNotDistinctFrom => "IS NOT DISTINCT FROM",
+        };
+        f.write_str(symbol)
+    }
+}
+
+impl fmt::Display for ScalarValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScalarValue::Utf8(s) => write!(f, "Utf8({:?})", s),
+            ScalarValue::Int64(n) => write!(f, "Int64({})", n),
+            ScalarValue::Boolean(b) => write!(f, "Boolean({})", b),
+            ScalarValue::IntervalDays(days) => write!(f, "IntervalDays({})", days),
+            ScalarValue::Null => f.write_str("NULL"),
+        }
+    }
+}
+
+impl fmt::Display for DataType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataType::Date32 => f.write_str("Date32"),
+        }
+    }
+}
+
+impl fmt::Display for BinaryExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // 优先级低于当前运算符的子表达式需要加括号
+        let write_operand = |f: &mut fmt::Formatter<'_>, operand: &Expr| match operand {
+            Expr::BinaryExpr(inner) if inner.op.precedence() < self.op.precedence() => write!(f, "({})", inner),
+            other => write!(f, "{}", other),
+        };
+        write_operand(f, &self.left)?;
+        write!(f, " {} ", self.op)?;
+        write_operand(f, &self.right)
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Column(name) => f.write_str(name),
+            Expr::Literal(value) => write!(f, "{}", value),
+            Expr::BinaryExpr(binary) => write!(f, "{}", binary),
+            Expr::Not(inner) => write!(f, "NOT ({})", inner),
+            Expr::IsNull(inner) => write!(f, "{} IS NULL", inner),
+            Expr::IsNotNull(inner) => write!(f, "{} IS NOT NULL", inner),
+            Expr::InList { expr, list, negated } => {
+                let list: Vec<String> = list.iter().map(ToString::to_string).collect();
+                let not = if *negated { "NOT " } else { "" };
+                write!(f, "{} {}IN ([{}])", expr, not, list.join(", "))
+            }
+            Expr::Between { expr, negated, low, high } => {
+                let not = if *negated { "NOT " } else { "" };
+                write!(f, "{} {}BETWEEN {} AND {}", expr, not, low, high)
+            }
+            Expr::Like { negated, expr, pattern, escape_char } => {
+                let not = if *negated { "NOT " } else { "" };
+                write!(f, "{} {}LIKE {}", expr, not, pattern)?;
+                match escape_char {
+                    Some(c) => write!(f, " ESCAPE '{}'", c),
+                    None => Ok(()),
+                }
+            }
+            Expr::Cast { expr, data_type } => write!(f, "CAST({} AS {})", expr, data_type),
+            Expr::ScalarFunction { name, args } => {
+                let args: Vec<String> = args.iter().map(ToString::to_string).collect();
+                write!(f, "{}({})", name, args.join(", "))
+            }
+        }
+    }
+}
+
+/// 生成 DataFusion 逻辑表达式的编译器，在 [`crate::sql_compiler::CompilerRegistry`] 中注册为 `"datafusion"`
+///
+/// 作为 [`QueryCompiler`] 使用时，结果中的 `sql` 是表达式的 Display 形式，只用于调试和日志；
+/// 需要表达式树本身时调用 [`DataFusionCompiler::compile_expr`]
+#[derive(Debug, Clone, Default)]
+pub struct DataFusionCompiler;
+
+impl DataFusionCompiler {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// 将查询编译为过滤表达式，查询没有任何条件时返回 `None`
+    ///
+    /// 主实体的基础Filter与 OR 分组以 AND 连接，段落级 OR 的各分支以 OR 连接
+    pub fn compile_expr(&self, query: &Query) -> Result<Option<Expr>, CompileError> {
+        if query.sample_percent.is_some() {
+            return Err(CompileError::new("DataFusion 表达式不支持采样子句".to_string()));
+        }
+
+        let query = query.clone().expand_cross_combinator();
+        let mut branch_exprs = Vec::new();
+        for branch in query.branches() {
+            if !branch.cross_filters.is_empty() {
+                return Err(CompileError::new("DataFusion 表达式只描述单表过滤条件，不支持关联Filter".to_string()));
+            }
+            if !branch.macros.is_empty() {
+                return Err(CompileError::new("DataFusion 表达式不支持过滤宏".to_string()));
+            }
+
+            let mut conditions = Vec::new();
+            for filter in &branch.base_filters {
+                conditions.push(self.filter_to_expr(filter)?);
+            }
+            for group in &branch.base_or_groups {
+                let group_exprs = group.iter().map(|filter| self.filter_to_expr(filter)).collect::<Result<Vec<_>, _>>()?;
+                conditions.extend(group_exprs.into_iter().reduce(Expr::or));
+            }
+            match conditions.into_iter().reduce(Expr::and) {
+                Some(expr) => branch_exprs.push(expr),
+                // 某个分支没有条件时整个 OR 恒成立
+                None => return Ok(None),
+            }
+        }
+
+        Ok(branch_exprs.into_iter().reduce(Expr::or))
+    }
+
+    fn filter_to_expr(&self, filter: &FieldFilter) -> Result<Expr, CompileError> {
+        self.condition_to_expr(&filter.field.0, &filter.condition)
+    }
+
+    fn condition_to_expr(&self, field: &str, condition: &Condition) -> Result<Expr, CompileError> {
+        let column = || Expr::Column(field.to_string());
+        let expr = match condition {
+            Condition::And(left, right) => self.condition_to_expr(field, left)?.and(self.condition_to_expr(field, right)?),
+            Condition::Or(left, right) => self.condition_to_expr(field, left)?.or(self.condition_to_expr(field, right)?),
+            Condition::Not(inner) => Expr::Not(Box::new(self.condition_to_expr(field, inner)?)),
+            Condition::Grouped(inner) => self.condition_to_expr(field, inner)?,
+            Condition::Comparison { op, value } => Expr::binary(column(), op.into(), self.literal_to_expr(value)?),
+            Condition::In(values) => {
+                // 与 SQL 编译器一致，列表中的 null 拆成额外的 IS NULL 检查
+                let list = values.iter()
+                    .filter(|value| **value != Literal::Null)
+                    .map(|value| self.literal_to_expr(value))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let has_null = values.contains(&Literal::Null);
+                let in_list = (!list.is_empty()).then(|| Expr::InList { expr: Box::new(column()), list, negated: false });
+                match (in_list, has_null) {
+                    (Some(in_list), true) => in_list.or(Expr::IsNull(Box::new(column()))),
+                    (Some(in_list), false) => in_list,
+                    (None, true) => Expr::IsNull(Box::new(column())),
+                    (None, false) => Expr::Literal(ScalarValue::Boolean(false)),
+                }
+            }
+            Condition::InSubquery { .. } => {
+                return Err(CompileError::new(format!("DataFusion 表达式不支持子查询: {}", field)));
+            }
+            Condition::InTuple { fields, rows } => {
+                let mut row_exprs = Vec::new();
+                for row in rows {
+                    let equalities = fields.iter().zip(row)
+                        .map(|(field, value)| Ok(Expr::binary(Expr::Column(field.0.clone()), Operator::Eq, self.literal_to_expr(value)?)))
+                        .collect::<Result<Vec<_>, CompileError>>()?;
+                    row_exprs.extend(equalities.into_iter().reduce(Expr::and));
+                }
+                row_exprs.into_iter().reduce(Expr::or).unwrap_or(Expr::Literal(ScalarValue::Boolean(false)))
+            }
+            // 与任意一个值比较成立即为 OR，与全部值比较都成立即为 AND
+            Condition::AnyOf { op, values } | Condition::AllOf { op, values } => {
+                let is_any = matches!(condition, Condition::AnyOf { .. });
+                let comparisons = values.iter()
+                    .map(|value| Ok(Expr::binary(column(), op.into(), self.literal_to_expr(value)?)))
+                    .collect::<Result<Vec<_>, CompileError>>()?;
+                let combined = comparisons.into_iter().reduce(if is_any { Expr::or } else { Expr::and });
+                combined.unwrap_or(Expr::Literal(ScalarValue::Boolean(!is_any)))
+            }
+            Condition::Between { low, high, negated } => Expr::Between {
+                expr: Box::new(column()),
+                negated: *negated,
+                low: Box::new(self.literal_to_expr(low)?),
+                high: Box::new(self.literal_to_expr(high)?),
+            },
+            Condition::Like { pattern, negated } => Expr::Like {
+                negated: *negated,
+                expr: Box::new(column()),
+                pattern: Box::new(Expr::Literal(ScalarValue::Utf8(pattern.clone()))),
+                escape_char: pattern.contains('\\').then_some('\\'),
+            },
+            Condition::IsNull => Expr::IsNull(Box::new(column())),
+            Condition::IsNotNull => Expr::IsNotNull(Box::new(column())),
+            Condition::IsEmpty => {
+                Expr::binary(column(), Operator::Eq, Expr::Literal(ScalarValue::Utf8(String::new())))
+                    .or(Expr::IsNull(Box::new(column())))
+            }
+            Condition::IsNotEmpty => {
+                Expr::binary(column(), Operator::NotEq, Expr::Literal(ScalarValue::Utf8(String::new())))
+                    .and(Expr::IsNotNull(Box::new(column())))
+            }
+        };
+        Ok(expr)
+    }
+
+    fn literal_to_expr(&self, literal: &Literal) -> Result<Expr, CompileError> {
+        match literal {
+            Literal::String(s) => Ok(Expr::Literal(ScalarValue::Utf8(s.clone()))),
+            Literal::Number(n) => Ok(Expr::Literal(ScalarValue::Int64(*n))),
+            Literal::Null => Ok(Expr::Literal(ScalarValue::Null)),
+            Literal::Column(column) => Ok(Expr::Column(column.0.clone())),
+            Literal::Date(d) => match date_keyword_offset(d) {
+                Some(offset) => Ok(Self::current_date_expr(offset)),
+                None => Ok(Expr::Cast {
+                    expr: Box::new(Expr::Literal(ScalarValue::Utf8(d.clone()))),
+                    data_type: DataType::Date32,
+                }),
+            },
+            Literal::RelativeDate { base, offset_days } => {
+                let base_offset = date_keyword_offset(base).ok_or_else(|| {
+                    CompileError::new(format!("未知的日期关键字: {}", base))
+                })?;
+                let offset = base_offset.checked_add(*offset_days).ok_or_else(|| {
+                    CompileError::new(format!("日期偏移量溢出: {}", literal))
+                })?;
+                Ok(Self::current_date_expr(offset))
+            }
+            Literal::CurrentUser => Err(CompileError::new("DataFusion 表达式不支持 current_user".to_string())),
+        }
+    }
+
+    /// 相对当前日期偏移 `offset_days` 天的表达式，例如 `current_date() - IntervalDays(7)`
+    fn current_date_expr(offset_days: i64) -> Expr {
+        let current_date = Expr::ScalarFunction { name: "current_date".to_string(), args: Vec::new() };
+        if offset_days == 0 {
+            return current_date;
+        }
+        let op = if offset_days < 0 { Operator::Minus } else { Operator::Plus };
+        let days = i64::try_from(offset_days.unsigned_abs()).unwrap_or(i64::MAX);
+        Expr::binary(current_date, op, Expr::Literal(ScalarValue::IntervalDays(days)))
+    }
+}
+
+impl QueryCompiler for DataFusionCompiler {
+    fn compile(&self, query: Query, _entity: &str) -> Result<CompileResult, CompileError> {
+        let expr = self.compile_expr(&query)?;
+        Ok(CompileResult {
+            sql: expr.map_or_else(|| ScalarValue::Boolean(true).to_string(), |expr| expr.to_string()),
+            optimizations: Vec::new(),
+            diagnostics: Vec::new(),
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "DataFusionExprCompiler"
+    }
+
+    /// DataFusion 的 SQL 方言与 PostgreSQL 最接近
+    fn supported_dialect(&self) -> SqlDialect {
+        SqlDialect::PostgreSQL
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_compiler::CompilerRegistry;
+
+    fn compile_expr(input: &str) -> Result<Option<Expr>, CompileError> {
+        let query: Query = input.parse().unwrap();
+        DataFusionCompiler::new().compile_expr(&query)
+    }
+
+    #[test]
+    fn test_comparison_builds_binary_expr() {
+        let expr = compile_expr(r#"Filter: priority[>5]"#).unwrap().unwrap();
+        assert_eq!(expr, Expr::BinaryExpr(BinaryExpr {
+            left: Box::new(Expr::Column("priority".to_string())),
+            op: Operator::Gt,
+            right: Box::new(Expr::Literal(ScalarValue::Int64(5))),
+        }));
+
+        // 多个Filter以 AND 连接，OR 子表达式按优先级加括号
+        let expr = compile_expr(r#"Filter: status["Open" OR "Reopened"]; flaky[IS NULL]"#).unwrap().unwrap();
+        let Expr::BinaryExpr(BinaryExpr { op: Operator::And, left, .. }) = &expr else {
+            panic!("expected AND, got {:?}", expr);
+        };
+        assert!(matches!(left.as_ref(), Expr::BinaryExpr(BinaryExpr { op: Operator::Or, .. })));
+        assert_eq!(
+            expr.to_string(),
+            r#"(status = Utf8("Open") OR status = Utf8("Reopened")) AND flaky IS NULL"#
+        );
+    }
+
+    #[test]
+    fn test_registered_as_datafusion() {
+        let compiler = CompilerRegistry::new().create("datafusion").unwrap();
+        assert_eq!(compiler.name(), "DataFusionExprCompiler");
+        let query: Query = r#"Filter: title[LIKE "bug%"]; id[IN (1, 2)]"#.parse().unwrap();
+        let result = compiler.compile(query, "Test").unwrap();
+        assert_eq!(result.sql, r#"title LIKE Utf8("bug%") AND id IN ([Int64(1), Int64(2)])"#);
+    }
+
+    #[test]
+    fn test_unsupported_constructs_are_rejected() {
+        assert!(compile_expr(r#"CrossFilter: <Test-Run> result["FAIL"]"#).is_err());
+        assert!(compile_expr(r#"Filter: owner[current_user]"#).is_err());
+        assert!(compile_expr("Filter: id[>0]; Sample: 10%").is_err());
+        assert_eq!(compile_expr("Sort: priority DESC").unwrap(), None);
+    }
+}
@@ -1,26 +1,74 @@
 //! Filter的词法分析器
 
+use std::collections::HashSet;
+
 use crate::token::{Span, Token, TokenKind};
 
+/// 默认保留的关键字，匹配时不区分大小写
+pub const RESERVED_KEYWORDS: &[&str] = &[
+    "and", "or", "not", "in", "is", "null", "today", "yesterday", "tomorrow", "current_user",
+];
+
+/// 词法分析器配置
+#[derive(Debug, Clone)]
+pub struct LexerConfig {
+    /// 识别为关键字的单词 (小写)，默认为 [`RESERVED_KEYWORDS`]
+    ///
+    /// 移除部署中用不到的关键字后，同名单词按标识符分析，例如去掉 `in` 后可以使用名为 `in` 的字段；
+    /// 不在 [`RESERVED_KEYWORDS`] 中的单词不会因为加入集合而成为关键字
+    pub reserved_keywords: HashSet<String>,
+}
+
+impl Default for LexerConfig {
+    fn default() -> Self {
+        Self {
+            reserved_keywords: RESERVED_KEYWORDS.iter().map(|keyword| keyword.to_string()).collect(),
+        }
+    }
+}
+
 pub struct Lexer<'a> {
     input: &'a str,
     /// 输入字符串中的当前位置（字节索引）
     position: usize,
+    /// 允许分析的字节数上限，默认为整个输入
+    limit: usize,
+    /// 是否已经为超出上限的输入产生过错误 token
+    limit_reported: bool,
+    /// 自定义配置，`None` 时使用默认的关键字集合
+    config: Option<&'a LexerConfig>,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
-        Lexer { input, position: 0 }
+        Lexer { input, position: 0, limit: input.len(), limit_reported: false, config: None }
     }
 
-    /// 返回当前位置的字符，不推进位置
-    fn peek(&self) -> Option<char> {
-        self.input[self.position..].chars().next()
+    /// 使用自定义配置创建词法分析器
+    pub fn with_config(input: &'a str, config: &'a LexerConfig) -> Self {
+        Lexer { config: Some(config), ..Lexer::new(input) }
+    }
+
+    /// 创建只分析前 `max_len` 字节的词法分析器，用于处理不可信的用户输入
+    ///
+    /// 输入超过上限时，分析到上限处后产生一个覆盖剩余输入的 `Illegal` token 并停止，
+    /// 超出部分不会被扫描
+    pub fn with_max_len(input: &'a str, max_len: usize) -> Self {
+        let mut limit = max_len.min(input.len());
+        while !input.is_char_boundary(limit) {
+            limit -= 1;
+        }
+        Lexer { input, position: 0, limit, limit_reported: false, config: None }
+    }
+
+    /// 对输入进行完整的词法分析，返回每个 token 的类型及其字节位置，供编辑器高亮等工具使用
+    pub fn spanned_tokens(input: &'a str) -> Vec<(TokenKind<'a>, Span)> {
+        Lexer::new(input).map(|token| (token.kind, token.span)).collect()
     }
 
-    /// 返回下一个位置的字符，不推进位置
-    fn peek_next(&self) -> Option<char> {
-        self.input[self.position..].chars().nth(1)
+    /// 返回当前位置的字符，不推进位置
+    fn peek(&self) -> Option<char> {
+        self.input[self.position..self.limit].chars().next()
     }
 
     /// 推进位置一个字符并返回该字符
@@ -75,7 +123,34 @@ impl<'a> Lexer<'a> {
         
         let content = &self.input[content_start..content_end];
         Token {
-            kind: TokenKind::String(content),
+            kind: TokenKind::String {
+                value: content,
+                raw: &self.input[start..self.position],
+            },
+            span: Span::new(start, self.position),
+        }
+    }
+
+    /// 读取反引号包围的标识符，例如 `` `Due Date` ``
+    /// 注意：开始的反引号已经被调用者消费
+    ///
+    /// 引号内的内容总是作为标识符，不会被识别为关键字；缺少结束反引号时返回非法 token
+    fn read_quoted_identifier(&mut self, start: usize) -> Token<'a> {
+        let content_start = self.position;
+        while let Some(c) = self.peek() {
+            if c == '`' {
+                break;
+            }
+            self.bump();
+        }
+        let content_end = self.position;
+
+        if self.bump().is_none() {
+            return Token { kind: TokenKind::Illegal, span: Span::new(start, self.position) };
+        }
+
+        Token {
+            kind: TokenKind::Identifier(&self.input[content_start..content_end]),
             span: Span::new(start, self.position),
         }
     }
@@ -84,7 +159,15 @@ impl<'a> Lexer<'a> {
     /// 标识符可以包含字母、数字、连字符和下划线
     fn read_identifier(&mut self, start: usize) -> Token<'a> {
         while let Some(c) = self.peek() {
-            if c.is_alphanumeric() || c == '-' || c == '_' {
+            if c.is_alphanumeric() || c == '_' {
+                self.bump();
+            } else if c == '-' {
+                // 日期关键字后的 `-` 是偏移量运算符，例如 `today-7`
+                if self.is_reserved(&self.input[start..self.position])
+                    && is_date_keyword(&self.input[start..self.position])
+                {
+                    break;
+                }
                 self.bump();
             } else {
                 break;
@@ -92,7 +175,7 @@ impl<'a> Lexer<'a> {
         }
         let literal = &self.input[start..self.position];
         
-        // 检查特殊关键字 "Filter:" 和 "CrossFilter:"
+        // 检查特殊关键字 "Filter:"、"CrossFilter:"、"Sample:" 和 "Sort:"
         if self.peek() == Some(':') {
              if literal.eq_ignore_ascii_case("Filter") {
                 self.bump(); // 消费 ':'
@@ -102,14 +185,32 @@ impl<'a> Lexer<'a> {
                 self.bump(); // 消费 ':'
                 return Token { kind: TokenKind::CrossFilter, span: Span::new(start, self.position) };
              }
+             if literal.eq_ignore_ascii_case("Sample") {
+                self.bump(); // 消费 ':'
+                return Token { kind: TokenKind::Sample, span: Span::new(start, self.position) };
+             }
+             if literal.eq_ignore_ascii_case("Sort") {
+                self.bump(); // 消费 ':'
+                return Token { kind: TokenKind::Sort, span: Span::new(start, self.position) };
+             }
         }
 
-        let kind = match_keyword(literal);
+        let kind = if self.is_reserved(literal) { match_keyword(literal) } else { TokenKind::Identifier(literal) };
         Token { kind, span: Span::new(start, self.position) }
     }
+
+    /// 单词是否按配置保留为关键字
+    fn is_reserved(&self, word: &str) -> bool {
+        self.config.is_none_or(|config| config.reserved_keywords.contains(&word.to_ascii_lowercase()))
+    }
+}
+
+/// 是否为可带天数偏移的日期关键字
+fn is_date_keyword(s: &str) -> bool {
+    ["today", "yesterday", "tomorrow"].iter().any(|k| s.eq_ignore_ascii_case(k))
 }
 
-fn match_keyword(s: &str) -> TokenKind {
+fn match_keyword(s: &str) -> TokenKind<'_> {
     match s.to_ascii_lowercase().as_str() {
         "and" => TokenKind::And,
         "or" => TokenKind::Or,
@@ -133,7 +234,12 @@ impl<'a> Iterator for Lexer<'a> {
         let start = self.position;
 
         let Some(c) = self.bump() else {
-            return None; // 到达输入末尾
+            // 到达输入末尾；输入超过长度上限时先报告一次错误
+            if self.limit < self.input.len() && !self.limit_reported {
+                self.limit_reported = true;
+                return Some(Token { kind: TokenKind::Illegal, span: Span::new(self.limit, self.input.len()) });
+            }
+            return None;
         };
 
         let token = match c {
@@ -146,6 +252,10 @@ impl<'a> Iterator for Lexer<'a> {
             '<' => {
                 if self.peek() == Some('=') {
                     self.bump();
+                    if self.peek() == Some('>') {
+                        self.bump();
+                        return Some(Token { kind: TokenKind::NullSafeEq, span: Span::new(start, self.position) });
+                    }
                     Token { kind: TokenKind::Lte, span: Span::new(start, self.position) }
                 } else {
                     Token { kind: TokenKind::Lt, span: Span::new(start, self.position) }
@@ -169,7 +279,14 @@ impl<'a> Iterator for Lexer<'a> {
             }
             ';' => Token { kind: TokenKind::Semicolon, span: Span::new(start, self.position) },
             '-' => Token { kind: TokenKind::Dash, span: Span::new(start, self.position) },
+            '%' => Token { kind: TokenKind::Percent, span: Span::new(start, self.position) },
+            '|' => Token { kind: TokenKind::Pipe, span: Span::new(start, self.position) },
+            '*' => Token { kind: TokenKind::Star, span: Span::new(start, self.position) },
+            '/' => Token { kind: TokenKind::Slash, span: Span::new(start, self.position) },
+            '+' => Token { kind: TokenKind::Plus, span: Span::new(start, self.position) },
+            '@' => Token { kind: TokenKind::At, span: Span::new(start, self.position) },
             '"' => self.read_string(start),
+            '`' => self.read_quoted_identifier(start),
             c if c.is_ascii_digit() => self.read_number(start),
             c if c.is_alphabetic() => self.read_identifier(start),
             _ => Token { kind: TokenKind::Illegal, span: Span::new(start, self.position) },
@@ -190,14 +307,14 @@ mod tests {
         assert_eq!(lexer.next().unwrap().kind, TokenKind::Filter);
         assert_eq!(lexer.next().unwrap().kind, TokenKind::Identifier("status"));
         assert_eq!(lexer.next().unwrap().kind, TokenKind::LBracket);
-        assert_eq!(lexer.next().unwrap().kind, TokenKind::String("Open"));
+        assert_eq!(lexer.next().unwrap().kind, TokenKind::String { value: "Open", raw: r#""Open""# });
         assert_eq!(lexer.next().unwrap().kind, TokenKind::RBracket);
         assert_eq!(lexer.next(), None);
     }
     
     #[test]
     fn test_all_operators_and_punctuation() {
-        let input = "!= = > < >= <= ( ) [ ] ; , -";
+        let input = "!= = > < >= <= ( ) [ ] ; , - % | * /";
         let kinds: Vec<_> = Lexer::new(input).map(|t| t.kind).collect();
         assert_eq!(
             kinds,
@@ -205,7 +322,8 @@ mod tests {
                 TokenKind::NotEq, TokenKind::Eq, TokenKind::Gt, TokenKind::Lt,
                 TokenKind::Gte, TokenKind::Lte, TokenKind::LParen, TokenKind::RParen,
                 TokenKind::LBracket, TokenKind::RBracket, TokenKind::Semicolon,
-                TokenKind::Comma, TokenKind::Dash,
+                TokenKind::Comma, TokenKind::Dash, TokenKind::Percent, TokenKind::Pipe,
+                TokenKind::Star, TokenKind::Slash,
             ]
         );
     }
@@ -232,7 +350,7 @@ mod tests {
             kinds,
             vec![
                 TokenKind::Number(12345),
-                TokenKind::String("hello world"),
+                TokenKind::String { value: "hello world", raw: r#""hello world""# },
             ]
         );
     }
@@ -247,7 +365,7 @@ mod tests {
                 TokenKind::Filter,
                 TokenKind::Identifier("title"),
                 TokenKind::LBracket,
-                TokenKind::String("Plan"),
+                TokenKind::String { value: "Plan", raw: r#""Plan""# },
                 TokenKind::And,
                 TokenKind::LParen,
                 TokenKind::Identifier("v1"),
@@ -284,6 +402,56 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_sample_clause() {
+        let input = "Sample: 10%";
+        let kinds: Vec<_> = Lexer::new(input).map(|t| t.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![TokenKind::Sample, TokenKind::Number(10), TokenKind::Percent]
+        );
+    }
+
+    #[test]
+    fn test_quoted_identifier() {
+        let input = "`Due Date`[>today] `in`[1] `unterminated";
+        let kinds: Vec<_> = Lexer::new(input).map(|t| t.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Identifier("Due Date"),
+                TokenKind::LBracket,
+                TokenKind::Gt,
+                TokenKind::Today,
+                TokenKind::RBracket,
+                TokenKind::Identifier("in"),
+                TokenKind::LBracket,
+                TokenKind::Number(1),
+                TokenKind::RBracket,
+                TokenKind::Illegal,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spanned_tokens_utf8_byte_offsets() {
+        let input = r#"Filter: 标题["测试"]"#;
+        let tokens = Lexer::spanned_tokens(input);
+        assert_eq!(
+            tokens,
+            vec![
+                (TokenKind::Filter, Span::new(0, 7)),
+                (TokenKind::Identifier("标题"), Span::new(8, 14)),
+                (TokenKind::LBracket, Span::new(14, 15)),
+                (TokenKind::String { value: "测试", raw: r#""测试""# }, Span::new(15, 23)),
+                (TokenKind::RBracket, Span::new(23, 24)),
+            ]
+        );
+        for (_, span) in &tokens {
+            assert!(input.is_char_boundary(span.start) && input.is_char_boundary(span.end));
+        }
+    }
+
     #[test]
     fn test_greater_than_operator() {
         let input = "field[>5]";
@@ -299,4 +467,101 @@ mod tests {
             ]
         );
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_string_preserves_raw_slice() {
+        let input = r#"title["Release Plan"]"#;
+        let token = Lexer::new(input).nth(2).unwrap();
+        assert_eq!(
+            token.kind,
+            TokenKind::String { value: "Release Plan", raw: r#""Release Plan""# }
+        );
+        assert_eq!(&input[token.span.start..token.span.end], r#""Release Plan""#);
+    }
+
+    #[test]
+    fn test_date_keyword_offset() {
+        let kinds: Vec<_> = Lexer::new("today-7d tomorrow+1 to-do").map(|t| t.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Today,
+                TokenKind::Dash,
+                TokenKind::Number(7),
+                TokenKind::Identifier("d"),
+                TokenKind::Tomorrow,
+                TokenKind::Plus,
+                TokenKind::Number(1),
+                TokenKind::Identifier("to-do"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_max_len_stops_with_illegal_token() {
+        let input = format!(r#"Filter: status["Open"]{}"#, "; x[1]".repeat(1000));
+        let tokens: Vec<_> = Lexer::with_max_len(&input, 22).collect();
+
+        assert_eq!(tokens.len(), 6);
+        assert_eq!(tokens[4].kind, TokenKind::RBracket);
+        assert_eq!(tokens[5].kind, TokenKind::Illegal);
+        assert_eq!(tokens[5].span, Span::new(22, input.len()));
+
+        // 未超过上限时与默认构造一致
+        let short = r#"Filter: status["Open"]"#;
+        assert_eq!(
+            Lexer::with_max_len(short, 1024).map(|t| t.kind).collect::<Vec<_>>(),
+            Lexer::new(short).map(|t| t.kind).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_null_safe_eq_operator() {
+        let kinds: Vec<_> = Lexer::new("a[<=>null] b[<=5]").map(|t| t.kind).collect();
+        assert_eq!(kinds[2], TokenKind::NullSafeEq);
+        assert_eq!(kinds[3], TokenKind::Null);
+        assert_eq!(kinds[7], TokenKind::Lte);
+    }
+
+    #[test]
+    fn test_configurable_reserved_keywords() {
+        let mut config = LexerConfig::default();
+        let input = "in[IN (1)]; today-1";
+        let kinds: Vec<_> = Lexer::with_config(input, &config).map(|t| t.kind).collect();
+        assert_eq!(kinds, Lexer::new(input).map(|t| t.kind).collect::<Vec<_>>());
+        assert_eq!(kinds[0], TokenKind::In);
+
+        config.reserved_keywords.remove("in");
+        config.reserved_keywords.remove("today");
+        let kinds: Vec<_> = Lexer::with_config(input, &config).map(|t| t.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Identifier("in"), TokenKind::LBracket, TokenKind::Identifier("IN"),
+                TokenKind::LParen, TokenKind::Number(1), TokenKind::RParen, TokenKind::RBracket,
+                TokenKind::Semicolon, TokenKind::Identifier("today-1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_adjacent_operators_maximal_munch() {
+        // 双字符运算符优先匹配，剩下的字符再单独成为 token
+        let cases = [
+            ("!==", vec![TokenKind::NotEq, TokenKind::Eq]),
+            (">==", vec![TokenKind::Gte, TokenKind::Eq]),
+            ("<==", vec![TokenKind::Lte, TokenKind::Eq]),
+            (">=<=", vec![TokenKind::Gte, TokenKind::Lte]),
+            ("<=>=", vec![TokenKind::NullSafeEq, TokenKind::Eq]),
+            ("<>", vec![TokenKind::Lt, TokenKind::Gt]),
+            ("=!=", vec![TokenKind::Eq, TokenKind::NotEq]),
+        ];
+        for (input, expected) in cases {
+            let tokens: Vec<_> = Lexer::new(input).collect();
+            let kinds: Vec<_> = tokens.iter().map(|t| t.kind.clone()).collect();
+            assert_eq!(kinds, expected, "输入: {}", input);
+            // 相邻 token 的位置首尾相接
+            assert_eq!(tokens.last().unwrap().span.end, input.len(), "输入: {}", input);
+        }
+    }
+}
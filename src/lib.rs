@@ -3,4 +3,7 @@ pub mod token;
 pub mod parser;
 pub mod lexer;
 pub mod sql_compiler;
-pub mod config; 
\ No newline at end of file
+pub mod config; 
+pub mod datafusion;
+#[cfg(feature = "condition-arena")]
+pub mod arena;
@@ -4,94 +4,142 @@ pub mod parser;
 pub mod lexer;
 pub mod sql_compiler;
 pub mod config;
+pub mod datafusion;
 
+use ast::Query as AstQuery;
 use lexer::Lexer;
-use parser::Parser;
+use parser::{Parser, ParseError};
 use sql_compiler::{
-    SqlCompiler, CompilerConfig
+    SqlCompiler, CompilerConfig, CompileResult, CompileError, BatchQueryResult
 };
 use config::TableMappingConfig;
 use anyhow::Result;
 use rustyline::error::ReadlineError;
 use rustyline::DefaultEditor;
 
+/// 表映射环境变量前缀，例如 `RD_TABLE_Test=tests`
+const TABLE_ENV_PREFIX: &str = "RD_TABLE_";
+
 /// 创建SQL编译器实例（静默版本，不打印加载信息）
+///
+/// 环境变量中的表映射覆盖 JSON 配置文件中的同名实体
 fn create_compiler_with_config_silent() -> SqlCompiler {
-    match TableMappingConfig::from_json_file("table_mapping.json") {
-        Ok(table_config) => {
-            let config = CompilerConfig {
-                table_mapping: table_config.mappings,
-                ..Default::default()
-            };
-            SqlCompiler::from_config(config)
+    let env_config = TableMappingConfig::from_env(TABLE_ENV_PREFIX);
+    let table_config = match TableMappingConfig::from_json_file("table_mapping.json") {
+        Ok(file_config) => file_config.merge(env_config),
+        Err(_) if !env_config.mappings.is_empty() => env_config,
+        Err(_) => return SqlCompiler::new(),
+    };
+
+    let config = CompilerConfig {
+        table_mapping: table_config.mappings,
+        ..Default::default()
+    };
+    SqlCompiler::from_config(config)
+}
+
+/// 单个Filter字符串经过完整处理流水线后的结果
+#[derive(Debug)]
+struct PipelineOutcome {
+    /// 词法分析生成的 token 数量
+    token_count: usize,
+    /// 语法分析结果
+    ast: std::result::Result<AstQuery, ParseError>,
+    /// SQL 编译结果，解析失败时为 `None`
+    compile: Option<std::result::Result<CompileResult, CompileError>>,
+    /// 批量编译结果，仅在 SQL 编译成功后执行
+    batch: Option<std::result::Result<BatchQueryResult, CompileError>>,
+}
+
+/// 处理单个Filter字符串的核心逻辑：分词 → 解析 → 编译 → 批量编译
+fn run_pipeline(compiler: &mut SqlCompiler, filter_string: &str, entity: &str) -> PipelineOutcome {
+    let tokens: Vec<_> = Lexer::new(filter_string).collect();
+    let token_count = tokens.len();
+
+    let ast = Parser::new(&tokens).parse();
+    let (compile, batch) = match &ast {
+        Ok(ast) => {
+            let compile = compiler.compile_optimized(ast.clone(), entity);
+            let batch = compile.is_ok().then(|| compiler.compile_batch_query(ast.clone(), entity));
+            (Some(compile), batch)
         }
-        Err(_) => SqlCompiler::new(),
+        Err(_) => (None, None),
+    };
+
+    PipelineOutcome {
+        token_count,
+        ast,
+        compile,
+        batch,
     }
 }
 
-/// 处理单个Filter字符串的核心逻辑
-fn process_filter_string(compiler: &mut SqlCompiler, filter_string: &str) {
+/// 打印流水线各步骤的结果
+fn print_pipeline_outcome(filter_string: &str, outcome: &PipelineOutcome) {
     println!("\n[输入 DSL]:\n{}\n", filter_string);
 
     println!("[步骤 1]: 对 DSL 进行分词...");
-    let tokens: Vec<_> = Lexer::new(filter_string).collect();
-    println!("生成了 {} 个 token", tokens.len());
+    println!("生成了 {} 个 token", outcome.token_count);
     
     println!("\n[步骤 2]: 将 token 解析为 AST...");
-    let mut parser = Parser::new(&tokens);
-    match parser.parse() {
-        Ok(ast) => {
-            println!("✓ 成功将 DSL 解析为 AST");
+    if let Err(e) = &outcome.ast {
+        println!("✗ 解析失败: {}", e.message);
+        if let Some(span) = e.span {
+            println!("  位置 {}-{}", span.start, span.end);
+        }
+        return;
+    }
+    println!("✓ 成功将 DSL 解析为 AST");
 
-            println!("\n[步骤 3]: 将 AST 编译为 SQL...");
+    println!("\n[步骤 3]: 将 AST 编译为 SQL...");
+    match &outcome.compile {
+        Some(Ok(result)) => {
+            println!("✅ 成功编译为 SQL");
+            println!("\n[生成的 SQL]:");
+            println!("{}", result.pretty_sql());
             
-            match compiler.compile_optimized(ast.clone(), "Issue") {
-                Ok(result) => {
-                    println!("✅ 成功编译为 SQL");
-                    println!("\n[生成的 SQL]:");
-                    println!("{}", result.sql);
-                    
-                    if !result.optimizations.is_empty() {
-                        println!("\n[应用的优化]:");
-                        for opt in &result.optimizations {
-                            println!("• {:?}", opt);
-                        }
-                    }
-
-                    println!("\n[步骤 4]: 演示批量查询编译...");
-                    
-                    match compiler.compile_batch_query(ast, "Issue") {
-                        Ok(batch_result) => {
-                            println!("✓ 批量编译完成");
-                            println!("生成了 {} 个 SQL 查询", batch_result.queries.len());
-                            
-                            if let Some(estimated_rows) = batch_result.total_estimated_rows {
-                                println!("预计处理的总行数: {}", estimated_rows);
-                            }
-                            
-                            if batch_result.queries.len() > 1 {
-                                println!("\n[批量查询]:");
-                                for (i, query) in batch_result.queries.iter().enumerate() {
-                                    println!("批次 {}: {}", i + 1, query);
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            println!("✗ 批量编译失败: {}", e.message);
-                        }
-                    }
+            if !result.optimizations.is_empty() {
+                println!("\n[应用的优化]:");
+                for opt in &result.optimizations {
+                    println!("• {}", opt.describe());
                 }
-                Err(e) => {
-                    println!("✗ SQL 编译失败: {}", e.message);
+            }
+
+            if !result.diagnostics.is_empty() {
+                println!("\n[诊断]:");
+                for diagnostic in &result.diagnostics {
+                    println!("⚠️ {}", diagnostic.message);
                 }
             }
         }
-        Err(e) => {
-            println!("✗ 解析失败: {}", e.message);
-            if let Some(span) = e.span {
-                println!("  位置 {}-{}", span.start, span.end);
+        Some(Err(e)) => {
+            println!("✗ SQL 编译失败: {}", e.message);
+            return;
+        }
+        None => return,
+    }
+
+    println!("\n[步骤 4]: 演示批量查询编译...");
+    match &outcome.batch {
+        Some(Ok(batch_result)) => {
+            println!("✓ 批量编译完成");
+            println!("生成了 {} 个 SQL 查询", batch_result.queries.len());
+            
+            if let Some(estimated_rows) = batch_result.total_estimated_rows {
+                println!("预计处理的总行数: {}", estimated_rows);
+            }
+            
+            if batch_result.queries.len() > 1 {
+                println!("\n[批量查询]:");
+                for (i, query) in batch_result.queries.iter().enumerate() {
+                    println!("批次 {}: {}", i + 1, query);
+                }
             }
         }
+        Some(Err(e)) => {
+            println!("✗ 批量编译失败: {}", e.message);
+        }
+        None => {}
     }
 }
 
@@ -132,7 +180,8 @@ fn main() -> Result<()> {
 
                 rl.add_history_entry(input)?;
                 
-                process_filter_string(&mut compiler, input);
+                let outcome = run_pipeline(&mut compiler, input, "Issue");
+                print_pipeline_outcome(input, &outcome);
             }
             Err(ReadlineError::Interrupted) => {
                 println!("接收到 Ctrl-C，正在退出...");
@@ -150,4 +199,37 @@ fn main() -> Result<()> {
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_pipeline_simple_filter() {
+        let mut compiler = SqlCompiler::new();
+        let outcome = run_pipeline(&mut compiler, r#"Filter: status["Open"]"#, "Issue");
+
+        assert_eq!(outcome.token_count, 5);
+
+        let ast = outcome.ast.unwrap();
+        assert_eq!(ast.base_filters.len(), 1);
+        assert_eq!(ast.base_filters[0].field.0, "status");
+
+        let compiled = outcome.compile.unwrap().unwrap();
+        assert!(compiled.sql.contains(r#""issue"."status" = 'Open'"#));
+
+        let batch = outcome.batch.unwrap().unwrap();
+        assert_eq!(batch.queries, vec![compiled.sql]);
+    }
+
+    #[test]
+    fn test_run_pipeline_parse_error() {
+        let mut compiler = SqlCompiler::new();
+        let outcome = run_pipeline(&mut compiler, r#"Filter: status["Open""#, "Issue");
+
+        assert!(outcome.ast.is_err());
+        assert!(outcome.compile.is_none());
+        assert!(outcome.batch.is_none());
+    }
+}
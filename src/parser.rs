@@ -12,11 +12,20 @@
 //!   │   │                     ├─ parse_condition()
 //!   │   │                     └─ 期望 ']'
 //!   │   │
-//!   │   └─ "CrossFilter:" → parse_cross_filter()
-//!   │                        ├─ 期望 '<'
-//!   │                        ├─ 解析实体名 Source-Target
-//!   │                        ├─ 期望 '>'
-//!   │                        └─ parse_field_filters_until_semicolon_or_crossfilter()
+//!   │   ├─ "CrossFilter:" → parse_cross_filter()
+//!   │   │                    ├─ 期望 '<'
+//!   │   │                    ├─ 解析实体名 Source-[Via-...-]Target
+//!   │   │                    ├─ 期望 '>'
+//!   │   │                    └─ parse_field_filters_until_semicolon_or_crossfilter()
+//!   │   │
+//!   │   ├─ "Sample:" → parse_sample()
+//!   │   │               ├─ 期望数字
+//!   │   │               └─ 期望 '%'
+//!   │   │
+//!   │   ├─ "Sort:" → parse_sort()
+//!   │   │             └─ 逗号分隔的 parse_sort_expr() [ASC|DESC]
+//!   │   │
+//!   │   └─ "OR" + 段落关键字 → 开始新的段落级 OR 分支
 //!   │
 //!   └─ parse_condition() (递归下降解析)
 //!        └─ parse_or_expression()
@@ -24,9 +33,9 @@
 //!             │    ├─ parse_not_expression()
 //!             │    │    └─ parse_primary_expression()
 //!             │    │         ├─ "(" → 分组表达式 (递归调用parse_condition)
-//!             │    │         ├─ "IS" → IS NULL / IS NOT NULL
-//!             │    │         ├─ "IN" → IN (值列表)
-//!             │    │         ├─ 比较运算符 → 比较操作 + 字面值
+//!             │    │         ├─ "IS" → IS [NOT] NULL / IS [NOT] EMPTY
+//!             │    │         ├─ "IN" → IN (值列表，可包含 null)
+//!             │    │         ├─ 比较运算符 → 比较操作 + 字面值 / ANY (值列表) / ALL (值列表)
 //!             │    │         └─ 其他 → 默认相等比较 + 字面值
 //!             │    │
 //!             │    └─ 遇到AND时，继续解析右侧NOT表达式
@@ -49,15 +58,58 @@
 //! Filter: field_name[condition]
 //! ```
 //!
+//! 不同字段之间默认以 AND 连接，需要 OR 时用括号分组：
+//! ```text
+//! Filter: (status["Open"] OR priority[>5]); type["Bug"]
+//! ```
+//!
+//! 字段名包含空格或与关键字冲突时可以用反引号包围：
+//! ```text
+//! Filter: `Due Date`[>today]
+//! ```
+//!
+//! 过滤宏在编译时按实体配置展开为若干字段Filter，只能出现在 `Filter:` 段落中：
+//! ```text
+//! Filter: MINE_RECENTLY; status["Open"]
+//! ```
+//!
 //! ### 交叉过滤器
 //! ```text
 //! CrossFilter: <Source-Target> field_name[condition]
+//! CrossFilter: <Source-Via-Target> field_name[condition]
+//! ```
+//!
+//! 实体名包含两个以上部分时表示多跳关联链，N 个实体生成 N-1 个 JOIN。
+//! 实体名本身包含连字符时用双引号包围，例如 `<"Test-Case"-Run>`。
+//!
+//! ### 段落级 OR
+//! ```text
+//! Filter: status["Open"]; priority[>5] OR CrossFilter: <Test-Run> result["FAIL"]
 //! ```
 //!
+//! 段落之间的 `;` 优先级高于 `OR`，上例等价于 `(status AND priority) OR (关联 Run 的 result)`。
+//!
+//! ### 采样子句
+//! ```text
+//! Sample: 10%
+//! ```
+//!
+//! ### 排序子句
+//! ```text
+//! Sort: priority * 2 DESC, name
+//! ```
+//!
+//! 排序键是由主实体字段、整数和 `+ - * /` 组成的表达式，可以用括号分组。
+//! 字段名中可以包含连字符，因此减号两侧需要空格，例如 `estimated - actual`
+//!
 //! ### 条件表达式
-//! - **比较操作**: `=`, `!=`, `>`, `<`, `>=`, `<=`
+//! - **比较操作**: `=`, `!=`, `>`, `<`, `>=`, `<=`, `<=>` (NULL 安全的相等比较)
 //! - **空值检查**: `IS NULL`, `IS NOT NULL`
+//! - **空字符串检查**: `IS EMPTY` (空字符串或 NULL), `IS NOT EMPTY`
 //! - **列表包含**: `IN (value1, value2, ...)`
+//! - **子查询包含**: `field[IN (CrossFilter: <Entity> field[condition])]`
+//! - **元组包含**: `(field1, field2)[IN ((v1, v2), (v3, v4))]`
+//! - **量化比较**: `= ANY (v1, v2, ...)`, `> ALL (v1, v2, ...)`（仅 PostgreSQL）
 //! - **逻辑操作**: `AND`, `OR`, `NOT`
 //! - **分组**: `(expression)`
 //!
@@ -65,7 +117,9 @@
 //! - **字符串**: `"quoted string"` 或 `unquoted_identifier`
 //! - **数字**: `123`, `-456`
 //! - **日期关键字**: `today`, `yesterday`, `tomorrow`
+//! - **相对日期**: `today-7`, `today + 1`, `today-7d`（偏移单位为天）
 //! - **用户关键字**: `current_user`
+//! - **列引用**: `@column`，与同一实体的另一列比较，例如 `actual[>@estimated]`
 //! - **空值**: `null`
 //!
 //! ## 解析示例
@@ -80,16 +134,110 @@
 //! // 交叉过滤
 //! CrossFilter: <Test-Run> result["PASS"]
 //!
+//! // 多跳关联
+//! CrossFilter: <Test-Run-Step> result["PASS"]
+//!
 //! // 混合查询
 //! Filter: assignee[current_user]; CrossFilter: <Bug-Fix> priority[>=3]
+//!
+//! // 采样查询
+//! Filter: status["Open"]; Sample: 10%
 //! ```
 
-use crate::ast::{Query, FieldFilter, CrossFilter, Condition, Identifier, CompOp, Literal};
+use crate::ast::{Query, FieldFilter, CrossFilter, Condition, Identifier, CompOp, Literal, SortKey, SortExpr, ArithOp, FilterMacro};
+use crate::lexer::Lexer;
 use crate::token::{Token, TokenKind, Span};
+use std::str::FromStr;
+
+/// 一个段落中解析出的字段Filter、OR 分组和过滤宏
+type SegmentFilters = (Vec<FieldFilter>, Vec<Vec<FieldFilter>>, Vec<FilterMacro>);
 
 pub struct Parser<'a> {
     tokens: &'a [Token<'a>],
     position: usize,
+    config: ParserConfig,
+    /// 最近一次解析中引用的实体及其在源文本中的位置
+    entity_spans: Vec<(Identifier, Span)>,
+}
+
+/// 带长度上限的值列表，超出上限后只计数不再保留值
+struct BoundedList<T> {
+    limit: usize,
+    items: Vec<T>,
+    len: usize,
+    /// 第一个超出上限的值的位置
+    overflow: Option<Option<Span>>,
+}
+
+impl<T> BoundedList<T> {
+    fn new(limit: usize) -> Self {
+        Self { limit, items: Vec::new(), len: 0, overflow: None }
+    }
+
+    fn push(&mut self, item: T, span: Option<Span>) {
+        self.len += 1;
+        if self.len <= self.limit {
+            self.items.push(item);
+        } else if self.overflow.is_none() {
+            self.overflow = Some(span);
+        }
+    }
+
+    /// 返回保留的值，超出上限时报告总数和上限，错误位置为第一个超出上限的值
+    fn finish(self, kind: &str) -> Result<Vec<T>, ParseError> {
+        match self.overflow {
+            None => Ok(self.items),
+            Some(span) => Err(ParseError::new(
+                format!("{} has {} values, more than the limit of {}", kind, self.len, self.limit),
+                span,
+            )),
+        }
+    }
+}
+
+/// IN 值列表默认的最大长度
+pub const DEFAULT_MAX_IN_LIST_LEN: usize = 100_000;
+
+/// 语法分析器配置
+#[derive(Debug, Clone)]
+pub struct ParserConfig {
+    /// 严格模式下值位置不允许使用不带引号的标识符，例如 `status[Opne]` 会报错而不是当作字符串 `"Opne"`
+    pub strict_values: bool,
+    /// IN 值列表除逗号外还接受 `|` 或空白 (包括换行) 分隔，例如 `IN ("a" | "b")`，便于粘贴导出的列表
+    pub relaxed_in_separators: bool,
+    /// 不带运算符的字符串值的匹配方式，例如 `status["Open"]`；数字等其他值总是相等比较
+    pub default_match: DefaultMatch,
+    /// `=` / `!=` 后面的括号值列表是否按 `IN` / `NOT IN` 解析，例如 `tags[= ("a", "b")]`
+    ///
+    /// 未启用时报错并提示改用 `IN`
+    pub list_comparison_as_in: bool,
+    /// 值列表的最大长度，默认为 [`DEFAULT_MAX_IN_LIST_LEN`]
+    ///
+    /// 适用于 IN 值列表 (包括按 IN 解析的 `= (...)`)、`ANY (...)` / `ALL (...)` 的值列表和元组 IN 的行数。
+    /// 超出上限后剩余的值仍会解析以统计总数，但不再保留，避免粘贴的超大列表占用过多内存
+    pub max_in_list_len: usize,
+}
+
+impl Default for ParserConfig {
+    fn default() -> Self {
+        Self {
+            strict_values: false,
+            relaxed_in_separators: false,
+            default_match: DefaultMatch::default(),
+            list_comparison_as_in: false,
+            max_in_list_len: DEFAULT_MAX_IN_LIST_LEN,
+        }
+    }
+}
+
+/// 不带运算符的字符串值的默认匹配方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DefaultMatch {
+    /// 相等比较，`status["Open"]` 等价于 `status[="Open"]`
+    #[default]
+    Equals,
+    /// 包含子串，`status["Open"]` 等价于 `status[LIKE "%Open%"]`，值中的通配符按字面匹配
+    Contains,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -108,14 +256,58 @@ impl ParseError {
     }
 }
 
+/// 生成包含 `value` 子串的 LIKE 模式，转义值中的 `\`、`%`、`_`
+fn contains_pattern(value: &str) -> String {
+    let mut pattern = String::with_capacity(value.len() + 2);
+    pattern.push('%');
+    for c in value.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+/// 直接从 DSL 文本解析查询，例如 `"Filter: status[\"Open\"]".parse::<Query>()`
+impl FromStr for Query {
+    type Err = ParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let tokens: Vec<_> = Lexer::new(input).collect();
+        Parser::new(&tokens).parse()
+    }
+}
+
 impl<'a> Parser<'a> {
     pub fn new(tokens: &'a [Token<'a>]) -> Self {
+        Self::with_config(tokens, ParserConfig::default())
+    }
+
+    pub fn with_config(tokens: &'a [Token<'a>], config: ParserConfig) -> Self {
         Self {
             tokens,
             position: 0,
+            config,
+            entity_spans: Vec::new(),
         }
     }
 
+    /// 绑定新的 token 序列并回到起始位置，保留原有配置，便于在循环中复用同一个解析器
+    pub fn reset(&mut self, tokens: &'a [Token<'a>]) {
+        self.tokens = tokens;
+        self.position = 0;
+        self.entity_spans.clear();
+    }
+
+    /// 最近一次解析中关联Filter和子查询引用的每个实体及其在源文本中的位置，按出现顺序排列
+    ///
+    /// 位置只覆盖实体名本身，不包括引号和连字符，便于编辑器实现跳转到定义
+    pub fn entity_spans(&self) -> &[(Identifier, Span)] {
+        &self.entity_spans
+    }
+
     /// 返回当前 token，不推进位置
     fn peek(&self) -> Option<&Token<'a>> {
         self.tokens.get(self.position)
@@ -151,6 +343,27 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// 期望与 `open_span` 处的左括号配对的 `closing` (`)` 或 `]`) 并推进
+    ///
+    /// 缺少右括号时错误信息指出左括号的位置，而不是笼统的 "reached end of input"
+    fn expect_closing(&mut self, closing: TokenKind, open_span: Span) -> Result<&Token<'a>, ParseError> {
+        let (open, close) = if closing == TokenKind::RBracket { ('[', ']') } else { ('(', ')') };
+        match self.peek() {
+            Some(token) if token.kind == closing => Ok(self.advance().unwrap()),
+            Some(token) => Err(ParseError::at_position(
+                format!(
+                    "Expected '{}' to close '{}' opened at position {}, found {:?}",
+                    close, open, open_span.start, token.kind
+                ),
+                token.span,
+            )),
+            None => Err(ParseError::at_position(
+                format!("Unclosed '{}' opened at position {}", open, open_span.start),
+                open_span,
+            )),
+        }
+    }
+
     /// 检查当前 token 是否匹配给定类型
     fn match_token(&self, kind: &TokenKind) -> bool {
         if let Some(token) = self.peek() {
@@ -165,27 +378,69 @@ impl<'a> Parser<'a> {
         if let Some(token) = self.peek() {
             matches!(token.kind, 
                 TokenKind::Eq | TokenKind::NotEq | TokenKind::Gt | 
-                TokenKind::Lt | TokenKind::Gte | TokenKind::Lte)
+                TokenKind::Lt | TokenKind::Gte | TokenKind::Lte | TokenKind::NullSafeEq)
         } else {
             false
         }
     }
 
     pub fn parse(&mut self) -> Result<Query, ParseError> {
-        let mut base_filters = Vec::new();
-        let mut cross_filters = Vec::new();
+        // 以段落级 OR 分隔的各个分支，`branch` 为正在解析的分支
+        let mut branches = Vec::new();
+        let mut branch = Query::default();
+        let mut sample_percent = None;
+        let mut sort: Option<Vec<SortKey>> = None;
+        self.entity_spans.clear();
 
         while let Some(token) = self.peek() {
             match &token.kind {
                 TokenKind::Filter => {
+                    let segment_span = token.span;
                     self.advance(); // 消费 "Filter:"
-                    let filters = self.parse_field_filters_until_semicolon_or_crossfilter()?;
-                    base_filters.extend(filters);
+                    let (filters, or_groups, macros) = self.parse_field_filters_until_semicolon_or_crossfilter(segment_span)?;
+                    branch.base_filters.extend(filters);
+                    branch.base_or_groups.extend(or_groups);
+                    branch.macros.extend(macros);
                 }
                 TokenKind::CrossFilter => {
+                    let segment_span = token.span;
                     self.advance(); // 消费 "CrossFilter:"
-                    let cross_filter = self.parse_cross_filter()?;
-                    cross_filters.push(cross_filter);
+                    let cross_filter = self.parse_cross_filter(segment_span)?;
+                    branch.cross_filters.push(cross_filter);
+                }
+                TokenKind::Or if self.is_segment_or() => {
+                    if branch.base_filters.is_empty() && branch.base_or_groups.is_empty() && branch.cross_filters.is_empty()
+                        && branch.macros.is_empty()
+                    {
+                        return Err(ParseError::at_position(
+                            "Segment-level OR must follow a Filter or CrossFilter segment".to_string(),
+                            token.span,
+                        ));
+                    }
+                    self.advance(); // 消费 OR
+                    branches.push(std::mem::take(&mut branch));
+                }
+                TokenKind::Sample => {
+                    let sample_span = token.span;
+                    self.advance(); // 消费 "Sample:"
+                    if sample_percent.is_some() {
+                        return Err(ParseError::at_position(
+                            "Duplicate Sample clause".to_string(),
+                            sample_span,
+                        ));
+                    }
+                    sample_percent = Some(self.parse_sample()?);
+                }
+                TokenKind::Sort => {
+                    let sort_span = token.span;
+                    self.advance(); // 消费 "Sort:"
+                    if sort.is_some() {
+                        return Err(ParseError::at_position(
+                            "Duplicate Sort clause".to_string(),
+                            sort_span,
+                        ));
+                    }
+                    sort = Some(self.parse_sort(sort_span)?);
                 }
                 _ => {
                     return Err(ParseError::at_position(
@@ -196,38 +451,201 @@ impl<'a> Parser<'a> {
             }
         }
 
-        Ok(Query {
-            base_filters,
-            cross_filters,
-        })
+        branches.push(branch);
+        let mut branches = branches.into_iter();
+        let mut query = branches.next().unwrap_or_default();
+        query.or_branches = branches.collect();
+        query.sample_percent = sample_percent;
+        query.sort = sort.unwrap_or_default();
+        Ok(query)
+    }
+
+    /// 检查当前位置是否为段落级 OR：`OR` 后紧跟 `Filter:` 或 `CrossFilter:`
+    fn is_segment_or(&self) -> bool {
+        self.match_token(&TokenKind::Or)
+            && matches!(
+                self.tokens.get(self.position + 1).map(|t| &t.kind),
+                Some(TokenKind::Filter | TokenKind::CrossFilter)
+            )
+    }
+
+    /// 检查当前 token 是否为段落关键字 (Filter:、CrossFilter:、Sample:、Sort:)
+    fn is_segment_start(&self) -> bool {
+        matches!(
+            self.peek().map(|t| &t.kind),
+            Some(TokenKind::Filter | TokenKind::CrossFilter | TokenKind::Sample | TokenKind::Sort)
+        )
+    }
+
+    /// 解析采样子句 `Sample: 10%`，返回采样百分比
+    fn parse_sample(&mut self) -> Result<u32, ParseError> {
+        let number_token = self.expect(TokenKind::Number(0))?;
+        let span = number_token.span;
+        let percent = if let TokenKind::Number(n) = number_token.kind {
+            n
+        } else {
+            return Err(ParseError::at_position(
+                "Expected sample percentage".to_string(),
+                span,
+            ));
+        };
+        self.expect(TokenKind::Percent)?;
+
+        if !(1..=100).contains(&percent) {
+            return Err(ParseError::at_position(
+                format!("Sample percentage must be between 1 and 100, found {}", percent),
+                span,
+            ));
+        }
+
+        // 采样子句后允许可选的分号
+        if self.match_token(&TokenKind::Semicolon) {
+            self.advance();
+        }
+
+        Ok(percent as u32)
+    }
+
+    /// 解析排序子句 `Sort: expr [ASC|DESC], ...`，`sort_span` 为关键字的位置，没有排序键时用于报告错误
+    fn parse_sort(&mut self, sort_span: Span) -> Result<Vec<SortKey>, ParseError> {
+        if self.peek().is_none() || self.match_token(&TokenKind::Semicolon) || self.is_segment_start() {
+            return Err(ParseError::at_position(
+                "Empty Sort clause: expected at least one sort key".to_string(),
+                sort_span,
+            ));
+        }
+
+        let mut keys = Vec::new();
+        loop {
+            let expr = self.parse_sort_expr()?;
+            let descending = match self.peek().map(|t| &t.kind) {
+                Some(TokenKind::Identifier(order)) if order.eq_ignore_ascii_case("DESC") => {
+                    self.advance();
+                    true
+                }
+                Some(TokenKind::Identifier(order)) if order.eq_ignore_ascii_case("ASC") => {
+                    self.advance();
+                    false
+                }
+                _ => false,
+            };
+            keys.push(SortKey { expr, descending });
+
+            if !self.match_token(&TokenKind::Comma) {
+                break;
+            }
+            self.advance(); // 消费逗号
+        }
+
+        // 排序子句后允许可选的分号
+        if self.match_token(&TokenKind::Semicolon) {
+            self.advance();
+        }
+
+        Ok(keys)
+    }
+
+    /// 解析排序表达式中的加减运算 (较低优先级)
+    fn parse_sort_expr(&mut self) -> Result<SortExpr, ParseError> {
+        let mut left = self.parse_sort_term()?;
+        loop {
+            let op = match self.peek().map(|t| &t.kind) {
+                Some(TokenKind::Plus) => ArithOp::Add,
+                Some(TokenKind::Dash) => ArithOp::Sub,
+                _ => break,
+            };
+            self.advance();
+            let right = self.parse_sort_term()?;
+            left = SortExpr::Binary { op, left: Box::new(left), right: Box::new(right) };
+        }
+        Ok(left)
+    }
+
+    /// 解析排序表达式中的乘除运算 (较高优先级)
+    fn parse_sort_term(&mut self) -> Result<SortExpr, ParseError> {
+        let mut left = self.parse_sort_factor()?;
+        loop {
+            let op = match self.peek().map(|t| &t.kind) {
+                Some(TokenKind::Star) => ArithOp::Mul,
+                Some(TokenKind::Slash) => ArithOp::Div,
+                _ => break,
+            };
+            self.advance();
+            let right = self.parse_sort_factor()?;
+            left = SortExpr::Binary { op, left: Box::new(left), right: Box::new(right) };
+        }
+        Ok(left)
+    }
+
+    /// 解析排序表达式的操作数：字段、整数 (可带负号) 或括号分组
+    fn parse_sort_factor(&mut self) -> Result<SortExpr, ParseError> {
+        let Some(token) = self.advance().cloned() else {
+            return Err(ParseError::new("Unexpected end of input in Sort clause".to_string(), None));
+        };
+        match token.kind {
+            TokenKind::Identifier(name) => Ok(SortExpr::Field(Identifier(name.to_string()))),
+            TokenKind::Number(n) => Ok(SortExpr::Number(n)),
+            TokenKind::Dash if matches!(self.peek().map(|t| &t.kind), Some(TokenKind::Number(_))) => {
+                match self.advance().map(|t| &t.kind) {
+                    Some(TokenKind::Number(n)) => Ok(SortExpr::Number(-n)),
+                    _ => unreachable!("已检查下一个 token 为数字"),
+                }
+            }
+            TokenKind::LParen => {
+                let expr = self.parse_sort_expr()?;
+                self.expect_closing(TokenKind::RParen, token.span)?;
+                Ok(expr)
+            }
+            other => Err(ParseError::at_position(
+                format!("Expected a field, number or '(' in Sort expression, found {:?}", other),
+                token.span,
+            )),
+        }
     }
 
     /// 解析字段Filter，直到遇到分号、CrossFilter 或输入结束
-    fn parse_field_filters_until_semicolon_or_crossfilter(&mut self) -> Result<Vec<FieldFilter>, ParseError> {
+    ///
+    /// `segment_span` 为段落关键字的位置，段落中没有任何字段Filter时用于报告错误。
+    /// 返回普通字段Filter以及括号包围、以 OR 连接的字段Filter分组
+    fn parse_field_filters_until_semicolon_or_crossfilter(&mut self, segment_span: Span) -> Result<SegmentFilters, ParseError> {
+        if self.peek().is_none() || self.match_token(&TokenKind::Semicolon) || self.is_segment_start() {
+            return Err(ParseError::at_position(
+                "Empty filter segment: expected at least one field filter".to_string(),
+                segment_span,
+            ));
+        }
+
         let mut filters = Vec::new();
+        let mut or_groups = Vec::new();
+        let mut macros = Vec::new();
 
         loop {
-            // 解析一个字段Filter
-            let filter = self.parse_field_filter()?;
-            filters.push(filter);
+            // 解析一个字段Filter、一个 OR 分组或一个过滤宏
+            if let Some(filter_macro) = self.peek_filter_macro() {
+                self.advance(); // 消费宏关键字
+                macros.push(filter_macro);
+            } else if self.is_or_group_start() {
+                or_groups.push(self.parse_or_group()?);
+            } else {
+                filters.push(self.parse_field_filter()?);
+            }
 
             // 检查是否需要继续
             if let Some(token) = self.peek() {
                 match &token.kind {
                     TokenKind::Semicolon => {
                         self.advance(); // 消费分号
-                        // 检查下一个 token 是否为 CrossFilter 或输入结束
-                        if let Some(next_token) = self.peek() {
-                            if matches!(next_token.kind, TokenKind::CrossFilter) {
-                                break; // 基础Filter结束
-                            }
-                            // 否则继续解析更多字段Filter
-                        } else {
-                            break; // 输入结束
+                        // 检查下一个 token 是否为新的段落、段落级 OR 或输入结束
+                        if self.peek().is_none() || self.is_segment_start() || self.is_segment_or() {
+                            break; // 当前段落结束
                         }
+                        // 否则继续解析更多字段Filter
+                    }
+                    TokenKind::Filter | TokenKind::CrossFilter | TokenKind::Sample | TokenKind::Sort => {
+                        break; // 当前段落结束
                     }
-                    TokenKind::CrossFilter => {
-                        break; // 基础Filter结束
+                    TokenKind::Or if self.is_segment_or() => {
+                        break; // 当前段落结束，后面是段落级 OR
                     }
                     _ => {
                         return Err(ParseError::at_position(
@@ -241,49 +659,154 @@ impl<'a> Parser<'a> {
             }
         }
 
-        Ok(filters)
+        Ok((filters, or_groups, macros))
     }
 
-    fn parse_cross_filter(&mut self) -> Result<CrossFilter, ParseError> {
-        // 期望 <Source-Target>
-        self.expect(TokenKind::Lt)?;
-        
-        let entity_token = self.expect(TokenKind::Identifier(""))?;
-        let entity_name = if let TokenKind::Identifier(name) = &entity_token.kind {
-            name
-        } else {
-            return Err(ParseError::at_position(
-                "Expected entity identifier".to_string(),
-                entity_token.span,
-            ));
-        };
+    /// 当前 token 是否为过滤宏关键字，后面紧跟 `[` 时仍是同名字段
+    fn peek_filter_macro(&self) -> Option<FilterMacro> {
+        match self.tokens.get(self.position).map(|t| &t.kind) {
+            Some(TokenKind::Identifier(name)) if !matches!(
+                self.tokens.get(self.position + 1).map(|t| &t.kind),
+                Some(TokenKind::LBracket)
+            ) => FilterMacro::from_keyword(name),
+            _ => None,
+        }
+    }
 
-        // 按连字符分割实体名称，获取源和目标
-        let parts: Vec<&str> = entity_name.split('-').collect();
-        if parts.len() != 2 {
-            return Err(ParseError::at_position(
-                format!("Entity identifier '{}' must be in format 'Source-Target'", entity_name),
-                entity_token.span,
-            ));
+    /// 检查当前位置是否为 OR 分组的开始：`(` 后紧跟 `field[`，以区别于元组字段列表 `(a, b)`
+    fn is_or_group_start(&self) -> bool {
+        matches!(
+            (
+                self.tokens.get(self.position).map(|t| &t.kind),
+                self.tokens.get(self.position + 1).map(|t| &t.kind),
+                self.tokens.get(self.position + 2).map(|t| &t.kind),
+            ),
+            (Some(TokenKind::LParen), Some(TokenKind::Identifier(_)), Some(TokenKind::LBracket))
+        )
+    }
+
+    /// 解析以 OR 连接的字段Filter分组，例如 `(status["Open"] OR priority[>5])`
+    fn parse_or_group(&mut self) -> Result<Vec<FieldFilter>, ParseError> {
+        let open_span = self.expect(TokenKind::LParen)?.span;
+
+        let mut group = vec![self.parse_field_filter()?];
+        while self.match_token(&TokenKind::Or) {
+            self.advance(); // 消费 OR
+            group.push(self.parse_field_filter()?);
         }
+        self.expect_closing(TokenKind::RParen, open_span)?;
+
+        Ok(group)
+    }
 
-        let source_entity = Identifier(parts[0].to_string());
-        let target_entity = Identifier(parts[1].to_string());
+    fn parse_cross_filter(&mut self, segment_span: Span) -> Result<CrossFilter, ParseError> {
+        // 期望 <Source-Target> 或 <Source-Via-...-Target>
+        let open_span = self.expect(TokenKind::Lt)?.span;
+        let parts = self.parse_entity_path(open_span)?;
+        self.entity_spans.extend(parts.iter().cloned());
+
+        let source_entity = parts[0].0.clone();
+        let target_entity = parts[parts.len() - 1].0.clone();
+        let intermediate_entities = parts[1..parts.len() - 1].iter()
+            .map(|(part, _)| part.clone())
+            .collect();
 
         self.expect(TokenKind::Gt)?;
 
         // 解析关联Filter的字段Filter
-        let filters = self.parse_field_filters_until_semicolon_or_crossfilter()?;
+        let (filters, or_groups, macros) = self.parse_field_filters_until_semicolon_or_crossfilter(segment_span)?;
+        if !or_groups.is_empty() {
+            return Err(ParseError::at_position(
+                "OR groups are only supported in Filter segments".to_string(),
+                segment_span,
+            ));
+        }
+        if let Some(filter_macro) = macros.first() {
+            return Err(ParseError::at_position(
+                format!("{} is only supported in Filter segments", filter_macro.keyword()),
+                segment_span,
+            ));
+        }
 
         Ok(CrossFilter {
             source_entity,
+            intermediate_entities,
             target_entity,
             filters,
         })
     }
 
+    /// 解析 `<...>` 中以连字符分隔的实体路径，返回各实体名称及其位置 (至少两个)
+    ///
+    /// 实体名本身包含连字符时可以用双引号包围，例如 `<"Test-Case"-Run>`
+    fn parse_entity_path(&mut self, open_span: Span) -> Result<Vec<(Identifier, Span)>, ParseError> {
+        let mut parts: Vec<(Identifier, Span)> = Vec::new();
+        let mut text = String::new();
+        // 是否期待下一个实体名 (路径开头或刚遇到分隔符)
+        let mut expect_part = true;
+        let mut well_formed = true;
+
+        while let Some(token) = self.peek() {
+            match &token.kind {
+                TokenKind::Gt => break,
+                TokenKind::Identifier(name) => {
+                    // 未加引号的标识符可能包含多个以连字符连接的实体名
+                    text.push_str(name);
+                    // 反引号标识符的内容从开始反引号之后算起
+                    let mut offset = token.span.end - name.len() - usize::from(token.span.end - token.span.start != name.len());
+                    for (i, piece) in name.split('-').enumerate() {
+                        if i > 0 {
+                            well_formed &= !expect_part;
+                            expect_part = true;
+                            offset += 1;
+                        }
+                        if !piece.is_empty() {
+                            well_formed &= expect_part;
+                            parts.push((Identifier(piece.to_string()), Span::new(offset, offset + piece.len())));
+                            expect_part = false;
+                        }
+                        offset += piece.len();
+                    }
+                }
+                TokenKind::String { value: name, raw } => {
+                    text.push_str(raw);
+                    well_formed &= expect_part && !name.is_empty();
+                    let start = token.span.start + 1;
+                    parts.push((Identifier(name.to_string()), Span::new(start, start + name.len())));
+                    expect_part = false;
+                }
+                TokenKind::Dash => {
+                    text.push('-');
+                    well_formed &= !expect_part;
+                    expect_part = true;
+                }
+                _ => {
+                    return Err(ParseError::at_position(
+                        format!("Expected entity identifier, found {:?}", token.kind),
+                        token.span,
+                    ));
+                }
+            }
+            self.advance();
+        }
+
+        if !well_formed || expect_part || parts.len() < 2 {
+            return Err(ParseError::at_position(
+                format!("Entity identifier '{}' must be in format 'Source-Target' or 'Source-Via-Target'", text),
+                open_span,
+            ));
+        }
+
+        Ok(parts)
+    }
+
     fn parse_field_filter(&mut self) -> Result<FieldFilter, ParseError> {
+        if self.match_token(&TokenKind::LParen) {
+            return self.parse_tuple_field_filter();
+        }
+
         let field_token = self.expect(TokenKind::Identifier(""))?;
+        let start = field_token.span.start;
         let field = if let TokenKind::Identifier(name) = &field_token.kind {
             Identifier(name.to_string())
         } else {
@@ -293,11 +816,157 @@ impl<'a> Parser<'a> {
             ));
         };
 
-        self.expect(TokenKind::LBracket)?;
+        let open_span = self.expect(TokenKind::LBracket)?.span;
+
+        // 空的 `[]` 没有明确含义，不默认视为匹配任意值；需要匹配任意值时直接省略该字段Filter
+        if let Some(close) = self.peek().filter(|t| t.kind == TokenKind::RBracket) {
+            return Err(ParseError::at_position(
+                format!("Empty condition for field '{}'", field.0),
+                Span::new(open_span.start, close.span.end),
+            ));
+        }
+
         let condition = self.parse_condition()?;
-        self.expect(TokenKind::RBracket)?;
+        let end = self.expect_closing(TokenKind::RBracket, open_span)?.span.end;
+
+        Ok(FieldFilter {
+            field,
+            condition,
+            span: Some(Span::new(start, end)),
+        })
+    }
+
+    /// 解析元组字段Filter，例如 `(a, b)[IN ((1, "x"), (2, "y"))]`
+    fn parse_tuple_field_filter(&mut self) -> Result<FieldFilter, ParseError> {
+        let start_span = self.expect(TokenKind::LParen)?.span;
+
+        // 解析逗号分隔的字段列表
+        let fields = self.parse_identifier_list()?;
+        self.expect(TokenKind::RParen)?;
+
+        if fields.len() < 2 {
+            return Err(ParseError::at_position(
+                "Tuple field list must contain at least two fields".to_string(),
+                start_span,
+            ));
+        }
+
+        self.expect(TokenKind::LBracket)?;
+        self.expect(TokenKind::In)?;
+        self.expect(TokenKind::LParen)?;
+
+        // 解析逗号分隔的元组值列表，每个元组的长度必须与字段数一致
+        let mut rows = BoundedList::new(self.config.max_in_list_len);
+        self.parse_comma_separated(|parser| {
+            let row_span = parser.expect(TokenKind::LParen)?.span;
+            let row = parser.parse_comma_separated(Self::parse_literal)?;
+            parser.expect(TokenKind::RParen)?;
+
+            if row.len() != fields.len() {
+                return Err(ParseError::at_position(
+                    format!("Tuple has {} values, expected {}", row.len(), fields.len()),
+                    row_span,
+                ));
+            }
+            rows.push(row, Some(row_span));
+            Ok(())
+        })?;
+        let rows = rows.finish("IN tuple list")?;
+        self.expect(TokenKind::RParen)?;
+        let end = self.expect(TokenKind::RBracket)?.span.end;
+
+        let names: Vec<&str> = fields.iter().map(|f| f.0.as_str()).collect();
+        Ok(FieldFilter {
+            field: Identifier(format!("({})", names.join(", "))),
+            condition: Condition::InTuple { fields, rows },
+            span: Some(Span::new(start_span.start, end)),
+        })
+    }
+
+    /// 解析逗号分隔的列表，直到 `)` 为止 (不消费 `)`)
+    ///
+    /// 所有列表共用同一套规则：允许空列表，拒绝尾随逗号
+    fn parse_comma_separated<T>(
+        &mut self,
+        mut parse_item: impl FnMut(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Vec<T>, ParseError> {
+        let mut items = Vec::new();
+        if self.match_token(&TokenKind::RParen) {
+            return Ok(items);
+        }
+
+        loop {
+            items.push(parse_item(self)?);
+            if !self.match_token(&TokenKind::Comma) {
+                break;
+            }
+            let comma_span = self.advance().unwrap().span;
+            if self.match_token(&TokenKind::RParen) {
+                return Err(ParseError::at_position(
+                    "Trailing comma in list".to_string(),
+                    comma_span,
+                ));
+            }
+        }
+
+        Ok(items)
+    }
+
+    /// 解析 IN 的值列表，结束的 `)` 由调用方消费
+    ///
+    /// 默认以逗号分隔；启用 `relaxed_in_separators` 时值之间也可以用 `|` 分隔或只用空白分隔
+    fn parse_in_values(&mut self) -> Result<Vec<Literal>, ParseError> {
+        let mut values = BoundedList::new(self.config.max_in_list_len);
+        if !self.config.relaxed_in_separators {
+            self.parse_comma_separated(|parser| {
+                let span = parser.peek().map(|token| token.span);
+                let value = parser.parse_in_value()?;
+                values.push(value, span);
+                Ok(())
+            })?;
+            return values.finish("IN list");
+        }
+
+        while self.peek().is_some_and(|token| !matches!(token.kind, TokenKind::RParen | TokenKind::Eof)) {
+            let span = self.peek().map(|token| token.span);
+            let value = self.parse_in_value()?;
+            values.push(value, span);
+            if self.match_token(&TokenKind::Comma) || self.match_token(&TokenKind::Pipe) {
+                let separator_span = self.advance().unwrap().span;
+                if self.match_token(&TokenKind::RParen) {
+                    return Err(ParseError::at_position(
+                        "Trailing separator in list".to_string(),
+                        separator_span,
+                    ));
+                }
+            }
+        }
+        values.finish("IN list")
+    }
+
+    /// 解析 IN 列表中的单个值，除普通字面量外还接受 `null`
+    ///
+    /// 值的位置出现逗号说明列表以逗号开头或有连续的逗号，例如 `IN (, "a")`
+    fn parse_in_value(&mut self) -> Result<Literal, ParseError> {
+        if self.match_token(&TokenKind::Comma) {
+            return Err(ParseError::at_position(
+                "Unexpected ',' in value list".to_string(),
+                self.peek().unwrap().span,
+            ));
+        }
+        if self.match_token(&TokenKind::Null) {
+            self.advance();
+            return Ok(Literal::Null);
+        }
+        self.parse_literal()
+    }
 
-        Ok(FieldFilter { field, condition })
+    /// 解析逗号分隔的标识符列表，例如元组字段列表 `a, b`，结束的 `)` 由调用方消费
+    fn parse_identifier_list(&mut self) -> Result<Vec<Identifier>, ParseError> {
+        self.parse_comma_separated(|parser| match &parser.expect(TokenKind::Identifier(""))?.kind {
+            TokenKind::Identifier(name) => Ok(Identifier(name.to_string())),
+            _ => unreachable!("expect 已检查 token 类型"),
+        })
     }
 
     /// 解析条件表达式的入口点
@@ -347,6 +1016,13 @@ impl<'a> Parser<'a> {
     fn parse_not_expression(&mut self) -> Result<Condition, ParseError> {
         if self.match_token(&TokenKind::Not) {
             self.advance(); // 消费 NOT
+            // `NOT BETWEEN a AND b` 作为一个整体解析，而不是包裹为 NOT (BETWEEN ...)
+            if self.peek_between() {
+                return self.parse_between(true);
+            }
+            if self.peek_like() {
+                return self.parse_like(true);
+            }
             let expr = self.parse_not_expression()?; // 允许 NOT 链式调用
             Ok(Condition::Not(Box::new(expr)))
         } else {
@@ -360,57 +1036,82 @@ impl<'a> Parser<'a> {
     /// - `(condition)` - 分组表达式
     /// - `IS [NOT] NULL` - 空值检查
     /// - `IN (value1, value2, ...)` - 列表包含
+    /// - `BETWEEN low AND high` - 闭区间范围
+    /// - `LIKE "pattern"` - 模式匹配
     /// - `op value` - 带运算符的比较 (如 `>5`, `="test"`)
     /// - `value` - 默认相等比较 (如 `"Open"` 等价于 `="Open"`)
     fn parse_primary_expression(&mut self) -> Result<Condition, ParseError> {
         if let Some(token) = self.peek() {
             match &token.kind {
                 TokenKind::LParen => {
+                    let open_span = token.span;
                     self.advance(); // 消费 (
                     let expr = self.parse_condition()?;
-                    self.expect(TokenKind::RParen)?;
+                    self.expect_closing(TokenKind::RParen, open_span)?;
                     Ok(Condition::Grouped(Box::new(expr)))
                 }
                 TokenKind::Is => {
                     self.advance(); // 消费 IS
-                    if self.match_token(&TokenKind::Not) {
+                    let negated = self.match_token(&TokenKind::Not);
+                    if negated {
                         self.advance(); // 消费 NOT
-                        self.expect(TokenKind::Null)?;
-                        Ok(Condition::IsNotNull)
-                    } else {
-                        self.expect(TokenKind::Null)?;
-                        Ok(Condition::IsNull)
                     }
+                    // EMPTY 不是保留字，以标识符形式出现
+                    let is_empty = matches!(
+                        self.peek().map(|t| &t.kind),
+                        Some(TokenKind::Identifier(name)) if name.eq_ignore_ascii_case("EMPTY")
+                    );
+                    if is_empty {
+                        self.advance(); // 消费 EMPTY
+                        return Ok(if negated { Condition::IsNotEmpty } else { Condition::IsEmpty });
+                    }
+                    self.expect(TokenKind::Null)?;
+                    Ok(if negated { Condition::IsNotNull } else { Condition::IsNull })
                 }
                 TokenKind::In => {
                     self.advance(); // 消费 IN
-                    self.expect(TokenKind::LParen)?;
-                    let mut values = Vec::new();
-
-                    // 解析逗号分隔的值列表
-                    if !self.match_token(&TokenKind::RParen) {
-                        loop {
-                            values.push(self.parse_literal()?);
-                            if self.match_token(&TokenKind::RParen) {
-                                break;
-                            }
-                            self.expect(TokenKind::Comma)?;
-                        }
+                    let open_span = self.expect(TokenKind::LParen)?.span;
+
+                    // IN 后紧跟 CrossFilter: 时为子查询
+                    if self.match_token(&TokenKind::CrossFilter) {
+                        return self.parse_in_subquery();
                     }
 
-                    self.expect(TokenKind::RParen)?;
+                    let values = self.parse_in_values()?;
+                    self.expect_closing(TokenKind::RParen, open_span)?;
                     Ok(Condition::In(values))
                 }
+                _ if self.peek_between() => self.parse_between(false),
+                _ if self.peek_like() => self.parse_like(false),
                 _ => {
+                    self.reject_operator_before_keyword()?;
                     // 检查是否以比较运算符开始
-                    if self.is_comparison_operator() {
-                        let op = self.parse_comparison_operator()?;
-                        let value = self.parse_literal()?;
-                        Ok(Condition::Comparison { op, value })
+                    let explicit_op = self.is_comparison_operator();
+                    let op = if explicit_op {
+                        self.parse_comparison_operator()?
                     } else {
                         // 如果没有指定运算符，默认为相等比较
-                        let value = self.parse_literal()?;
-                        Ok(Condition::Comparison { op: CompOp::Eq, value })
+                        CompOp::Eq
+                    };
+                    // `<=> null` 与 `IS NULL` 等价
+                    if op == CompOp::NullSafeEq && self.match_token(&TokenKind::Null) {
+                        self.advance();
+                        return Ok(Condition::IsNull);
+                    }
+                    if let Some(quantifier) = self.peek_quantifier() {
+                        return self.parse_quantified(op, quantifier);
+                    }
+                    if explicit_op && self.match_token(&TokenKind::LParen) {
+                        return self.parse_list_comparison(op);
+                    }
+                    let value = self.parse_literal()?;
+                    self.reject_range_dash(&value)?;
+                    self.reject_number_separator(&value)?;
+                    match value {
+                        Literal::String(s) if !explicit_op && self.config.default_match == DefaultMatch::Contains => {
+                            Ok(Condition::Like { pattern: contains_pattern(&s), negated: false })
+                        }
+                        value => Ok(Condition::Comparison { op, value }),
                     }
                 }
             }
@@ -419,16 +1120,258 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn parse_comparison_operator(&mut self) -> Result<CompOp, ParseError> {
-        if let Some(token) = self.advance() {
-            match &token.kind {
-                TokenKind::Eq => Ok(CompOp::Eq),
-                TokenKind::NotEq => Ok(CompOp::NotEq),
-                TokenKind::Gt => Ok(CompOp::Gt),
-                TokenKind::Lt => Ok(CompOp::Lt),
-                TokenKind::Gte => Ok(CompOp::Gte),
-                TokenKind::Lte => Ok(CompOp::Lte),
-                _ => Err(ParseError::at_position(
+    /// 解析比较运算符后的括号值列表，例如 `= ("a", "b")`
+    ///
+    /// 启用 `list_comparison_as_in` 时 `=` 解析为 IN、`!=` 解析为 NOT IN，其余情况给出改写建议
+    fn parse_list_comparison(&mut self, op: CompOp) -> Result<Condition, ParseError> {
+        let open_span = self.peek().expect("调用方已检查左括号").span;
+        let negated = match op {
+            CompOp::Eq => false,
+            CompOp::NotEq => true,
+            _ => {
+                return Err(ParseError::at_position(
+                    format!(
+                        "Value list after '{}' is not supported: write [{} ANY (...)] or [{} ALL (...)]",
+                        op, op, op
+                    ),
+                    open_span,
+                ));
+            }
+        };
+        if !self.config.list_comparison_as_in {
+            let suggestion = if negated { "NOT IN (...)" } else { "IN (...)" };
+            return Err(ParseError::at_position(
+                format!("Value list after '{}' is not supported: write [{}] to match any of the values", op, suggestion),
+                open_span,
+            ));
+        }
+
+        self.advance(); // 消费 (
+        let values = self.parse_in_values()?;
+        self.expect_closing(TokenKind::RParen, open_span)?;
+        let condition = Condition::In(values);
+        Ok(if negated { Condition::Not(Box::new(condition)) } else { condition })
+    }
+
+    /// `IN`/`IS` 自身就是完整的条件，前面不能再加比较运算符，例如 `[= IN ("a")]`、`[= IS NULL]`
+    fn reject_operator_before_keyword(&self) -> Result<(), ParseError> {
+        if !self.is_comparison_operator() {
+            return Ok(());
+        }
+        let keyword = match self.tokens.get(self.position + 1).map(|t| &t.kind) {
+            Some(TokenKind::In) => "IN",
+            Some(TokenKind::Is) => "IS",
+            _ => return Ok(()),
+        };
+        let operator = &self.tokens[self.position];
+        Err(ParseError::at_position(
+            format!(
+                "Unexpected operator before {}: write [{} ...] without a comparison operator",
+                keyword, keyword
+            ),
+            operator.span,
+        ))
+    }
+
+    /// `-` 只用于负数和日期偏移，`[1-5]` 这样的范围简写没有定义，给出明确的错误而不是笼统的 "Expected RBracket"
+    fn reject_range_dash(&self, value: &Literal) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(token) if token.kind == TokenKind::Dash => Err(ParseError::at_position(
+                format!(
+                    "Unexpected '-' after {}: range shorthand is not supported, write the range as [>={} AND <=...]",
+                    value, value
+                ),
+                token.span,
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// 检查当前位置是否为 `ANY (` 或 `ALL (`，返回大写的量词
+    ///
+    /// ANY/ALL 不是保留关键字，只有紧跟左括号时才视为量词，其余情况仍是普通标识符
+    fn peek_quantifier(&self) -> Option<&'static str> {
+        let Some(Token { kind: TokenKind::Identifier(name), .. }) = self.peek() else {
+            return None;
+        };
+        let quantifier = if name.eq_ignore_ascii_case("ANY") {
+            "ANY"
+        } else if name.eq_ignore_ascii_case("ALL") {
+            "ALL"
+        } else {
+            return None;
+        };
+        match self.tokens.get(self.position + 1) {
+            Some(Token { kind: TokenKind::LParen, .. }) => Some(quantifier),
+            _ => None,
+        }
+    }
+
+    /// 当前 token 是否为 `BETWEEN` 且后面跟着一个值，`kind[between]` 中的 between 仍是普通值
+    fn peek_between(&self) -> bool {
+        let Some(Token { kind: TokenKind::Identifier(name), .. }) = self.peek() else {
+            return false;
+        };
+        name.eq_ignore_ascii_case("BETWEEN")
+            && matches!(
+                self.tokens.get(self.position + 1).map(|token| &token.kind),
+                Some(
+                    TokenKind::String { .. } | TokenKind::Number(_) | TokenKind::Dash | TokenKind::At
+                        | TokenKind::Identifier(_) | TokenKind::Today | TokenKind::Yesterday
+                        | TokenKind::Tomorrow | TokenKind::CurrentUser
+                )
+            )
+    }
+
+    /// 解析 `BETWEEN low AND high`，`negated` 表示前面已消费 NOT
+    fn parse_between(&mut self, negated: bool) -> Result<Condition, ParseError> {
+        self.advance(); // 消费 BETWEEN
+        let low = self.parse_literal()?;
+        self.expect(TokenKind::And)?;
+        let high = self.parse_literal()?;
+        Ok(Condition::Between { low, high, negated })
+    }
+
+    /// 当前 token 是否为 `LIKE` 且后面跟着字符串模式，`kind[like]` 中的 like 仍是普通值
+    fn peek_like(&self) -> bool {
+        let Some(Token { kind: TokenKind::Identifier(name), .. }) = self.peek() else {
+            return false;
+        };
+        name.eq_ignore_ascii_case("LIKE")
+            && matches!(
+                self.tokens.get(self.position + 1).map(|token| &token.kind),
+                Some(TokenKind::String { .. })
+            )
+    }
+
+    /// 解析 `LIKE "pattern"`，`negated` 表示前面已消费 NOT
+    fn parse_like(&mut self, negated: bool) -> Result<Condition, ParseError> {
+        self.advance(); // 消费 LIKE
+        match self.advance().map(|token| &token.kind) {
+            Some(TokenKind::String { value, .. }) => Ok(Condition::Like { pattern: value.to_string(), negated }),
+            _ => unreachable!("peek_like 已检查模式为字符串"),
+        }
+    }
+
+    /// 解析比较运算符之后的 `ANY (v1, v2, ...)` / `ALL (v1, v2, ...)`
+    fn parse_quantified(&mut self, op: CompOp, quantifier: &str) -> Result<Condition, ParseError> {
+        let quantifier_span = self.advance().map(|token| token.span);
+        if op == CompOp::NullSafeEq {
+            return Err(ParseError::new(
+                format!("Operator '<=>' cannot be combined with {}", quantifier),
+                quantifier_span,
+            ));
+        }
+
+        self.expect(TokenKind::LParen)?;
+        let mut values = BoundedList::new(self.config.max_in_list_len);
+        self.parse_comma_separated(|parser| {
+            let span = parser.peek().map(|token| token.span);
+            let value = parser.parse_literal()?;
+            values.push(value, span);
+            Ok(())
+        })?;
+        let values = values.finish(&format!("{} list", quantifier))?;
+        let close_span = self.expect(TokenKind::RParen)?.span;
+        if values.is_empty() {
+            return Err(ParseError::at_position(
+                format!("{} requires at least one value", quantifier),
+                close_span,
+            ));
+        }
+
+        Ok(match quantifier {
+            "ANY" => Condition::AnyOf { op, values },
+            _ => Condition::AllOf { op, values },
+        })
+    }
+
+    /// 比较中的数字后不能跟逗号，`>1,000` 这样的千位分隔写法给出明确的错误而不是笼统的 "Expected RBracket"
+    fn reject_number_separator(&self, value: &Literal) -> Result<(), ParseError> {
+        let Literal::Number(n) = value else {
+            return Ok(());
+        };
+        let Some(comma) = self.peek().filter(|t| t.kind == TokenKind::Comma) else {
+            return Ok(());
+        };
+
+        // 还原紧挨着的数字分组，例如 `1,000,000`，用于提示去掉分隔符后的写法
+        let mut written = n.to_string();
+        let mut joined = n.to_string();
+        let mut end = comma.span.start;
+        for pair in self.tokens[self.position..].chunks(2) {
+            match pair {
+                [Token { kind: TokenKind::Comma, span: comma_span }, Token { kind: TokenKind::Number(group), span }]
+                    if comma_span.start == end && span.start == comma_span.end =>
+                {
+                    let width = span.end - span.start;
+                    written.push_str(&format!(",{:0width$}", group, width = width));
+                    joined.push_str(&format!("{:0width$}", group, width = width));
+                    end = span.end;
+                }
+                _ => break,
+            }
+        }
+
+        let hint = if written.contains(',') {
+            format!(", write {} instead of {}", joined, written)
+        } else {
+            String::new()
+        };
+        Err(ParseError::at_position(
+            format!("Unexpected ',' after {}: numbers cannot contain digit separators{}", n, hint),
+            comma.span,
+        ))
+    }
+
+    /// 解析 IN 中的子查询 `CrossFilter: <Entity> field[condition]; ...)`，左括号已被消费
+    fn parse_in_subquery(&mut self) -> Result<Condition, ParseError> {
+        let segment_span = self.expect(TokenKind::CrossFilter)?.span;
+        self.expect(TokenKind::Lt)?;
+        let entity_token = self.expect(TokenKind::Identifier(""))?;
+        let entity = match &entity_token.kind {
+            TokenKind::Identifier(name) => Identifier(name.to_string()),
+            _ => unreachable!("expect 已检查 token 类型"),
+        };
+        let entity_span = entity_token.span;
+        self.entity_spans.push((entity.clone(), entity_span));
+        self.expect(TokenKind::Gt)?;
+
+        if self.match_token(&TokenKind::RParen) {
+            return Err(ParseError::at_position(
+                "Empty filter segment: expected at least one field filter".to_string(),
+                segment_span,
+            ));
+        }
+
+        // 子查询中的字段Filter以分号分隔，直到右括号结束
+        let mut base_filters = Vec::new();
+        loop {
+            base_filters.push(self.parse_field_filter()?);
+            if !self.match_token(&TokenKind::Semicolon) {
+                break;
+            }
+            self.advance(); // 消费分号
+        }
+        self.expect(TokenKind::RParen)?;
+
+        Ok(Condition::InSubquery {
+            entity,
+            query: Box::new(Query { base_filters, ..Default::default() }),
+        })
+    }
+
+    fn parse_comparison_operator(&mut self) -> Result<CompOp, ParseError> {
+        if let Some(token) = self.advance() {
+            match &token.kind {
+                TokenKind::Eq => Ok(CompOp::Eq),
+                TokenKind::NotEq => Ok(CompOp::NotEq),
+                TokenKind::Gt => Ok(CompOp::Gt),
+                TokenKind::Lt => Ok(CompOp::Lt),
+                TokenKind::Gte => Ok(CompOp::Gte),
+                TokenKind::Lte => Ok(CompOp::Lte),
+                TokenKind::NullSafeEq => Ok(CompOp::NullSafeEq),
+                _ => Err(ParseError::at_position(
                     format!("Expected comparison operator, found {:?}", token.kind),
                     token.span,
                 )),
@@ -439,16 +1382,42 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_literal(&mut self) -> Result<Literal, ParseError> {
-        if let Some(token) = self.advance() {
+        if let Some(token) = self.advance().cloned() {
             match &token.kind {
-                TokenKind::String(s) => Ok(Literal::String(s.to_string())),
+                TokenKind::String { value, .. } => Ok(Literal::String(value.to_string())),
                 TokenKind::Number(n) => Ok(Literal::Number(*n)),
-                TokenKind::Today => Ok(Literal::Date("today".to_string())),
-                TokenKind::Yesterday => Ok(Literal::Date("yesterday".to_string())),
-                TokenKind::Tomorrow => Ok(Literal::Date("tomorrow".to_string())),
+                TokenKind::Dash => match self.peek() {
+                    // 负数，例如 `-456`
+                    Some(Token { kind: TokenKind::Number(n), .. }) => {
+                        let n = *n;
+                        self.advance();
+                        Ok(Literal::Number(-n))
+                    }
+                    _ => Err(ParseError::at_position(
+                        "Unexpected '-': expected a number after '-'".to_string(),
+                        token.span,
+                    )),
+                },
+                TokenKind::Today => self.parse_date_offset("today"),
+                TokenKind::Yesterday => self.parse_date_offset("yesterday"),
+                TokenKind::Tomorrow => self.parse_date_offset("tomorrow"),
                 TokenKind::CurrentUser => Ok(Literal::CurrentUser),
+                TokenKind::At => {
+                    // 列引用，例如 `@estimated`
+                    let column_token = self.expect(TokenKind::Identifier(""))?;
+                    match &column_token.kind {
+                        TokenKind::Identifier(name) => Ok(Literal::Column(Identifier(name.to_string()))),
+                        _ => unreachable!("expect 已检查 token 类型"),
+                    }
+                }
                 TokenKind::Identifier(s) => {
-                    // 不带引号的字符串
+                    // 不带引号的字符串，严格模式下拒绝以避免拼写错误被静默当作字符串
+                    if self.config.strict_values {
+                        return Err(ParseError::at_position(
+                            format!("Unquoted value '{}' is not allowed in strict mode; write it as \"{}\"", s, s),
+                            token.span,
+                        ));
+                    }
                     Ok(Literal::String(s.to_string()))
                 }
                 _ => Err(ParseError::at_position(
@@ -460,6 +1429,45 @@ impl<'a> Parser<'a> {
             Err(ParseError::new("Expected literal value".to_string(), None))
         }
     }
+
+    /// 解析日期关键字后可选的天数偏移，例如 `today-7`、`today + 1`、`today-7d`
+    fn parse_date_offset(&mut self, base: &str) -> Result<Literal, ParseError> {
+        let sign = match self.peek().map(|t| &t.kind) {
+            Some(TokenKind::Dash) => -1,
+            Some(TokenKind::Plus) => 1,
+            _ => return Ok(Literal::Date(base.to_string())),
+        };
+        let operator_span = self.advance().unwrap().span;
+
+        let (days, number_end) = match self.peek() {
+            Some(Token { kind: TokenKind::Number(n), span }) => (*n, span.end),
+            Some(token) => {
+                return Err(ParseError::at_position(
+                    format!("Expected day offset after '{}', found {:?}", base, token.kind),
+                    token.span,
+                ));
+            }
+            None => {
+                return Err(ParseError::at_position(
+                    format!("Expected day offset after '{}'", base),
+                    operator_span,
+                ));
+            }
+        };
+        self.advance();
+
+        // 可选的天数单位后缀 `d`，必须紧跟在数字之后
+        if let Some(Token { kind: TokenKind::Identifier(unit), span }) = self.peek() {
+            if unit.eq_ignore_ascii_case("d") && span.start == number_end {
+                self.advance();
+            }
+        }
+
+        Ok(Literal::RelativeDate {
+            base: base.to_string(),
+            offset_days: sign * days,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -530,6 +1538,157 @@ mod tests {
         assert_eq!(filter.field.0, "status");
     }
 
+    #[test]
+    fn test_cross_filter_chain() {
+        let input = r#"CrossFilter: <Test-Run-Step> result["PASS"]"#;
+        let result = parse_string(input).unwrap();
+
+        let cross_filter = &result.cross_filters[0];
+        assert_eq!(cross_filter.source_entity.0, "Test");
+        assert_eq!(cross_filter.intermediate_entities, vec![Identifier("Run".to_string())]);
+        assert_eq!(cross_filter.target_entity.0, "Step");
+        let path: Vec<_> = cross_filter.join_path().map(|e| e.0.as_str()).collect();
+        assert_eq!(path, vec!["Run", "Step"]);
+
+        assert!(parse_string(r#"CrossFilter: <Test> result["PASS"]"#).is_err());
+        assert!(parse_string(r#"CrossFilter: <Test--Step> result["PASS"]"#).is_err());
+    }
+
+    #[test]
+    fn test_cross_filter_quoted_entity_with_dash() {
+        let result = parse_string(r#"CrossFilter: <"Test-Case"-Run> status["PASS"]"#).unwrap();
+        let cross_filter = &result.cross_filters[0];
+        assert_eq!(cross_filter.source_entity.0, "Test-Case");
+        assert!(cross_filter.intermediate_entities.is_empty());
+        assert_eq!(cross_filter.target_entity.0, "Run");
+
+        let result = parse_string(r#"CrossFilter: <Test-"Run-Step"> status["PASS"]"#).unwrap();
+        let cross_filter = &result.cross_filters[0];
+        assert_eq!(cross_filter.source_entity.0, "Test");
+        assert_eq!(cross_filter.target_entity.0, "Run-Step");
+
+        // 加引号的实体名仍然需要源和目标两部分
+        let err = parse_string(r#"CrossFilter: <"Test-Case"> status["PASS"]"#).unwrap_err();
+        assert!(err.message.contains("Source-Target"), "{}", err.message);
+        assert!(parse_string(r#"CrossFilter: <"Test-Case""Run"> status["PASS"]"#).is_err());
+        assert!(parse_string(r#"CrossFilter: <"Test-Case"-> status["PASS"]"#).is_err());
+    }
+
+    #[test]
+    fn test_quoted_field_identifier() {
+        let input = r#"Filter: `Due Date`[>today]; `in`["x"]"#;
+        let result = parse_string(input).unwrap();
+
+        assert_eq!(result.base_filters.len(), 2);
+        assert_eq!(result.base_filters[0].field.0, "Due Date");
+        assert_eq!(result.base_filters[1].field.0, "in");
+    }
+
+    #[test]
+    fn test_query_from_str() {
+        let query: Query = r#"Filter: status["Open"]"#.parse().unwrap();
+        assert_eq!(query.base_filters.len(), 1);
+        assert_eq!(query.base_filters[0].field.0, "status");
+
+        let err = r#"Filter: status["Open""#.parse::<Query>().unwrap_err();
+        assert!(err.message.contains("Unclosed '['"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_tuple_in() {
+        let input = r#"Filter: (a, b)[IN ((1, "x"), (2, "y"))]"#;
+        let result = parse_string(input).unwrap();
+        let filter = &result.base_filters[0];
+        assert_eq!(filter.field.0, "(a, b)");
+        assert_eq!(
+            filter.condition,
+            Condition::InTuple {
+                fields: vec![Identifier("a".to_string()), Identifier("b".to_string())],
+                rows: vec![
+                    vec![Literal::Number(1), Literal::String("x".to_string())],
+                    vec![Literal::Number(2), Literal::String("y".to_string())],
+                ],
+            }
+        );
+
+        // 单个元组与空列表
+        let result = parse_string(r#"Filter: (a, b)[IN ((1, "x"))]"#).unwrap();
+        assert!(matches!(&result.base_filters[0].condition, Condition::InTuple { rows, .. } if rows.len() == 1));
+        let result = parse_string(r#"Filter: (a, b)[IN ()]"#).unwrap();
+        assert!(matches!(&result.base_filters[0].condition, Condition::InTuple { rows, .. } if rows.is_empty()));
+
+        // 元组长度不匹配
+        let err = parse_string(r#"Filter: (a, b)[IN ((1))]"#).unwrap_err();
+        assert!(err.message.contains("expected 2"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_empty_field_condition() {
+        let err = parse_string(r#"Filter: status[]"#).unwrap_err();
+        assert_eq!(err.message, "Empty condition for field 'status'");
+        assert_eq!(err.span, Some(Span::new(14, 16)));
+    }
+
+    #[test]
+    fn test_empty_filter_segment() {
+        let err = parse_string(r#"Filter: ; priority[>2]"#).unwrap_err();
+        assert!(err.message.contains("Empty filter segment"), "{}", err.message);
+        assert_eq!(err.span, Some(Span::new(0, 7)));
+
+        let err = parse_string(r#"Filter: status["Open"]; Filter:"#).unwrap_err();
+        assert!(err.message.contains("Empty filter segment"), "{}", err.message);
+        assert_eq!(err.span, Some(Span::new(24, 31)));
+
+        let err = parse_string(r#"CrossFilter: <Test-Run>; Filter: status["Open"]"#).unwrap_err();
+        assert!(err.message.contains("Empty filter segment"), "{}", err.message);
+        assert_eq!(err.span, Some(Span::new(0, 12)));
+    }
+
+    #[test]
+    fn test_in_subquery() {
+        let input = r#"Filter: project_id[IN (CrossFilter: <Project> status["Active"]; owner[current_user])]"#;
+        let result = parse_string(input).unwrap();
+        let filter = &result.base_filters[0];
+        assert_eq!(filter.field.0, "project_id");
+
+        let Condition::InSubquery { entity, query } = &filter.condition else {
+            panic!("Expected IN subquery condition, got {:?}", filter.condition);
+        };
+        assert_eq!(entity.0, "Project");
+        assert_eq!(query.base_filters.len(), 2);
+        assert_eq!(query.base_filters[0].to_string(), r#"status["Active"]"#);
+        assert_eq!(result.referenced_entities("Task"), vec!["Task", "Project"]);
+
+        let err = parse_string(r#"Filter: project_id[IN (CrossFilter: <Project>)]"#).unwrap_err();
+        assert!(err.message.contains("Empty filter segment"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_referenced_fields_and_entities() {
+        let input = r#"Filter: status[NOT ("Open" OR IS NULL)]; (a, b)[IN ((1, "x"))]; status["Closed"]; CrossFilter: <Test-Run-Step> result[("PASS" AND NOT "SKIP")]; CrossFilter: <Test-Bug> severity[>2]"#;
+        let query = parse_string(input).unwrap();
+
+        assert_eq!(
+            query.referenced_fields("Test"),
+            vec![
+                ("Test".to_string(), "status".to_string()),
+                ("Test".to_string(), "a".to_string()),
+                ("Test".to_string(), "b".to_string()),
+                ("Step".to_string(), "result".to_string()),
+                ("Bug".to_string(), "severity".to_string()),
+            ]
+        );
+        assert_eq!(query.referenced_entities("Test"), vec!["Test", "Run", "Step", "Bug"]);
+
+        // `@column` 引用的列和排序键中的字段同样计入
+        let query = parse_string("Filter: a[>@b]; d[BETWEEN 1 AND @e]; CrossFilter: <Test-Run> f[= @g]; Sort: c DESC, h * 2").unwrap();
+        let fields: Vec<(String, String)> = [("Test", "a"), ("Test", "b"), ("Test", "d"), ("Test", "e"), ("Run", "f"), ("Run", "g"), ("Test", "c"), ("Test", "h")]
+            .into_iter()
+            .map(|(entity, field)| (String::from(entity), String::from(field)))
+            .collect();
+        assert_eq!(query.referenced_fields("Test"), fields);
+    }
+
     #[test]
     fn test_logical_operations() {
         let input = r#"Filter: status["Open" OR "Pending"]"#;
@@ -664,12 +1823,538 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_relative_date_literal() {
+        let result = parse_string("Filter: created[>today-7]; due[<=today + 1]; updated[>=yesterday-3d]").unwrap();
+        let values: Vec<_> = result.base_filters.iter()
+            .map(|f| match &f.condition {
+                Condition::Comparison { value, .. } => value.clone(),
+                other => panic!("Expected comparison, got {:?}", other),
+            })
+            .collect();
+
+        assert_eq!(values, vec![
+            Literal::RelativeDate { base: "today".to_string(), offset_days: -7 },
+            Literal::RelativeDate { base: "today".to_string(), offset_days: 1 },
+            Literal::RelativeDate { base: "yesterday".to_string(), offset_days: -3 },
+        ]);
+
+        assert!(parse_string("Filter: created[>today-]").is_err());
+    }
+
+    #[test]
+    fn test_cross_field_or_group() {
+        let result = parse_string(r#"Filter: (status["Open"] OR priority[>5]); type["Bug"]"#).unwrap();
+
+        assert_eq!(result.base_filters.len(), 1);
+        assert_eq!(result.base_filters[0].field.0, "type");
+        assert_eq!(result.base_or_groups.len(), 1);
+        let fields: Vec<_> = result.base_or_groups[0].iter().map(|f| f.field.0.as_str()).collect();
+        assert_eq!(fields, vec!["status", "priority"]);
+
+        // 元组字段列表仍按元组解析
+        let tuple = parse_string(r#"Filter: (a, b)[IN ((1, 2))]"#).unwrap();
+        assert!(tuple.base_or_groups.is_empty());
+
+        assert!(parse_string(r#"CrossFilter: <Test-Run> (status["Open"] OR id[1])"#).is_err());
+    }
+
+    #[test]
+    fn test_dash_in_condition() {
+        // `-` 后紧跟数字时为负数
+        let result = parse_string("Filter: delta[>-5]; offset[IN (-1, 2)]").unwrap();
+        assert_eq!(
+            result.base_filters[0].condition,
+            Condition::Comparison { op: CompOp::Gt, value: Literal::Number(-5) }
+        );
+        assert_eq!(
+            result.base_filters[1].condition,
+            Condition::In(vec![Literal::Number(-1), Literal::Number(2)])
+        );
+
+        // 范围简写没有定义，报告指向 `-` 的明确错误
+        let input = "Filter: range[1-5]";
+        let err = parse_string(input).unwrap_err();
+        assert!(err.message.starts_with("Unexpected '-' after 1"), "{}", err.message);
+        let span = err.span.unwrap();
+        assert_eq!(&input[span.start..span.end], "-");
+
+        assert!(parse_string(r#"Filter: range[-"x"]"#).unwrap_err().message.contains("expected a number"));
+    }
+
+    #[test]
+    fn test_strict_values_rejects_unquoted_identifier() {
+        let input = "Filter: status[Opne]";
+        let tokens: Vec<_> = Lexer::new(input).collect();
+
+        let lenient = Parser::new(&tokens).parse().unwrap();
+        assert_eq!(
+            lenient.base_filters[0].condition,
+            Condition::Comparison { op: CompOp::Eq, value: Literal::String("Opne".to_string()) }
+        );
+
+        let strict = ParserConfig { strict_values: true, ..Default::default() };
+        let err = Parser::with_config(&tokens, strict.clone()).parse().unwrap_err();
+        assert!(err.message.contains("'Opne'"), "{}", err.message);
+        let span = err.span.unwrap();
+        assert_eq!(&input[span.start..span.end], "Opne");
+
+        let quoted: Vec<_> = Lexer::new(r#"Filter: status["Open"]"#).collect();
+        assert!(Parser::with_config(&quoted, strict).parse().is_ok());
+    }
+
+    #[test]
+    fn test_column_reference_literal() {
+        let result = parse_string("Filter: actual[>@estimated]").unwrap();
+        assert_eq!(
+            result.base_filters[0].condition,
+            Condition::Comparison {
+                op: CompOp::Gt,
+                value: Literal::Column(Identifier("estimated".to_string())),
+            }
+        );
+        assert_eq!(result.base_filters[0].to_string(), "actual[>@estimated]");
+
+        assert!(parse_string(r#"Filter: actual[>@"estimated"]"#).is_err());
+    }
+
+    #[test]
+    fn test_any_all_quantified_comparison() {
+        let result = parse_string(r#"Filter: tags[= ANY ("a", "b")]; score[> all (1, 2)]"#).unwrap();
+        assert_eq!(
+            result.base_filters[0].condition,
+            Condition::AnyOf {
+                op: CompOp::Eq,
+                values: vec![Literal::String("a".to_string()), Literal::String("b".to_string())],
+            }
+        );
+        assert_eq!(
+            result.base_filters[1].condition,
+            Condition::AllOf { op: CompOp::Gt, values: vec![Literal::Number(1), Literal::Number(2)] }
+        );
+        assert_eq!(result.base_filters[0].to_string(), r#"tags[= ANY ("a", "b")]"#);
+        assert_eq!(result.base_filters[1].to_string(), "score[> ALL (1, 2)]");
+
+        // 不跟左括号时 any 仍是普通值
+        let result = parse_string("Filter: kind[any]").unwrap();
+        assert_eq!(
+            result.base_filters[0].condition,
+            Condition::Comparison { op: CompOp::Eq, value: Literal::String("any".to_string()) }
+        );
+
+        let err = parse_string("Filter: tags[= ANY ()]").unwrap_err();
+        assert!(err.message.contains("ANY requires at least one value"), "{}", err.message);
+        let err = parse_string("Filter: tags[<=> ALL (1)]").unwrap_err();
+        assert!(err.message.contains("'<=>' cannot be combined with ALL"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_between_and_not_between() {
+        let result = parse_string("Filter: age[BETWEEN 18 AND 65]; age[not between 18 and 65]").unwrap();
+        assert_eq!(
+            result.base_filters[0].condition,
+            Condition::Between { low: Literal::Number(18), high: Literal::Number(65), negated: false }
+        );
+        assert_eq!(
+            result.base_filters[1].condition,
+            Condition::Between { low: Literal::Number(18), high: Literal::Number(65), negated: true }
+        );
+        assert_eq!(result.base_filters[0].to_string(), "age[BETWEEN 18 AND 65]");
+        assert_eq!(result.base_filters[1].to_string(), "age[NOT BETWEEN 18 AND 65]");
+
+        // BETWEEN 内部的 AND 不会与外层的 AND 混淆
+        let result = parse_string(r#"Filter: due[BETWEEN today-7 AND today AND != "2024-01-01"]"#).unwrap();
+        assert!(matches!(
+            &result.base_filters[0].condition,
+            Condition::And(left, _) if matches!(**left, Condition::Between { negated: false, .. })
+        ));
+
+        // 不跟值时 between 仍是普通值
+        let result = parse_string("Filter: kind[between]").unwrap();
+        assert_eq!(
+            result.base_filters[0].condition,
+            Condition::Comparison { op: CompOp::Eq, value: Literal::String("between".to_string()) }
+        );
+
+        assert!(parse_string("Filter: age[BETWEEN 18 OR 65]").is_err());
+    }
+
+    #[test]
+    fn test_relaxed_in_separators() {
+        let parse_relaxed = |input: &str| {
+            let tokens: Vec<_> = Lexer::new(input).collect();
+            let config = ParserConfig { relaxed_in_separators: true, ..Default::default() };
+            Parser::with_config(&tokens, config).parse()
+        };
+
+        let comma = parse_relaxed(r#"Filter: status[IN ("a", "b", "c")]"#).unwrap();
+        let pipe = parse_relaxed(r#"Filter: status[IN ("a" | "b" | "c")]"#).unwrap();
+        let newline = parse_relaxed("Filter: status[IN (\n\"a\"\n\"b\"\n\"c\"\n)]").unwrap();
+        assert_eq!(pipe, comma);
+        assert_eq!(newline, comma);
+        assert_eq!(comma, parse_string(r#"Filter: status[IN ("a", "b", "c")]"#).unwrap());
+
+        let err = parse_relaxed(r#"Filter: status[IN ("a" | )]"#).unwrap_err();
+        assert!(err.message.contains("Trailing separator"), "{}", err.message);
+
+        // 未启用时只接受逗号
+        assert!(parse_string(r#"Filter: status[IN ("a" | "b")]"#).is_err());
+        assert!(parse_string("Filter: status[IN (\"a\" \"b\")]").is_err());
+    }
+
+    #[test]
+    fn test_like_and_not_like() {
+        let result = parse_string(r#"Filter: name[LIKE "bug%"]; name[NOT LIKE "50\%"]"#).unwrap();
+        assert_eq!(
+            result.base_filters[0].condition,
+            Condition::Like { pattern: "bug%".to_string(), negated: false }
+        );
+        assert_eq!(
+            result.base_filters[1].condition,
+            Condition::Like { pattern: r"50\%".to_string(), negated: true }
+        );
+        assert_eq!(result.base_filters[1].to_string(), r#"name[NOT LIKE "50\%"]"#);
+
+        // 不跟字符串时 like 仍是普通值
+        let result = parse_string("Filter: kind[like]").unwrap();
+        assert_eq!(
+            result.base_filters[0].condition,
+            Condition::Comparison { op: CompOp::Eq, value: Literal::String("like".to_string()) }
+        );
+    }
+
+    #[test]
+    fn test_default_match_configuration() {
+        let input = r#"Filter: status["Open"]; title["50%_off"]; priority[5]; owner[!="bob"]"#;
+        let tokens: Vec<_> = Lexer::new(input).collect();
+
+        let result = Parser::new(&tokens).parse().unwrap();
+        assert_eq!(
+            result.base_filters[0].condition,
+            Condition::Comparison { op: CompOp::Eq, value: Literal::String("Open".to_string()) }
+        );
+
+        let config = ParserConfig { default_match: DefaultMatch::Contains, ..Default::default() };
+        let result = Parser::with_config(&tokens, config).parse().unwrap();
+        assert_eq!(
+            result.base_filters[0].condition,
+            Condition::Like { pattern: "%Open%".to_string(), negated: false }
+        );
+        // 值中的通配符按字面匹配
+        let expected_pattern = format!("%50{}%{}_off%", '\\', '\\');
+        assert_eq!(
+            result.base_filters[1].condition,
+            Condition::Like { pattern: expected_pattern, negated: false }
+        );
+        // 数字和显式运算符不受影响
+        assert_eq!(result.base_filters[2].condition, Condition::Comparison { op: CompOp::Eq, value: Literal::Number(5) });
+        assert_eq!(
+            result.base_filters[3].condition,
+            Condition::Comparison { op: CompOp::NotEq, value: Literal::String("bob".to_string()) }
+        );
+    }
+
+    #[test]
+    fn test_sort_clause_with_expression() {
+        let result = parse_string(r#"Filter: status["Open"]; Sort: priority * 2 DESC, name"#).unwrap();
+        assert_eq!(
+            result.sort,
+            vec![
+                SortKey {
+                    expr: SortExpr::Binary {
+                        op: ArithOp::Mul,
+                        left: Box::new(SortExpr::Field(Identifier("priority".to_string()))),
+                        right: Box::new(SortExpr::Number(2)),
+                    },
+                    descending: true,
+                },
+                SortKey { expr: SortExpr::Field(Identifier("name".to_string())), descending: false },
+            ]
+        );
+        assert_eq!(result.base_filters.len(), 1);
+
+        // 乘除优先于加减，括号改变优先级
+        let result = parse_string("Sort: (estimated - actual) / 2 + bonus ASC").unwrap();
+        assert_eq!(result.sort[0].to_string(), "(estimated - actual) / 2 + bonus");
+        assert!(!result.sort[0].descending);
+
+        let err = parse_string("Sort: ;").unwrap_err();
+        assert!(err.message.contains("Empty Sort clause"), "{}", err.message);
+        assert!(parse_string("Sort: a; Sort: b").is_err());
+        assert!(parse_string("Sort: priority *").is_err());
+    }
+
+    #[test]
+    fn test_entity_spans() {
+        let input = r#"CrossFilter: <Test-Run> result["FAIL"]; CrossFilter: <Test-"Test-Case"-Step> id[IN (CrossFilter: <Project> name["x"])]"#;
+        let tokens: Vec<_> = Lexer::new(input).collect();
+        let mut parser = Parser::new(&tokens);
+        parser.parse().unwrap();
+
+        let spans: Vec<(&str, &str)> = parser.entity_spans().iter()
+            .map(|(entity, span)| (entity.0.as_str(), &input[span.start..span.end]))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                ("Test", "Test"), ("Run", "Run"),
+                ("Test", "Test"), ("Test-Case", "Test-Case"), ("Step", "Step"),
+                ("Project", "Project"),
+            ]
+        );
+        let (_, run_span) = &parser.entity_spans()[1];
+        assert_eq!((run_span.start, run_span.end), (19, 22));
+    }
+
+    #[test]
+    fn test_parse_is_empty() {
+        let query = parse_string("Filter: note[IS EMPTY]; title[is not empty]; empty[IS NULL]").unwrap();
+        assert_eq!(query.base_filters[0].condition, Condition::IsEmpty);
+        assert_eq!(query.base_filters[1].condition, Condition::IsNotEmpty);
+        // 名为 empty 的字段不受影响
+        assert_eq!(query.base_filters[2].field.0, "empty");
+        assert_eq!(query.base_filters[2].condition, Condition::IsNull);
+
+        assert_eq!(Condition::IsEmpty.to_string(), "IS EMPTY");
+        assert!(parse_string("Filter: note[IS BLANK]").is_err());
+    }
+
+    #[test]
+    fn test_parse_filter_macro() {
+        let query = parse_string(r#"Filter: status["Open"]; mine_recently"#).unwrap();
+        assert_eq!(query.macros, vec![FilterMacro::MineRecently]);
+        assert_eq!(query.base_filters.len(), 1);
+
+        // 后面带 `[` 时是普通字段
+        let query = parse_string(r#"Filter: MINE_RECENTLY["x"]"#).unwrap();
+        assert!(query.macros.is_empty());
+        assert_eq!(query.base_filters[0].field.0, "MINE_RECENTLY");
+
+        let err = parse_string("CrossFilter: <Test-Run> MINE_RECENTLY").unwrap_err();
+        assert_eq!(err.message, "MINE_RECENTLY is only supported in Filter segments");
+    }
+
+    #[test]
+    fn test_list_after_comparison_operator() {
+        let err = parse_string(r#"Filter: tags[= ("a","b")]"#).unwrap_err();
+        assert_eq!(err.message, "Value list after '=' is not supported: write [IN (...)] to match any of the values");
+        assert_eq!(err.span, Some(Span::new(15, 16)));
+        let err = parse_string(r#"Filter: tags[> (1, 2)]"#).unwrap_err();
+        assert!(err.message.contains("[> ANY (...)]"), "{}", err.message);
+
+        let parse_as_in = |input: &str| {
+            let tokens: Vec<_> = Lexer::new(input).collect();
+            let config = ParserConfig { list_comparison_as_in: true, ..Default::default() };
+            Parser::with_config(&tokens, config).parse()
+        };
+        let query = parse_as_in(r#"Filter: tags[= ("a","b")]; kind[!= ("x")]"#).unwrap();
+        let values = vec![Literal::String("a".to_string()), Literal::String("b".to_string())];
+        assert_eq!(query.base_filters[0].condition, Condition::In(values));
+        assert_eq!(
+            query.base_filters[1].condition,
+            Condition::Not(Box::new(Condition::In(vec![Literal::String("x".to_string())])))
+        );
+        assert!(parse_as_in(r#"Filter: tags[< ("a")]"#).is_err());
+    }
+
+    #[test]
+    fn test_in_list_with_null() {
+        let result = parse_string(r#"Filter: status[IN ("Open", null)]; owner[NOT IN (NULL)]"#).unwrap();
+        assert_eq!(
+            result.base_filters[0].condition,
+            Condition::In(vec![Literal::String("Open".to_string()), Literal::Null])
+        );
+        assert_eq!(
+            result.base_filters[1].condition,
+            Condition::Not(Box::new(Condition::In(vec![Literal::Null])))
+        );
+
+        // `null` 只能出现在 IN 列表中
+        assert!(parse_string("Filter: status[= null]").is_err());
+    }
+
+    #[test]
+    fn test_in_list_leading_comma() {
+        let input = r#"Filter: status[IN (, "a")]"#;
+        let err = parse_string(input).unwrap_err();
+        assert_eq!(err.message, "Unexpected ',' in value list");
+        assert_eq!(err.span, Some(Span::new(input.find(',').unwrap(), input.find(',').unwrap() + 1)));
+
+        let err = parse_string(r#"Filter: status[IN ("a",, "b")]"#).unwrap_err();
+        assert_eq!(err.message, "Unexpected ',' in value list");
+
+        let tokens: Vec<_> = Lexer::new(input).collect();
+        let parser_config = ParserConfig { relaxed_in_separators: true, ..Default::default() };
+        let err = Parser::with_config(&tokens, parser_config).parse().unwrap_err();
+        assert_eq!(err.message, "Unexpected ',' in value list");
+    }
+
+    #[test]
+    fn test_in_list_length_limit() {
+        let parse = |input: &str, relaxed_in_separators| {
+            let tokens: Vec<_> = Lexer::new(input).collect();
+            let config = ParserConfig { max_in_list_len: 3, relaxed_in_separators, ..Default::default() };
+            Parser::with_config(&tokens, config).parse()
+        };
+
+        assert!(parse("Filter: id[IN (1, 2, 3)]", false).is_ok());
+        let input = "Filter: id[IN (1, 2, 3, 4, 5)]";
+        for relaxed in [false, true] {
+            let err = parse(input, relaxed).unwrap_err();
+            assert_eq!(err.message, "IN list has 5 values, more than the limit of 3");
+            assert_eq!(err.span, Some(Span::new(input.find('4').unwrap(), input.find('4').unwrap() + 1)));
+        }
+        assert!(parse("Filter: id[IN (1 | 2 | 3 | 4)]", true).is_err());
+
+        assert!(parse("Filter: id[= ANY (1, 2, 3)]", false).is_ok());
+        let err = parse("Filter: id[= ANY (1, 2, 3, 4)]", false).unwrap_err();
+        assert_eq!(err.message, "ANY list has 4 values, more than the limit of 3");
+        let err = parse("Filter: id[> ALL (1, 2, 3, 4, 5, 6)]", false).unwrap_err();
+        assert_eq!(err.message, "ALL list has 6 values, more than the limit of 3");
+
+        let input = r#"Filter: (a, b)[IN ((1, "x"), (2, "y"), (3, "z"), (4, "w"))]"#;
+        let err = parse(input, false).unwrap_err();
+        assert_eq!(err.message, "IN tuple list has 4 values, more than the limit of 3");
+        assert_eq!(err.span.unwrap().start, input.find("(4").unwrap());
+        assert_eq!(ParserConfig::default().max_in_list_len, DEFAULT_MAX_IN_LIST_LEN);
+    }
+
+    #[test]
+    fn test_parser_reset_reuses_instance() {
+        let first: Vec<_> = Lexer::new(r#"Filter: status["Open"]"#).collect();
+        let second: Vec<_> = Lexer::new("CrossFilter: <Test-Run> result[IS NULL]").collect();
+
+        let mut parser = Parser::new(&first);
+        let query = parser.parse().unwrap();
+        assert_eq!(query.base_filters[0].to_string(), r#"status["Open"]"#);
+
+        parser.reset(&second);
+        let query = parser.parse().unwrap();
+        assert!(query.base_filters.is_empty());
+        assert_eq!(query.cross_filters[0].target_entity.0, "Run");
+        assert_eq!(query.cross_filters[0].filters[0].condition, Condition::IsNull);
+    }
+
+    #[test]
+    fn test_unclosed_bracket_and_paren_report_opener() {
+        let err = parse_string(r#"Filter: status["Open""#).unwrap_err();
+        assert_eq!(err.message, "Unclosed '[' opened at position 14");
+        assert_eq!(err.span, Some(Span::new(14, 15)));
+
+        let err = parse_string(r#"Filter: status[("Open" OR "Closed"]"#).unwrap_err();
+        assert_eq!(err.message, "Expected ')' to close '(' opened at position 15, found RBracket");
+
+        let err = parse_string(r#"Filter: status[IN ("Open", "Closed""#).unwrap_err();
+        assert_eq!(err.message, "Unclosed '(' opened at position 18");
+    }
+
+    #[test]
+    fn test_number_with_digit_separator_is_error() {
+        let input = "Filter: amount[>1,000]";
+        let err = parse_string(input).unwrap_err();
+        assert_eq!(
+            err.message,
+            "Unexpected ',' after 1: numbers cannot contain digit separators, write 1000 instead of 1,000"
+        );
+        let span = err.span.unwrap();
+        assert_eq!(&input[span.start..span.end], ",");
+
+        let err = parse_string("Filter: amount[<2,500,000]").unwrap_err();
+        assert!(err.message.ends_with("write 2500000 instead of 2,500,000"), "{}", err.message);
+
+        // IN 列表中的逗号仍是分隔符
+        assert!(parse_string("Filter: amount[IN (1,000)]").is_ok());
+    }
+
+    #[test]
+    fn test_segment_level_or() {
+        let result = parse_string(
+            r#"Filter: status["Open"]; priority[>5] OR CrossFilter: <Test-Run> result["FAIL"] OR Filter: owner[current_user]"#,
+        )
+        .unwrap();
+
+        // `;` 优先级高于 OR：第一个分支包含两个基础Filter
+        assert_eq!(result.base_filters.len(), 2);
+        assert!(result.cross_filters.is_empty());
+        assert_eq!(result.or_branches.len(), 2);
+        assert_eq!(result.or_branches[0].cross_filters[0].target_entity.0, "Run");
+        assert!(result.or_branches[0].base_filters.is_empty());
+        assert_eq!(result.or_branches[1].base_filters[0].field.0, "owner");
+
+        // 分号后同样可以接段落级 OR
+        let with_semicolon = parse_string(r#"Filter: status["Open"]; OR Filter: priority[>5]"#).unwrap();
+        assert_eq!(with_semicolon.or_branches.len(), 1);
+
+        let err = parse_string(r#"OR Filter: status["Open"]"#).unwrap_err();
+        assert!(err.message.contains("Segment-level OR"), "{}", err.message);
+        assert!(parse_string(r#"Filter: status["Open"] OR"#).is_err());
+    }
+
+    #[test]
+    fn test_operator_before_in_or_is_is_error() {
+        let input = r#"Filter: x[= IN ("a")]"#;
+        let err = parse_string(input).unwrap_err();
+        assert_eq!(err.message, "Unexpected operator before IN: write [IN ...] without a comparison operator");
+        let span = err.span.unwrap();
+        assert_eq!(&input[span.start..span.end], "=");
+
+        let err = parse_string("Filter: x[!= IS NULL]").unwrap_err();
+        assert_eq!(err.message, "Unexpected operator before IS: write [IS ...] without a comparison operator");
+    }
+
     #[test]
     fn test_in_clause_trailing_comma_is_error() {
         let input = r#"Filter: status[IN ("Open",)]"#;
         assert!(parse_string(input).is_err());
     }
 
+    #[test]
+    fn test_parse_identifier_list() {
+        fn identifiers(input: &str) -> Result<Vec<String>, ParseError> {
+            let tokens: Vec<_> = Lexer::new(input).collect();
+            let mut parser = Parser::new(&tokens);
+            let list = parser.parse_identifier_list()?;
+            parser.expect(TokenKind::RParen)?;
+            Ok(list.into_iter().map(|i| i.0).collect())
+        }
+
+        assert_eq!(identifiers(")").unwrap(), Vec::<String>::new());
+        assert_eq!(identifiers("a)").unwrap(), vec!["a"]);
+        assert_eq!(identifiers("a, b, c)").unwrap(), vec!["a", "b", "c"]);
+
+        let err = identifiers("a, b,)").unwrap_err();
+        assert_eq!(err.message, "Trailing comma in list");
+        assert_eq!(err.span, Some(Span::new(4, 5)));
+
+        // 元组字段列表与 IN 列表使用同样的规则
+        let err = parse_string(r#"Filter: (a, b,)[IN ((1, 2))]"#).unwrap_err();
+        assert_eq!(err.message, "Trailing comma in list");
+        let err = parse_string(r#"Filter: status[IN ("Open",)]"#).unwrap_err();
+        assert_eq!(err.message, "Trailing comma in list");
+    }
+
+    #[test]
+    fn test_sample_clause() {
+        let input = r#"Filter: status["Open"]; Sample: 10%"#;
+        let result = parse_string(input).unwrap();
+
+        assert_eq!(result.base_filters.len(), 1);
+        assert_eq!(result.sample_percent, Some(10));
+
+        let input = r#"Sample: 25%; CrossFilter: <Test-Run> status["PASS"]"#;
+        let result = parse_string(input).unwrap();
+        assert_eq!(result.sample_percent, Some(25));
+        assert_eq!(result.cross_filters.len(), 1);
+    }
+
+    #[test]
+    fn test_sample_clause_errors() {
+        assert!(parse_string("Sample: 0%").is_err());
+        assert!(parse_string("Sample: 101%").is_err());
+        assert!(parse_string("Sample: 10").is_err());
+        assert!(parse_string("Sample: 10%; Sample: 20%").is_err());
+    }
+
     #[test]
     fn test_complex_query() {
         let input = r#"Filter: title["Plan" AND ("v1" OR "v2")]; priority[>2]; CrossFilter: <Test-Run> status["PASS"]"#;
@@ -1,9 +1,18 @@
 //! SQL 编译器，将 AST 转换为使用 sea-query 的优化 SQL 查询
 
-use crate::ast::{Query as AstQuery, FieldFilter, CrossFilter, Condition, CompOp, Literal};
-use crate::config::{TableMappingConfig, ConfigError};
-use sea_query::{SelectStatement, Asterisk, Expr, SimpleExpr, PostgresQueryBuilder, JoinType, Iden, Value};
-use std::collections::HashMap;
+use crate::ast::{Query as AstQuery, FieldFilter, CrossFilter, CrossCombinator, Condition, CompOp, Literal, SortKey, SortExpr, ArithOp, FilterMacro, Identifier};
+use crate::config::{TableMappingConfig, ConfigError, NamingStrategy, ColumnNaming, JoinKey, JoinKeyStrategy};
+use crate::token::Span;
+use crate::datafusion::DataFusionCompiler;
+use sea_query::{
+    SelectStatement, Asterisk, Expr, Func, SimpleExpr, BinOper, JoinType, Iden, Value, ArrayType, Quote,
+    QueryBuilder, PostgresQueryBuilder, MysqlQueryBuilder, SqliteQueryBuilder, PgFunc, Keyword, Alias, IntoIden,
+    SqlWriter, TableRef, DeleteStatement, UpdateStatement, LikeExpr, Order,
+};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex, PoisonError};
 
 /// 核心查询编译器 trait - 所有编译器必须实现的基本功能
 pub trait QueryCompiler {
@@ -36,6 +45,19 @@ pub trait BatchQueryCompiler {
     
     /// 估算查询复杂度
     fn estimate_query_complexity(&self, query: &AstQuery) -> QueryComplexity;
+
+    /// 估算查询复杂度，`entity` 为主实体，`tables` 用于查找各实体的实际表名以获取表统计信息
+    ///
+    /// 默认忽略表信息，与 [`BatchQueryCompiler::estimate_query_complexity`] 相同
+    fn estimate_query_complexity_for(&self, query: &AstQuery, _entity: &str, _tables: &dyn TableMappingProvider) -> QueryComplexity {
+        self.estimate_query_complexity(query)
+    }
+}
+
+/// 表统计信息 trait - 为行数估算提供数据来源
+pub trait TableStats: std::fmt::Debug + Send + Sync {
+    /// 获取表的总行数，未知时返回 `None`
+    fn row_count(&self, table: &str) -> Option<u64>;
 }
 
 /// 表映射配置器 trait - 可选的表映射功能
@@ -50,6 +72,40 @@ pub trait TableMappingProvider {
     fn load_mapping_from_config(&mut self, config: &TableMappingConfig) -> Result<(), ConfigError>;
 }
 
+/// 数据库结构信息 trait - 用于在编译前校验字段是否存在
+pub trait SchemaProvider {
+    /// 判断实体是否包含指定字段
+    fn has_column(&self, entity: &str, field: &str) -> bool;
+
+    /// 字段的数据类型，用于校验运算符是否适用于该字段；未知时返回 `None`，不做检查
+    fn column_type(&self, _entity: &str, _field: &str) -> Option<ColumnType> {
+        None
+    }
+}
+
+/// 字段的数据类型，见 [`SchemaProvider::column_type`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Text,
+    Integer,
+    Float,
+    Boolean,
+    Date,
+}
+
+impl ColumnType {
+    /// 运算符是否适用于该类型：`LIKE` 与 `IS EMPTY` 只用于文本，`BETWEEN` 只用于数字和日期，
+    /// 大小比较不能用于布尔值
+    fn supports(self, operator: &str) -> bool {
+        match operator {
+            "LIKE" | "IS EMPTY" => self == ColumnType::Text,
+            "BETWEEN" => matches!(self, ColumnType::Integer | ColumnType::Float | ColumnType::Date),
+            ">" | "<" | ">=" | "<=" => self != ColumnType::Boolean,
+            _ => true,
+        }
+    }
+}
+
 /// 编译器工厂 trait - 用于创建不同类型的编译器
 pub trait CompilerFactory {
     type Compiler: QueryCompiler;
@@ -79,11 +135,31 @@ impl DefaultQueryOptimizer {
     }
 }
 
+impl Default for DefaultQueryOptimizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl QueryOptimizer for DefaultQueryOptimizer {
-    fn optimize(&self, _query: &mut AstQuery) -> Vec<Optimization> {
-        // 预处理优化逻辑可以在这里实现
-        // 目前优化逻辑在 compile 过程中进行
-        Vec::new()
+    fn optimize(&self, query: &mut AstQuery) -> Vec<Optimization> {
+        // AST 级别的预处理优化在这里进行，其余优化逻辑在 compile 过程中进行
+        let mut optimizations = Vec::new();
+
+        if self.config.push_down_not {
+            for filter in query.field_filters_mut() {
+                let original = filter.condition.clone();
+                filter.condition = Self::push_down_not(original.clone());
+                if filter.condition != original {
+                    optimizations.push(Optimization::ConditionSimplification {
+                        original: format!("{}[{}]", filter.field.0, original),
+                        simplified: format!("{}[{}]", filter.field.0, filter.condition),
+                    });
+                }
+            }
+        }
+
+        optimizations
     }
     
     fn optimization_config(&self) -> &OptimizationConfig {
@@ -95,66 +171,184 @@ impl QueryOptimizer for DefaultQueryOptimizer {
     }
 }
 
+impl DefaultQueryOptimizer {
+    /// 递归地将 NOT 下推到条件树的叶子节点 (De Morgan 定律)
+    fn push_down_not(condition: Condition) -> Condition {
+        match condition {
+            Condition::Not(inner) => Self::negate(*inner),
+            Condition::And(left, right) => Condition::And(
+                Box::new(Self::push_down_not(*left)),
+                Box::new(Self::push_down_not(*right)),
+            ),
+            Condition::Or(left, right) => Condition::Or(
+                Box::new(Self::push_down_not(*left)),
+                Box::new(Self::push_down_not(*right)),
+            ),
+            Condition::Grouped(inner) => Condition::Grouped(Box::new(Self::push_down_not(*inner))),
+            other => other,
+        }
+    }
+
+    /// 返回与 `NOT condition` 等价且 NOT 已尽量下推的条件
+    ///
+    /// 比较运算符直接取反，AND/OR 按 De Morgan 定律互换并加上分组以保持优先级，
+    /// 无法取反的 IN 条件和 NULL 安全比较保留 NOT
+    fn negate(condition: Condition) -> Condition {
+        match condition {
+            Condition::Not(inner) => Self::push_down_not(*inner),
+            Condition::Comparison { op, value } => match op.negate() {
+                Some(op) => Condition::Comparison { op, value },
+                None => Condition::Not(Box::new(Condition::Comparison { op, value })),
+            },
+            Condition::IsNull => Condition::IsNotNull,
+            Condition::IsNotNull => Condition::IsNull,
+            Condition::IsEmpty => Condition::IsNotEmpty,
+            Condition::IsNotEmpty => Condition::IsEmpty,
+            Condition::Grouped(inner) => Self::negate(*inner),
+            Condition::And(left, right) => Condition::Grouped(Box::new(Condition::Or(
+                Box::new(Self::negate(*left)),
+                Box::new(Self::negate(*right)),
+            ))),
+            Condition::Or(left, right) => Condition::Grouped(Box::new(Condition::And(
+                Box::new(Self::negate(*left)),
+                Box::new(Self::negate(*right)),
+            ))),
+            // NOT (x op ANY (...)) 等价于 x (取反的 op) ALL (...)，反之亦然
+            Condition::AnyOf { op, values } => match op.negate() {
+                Some(op) => Condition::AllOf { op, values },
+                None => Condition::Not(Box::new(Condition::AnyOf { op, values })),
+            },
+            Condition::AllOf { op, values } => match op.negate() {
+                Some(op) => Condition::AnyOf { op, values },
+                None => Condition::Not(Box::new(Condition::AllOf { op, values })),
+            },
+            Condition::Between { low, high, negated } => Condition::Between { low, high, negated: !negated },
+            Condition::Like { pattern, negated } => Condition::Like { pattern, negated: !negated },
+            other @ (Condition::In(_) | Condition::InTuple { .. } | Condition::InSubquery { .. }) => {
+                Condition::Not(Box::new(other))
+            }
+        }
+    }
+}
+
+/// 等值比较的估算选择率
+const EQUALITY_SELECTIVITY: f64 = 0.1;
+/// 范围比较的估算选择率
+const RANGE_SELECTIVITY: f64 = 0.3;
+/// 空值检查的估算选择率
+const NULL_SELECTIVITY: f64 = 0.1;
+/// 子查询 IN 的估算选择率
+const SUBQUERY_SELECTIVITY: f64 = 0.5;
+
 /// 批量查询处理器的具体实现
+///
+/// 通过 [`BatchQueryCompiler::compile_batch`] 编译时使用处理器自身的方言和表映射；
+/// [`SqlCompiler::from_config`] 创建的处理器沿用编译器配置的方言和表映射
 #[derive(Debug, Clone)]
 pub struct DefaultBatchProcessor {
     config: BatchConfig,
+    table_stats: Option<Arc<dyn TableStats>>,
+    dialect: SqlDialect,
+    table_mapper: DefaultTableMapper,
 }
 
 impl DefaultBatchProcessor {
     pub fn new() -> Self {
-        Self {
-            config: BatchConfig::default(),
-        }
+        Self::with_config(BatchConfig::default())
     }
     
     pub fn with_config(config: BatchConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            table_stats: None,
+            dialect: CompilerConfig::default().dialect,
+            table_mapper: DefaultTableMapper::new(),
+        }
     }
-}
 
-impl BatchQueryCompiler for DefaultBatchProcessor {
-    fn compile_batch(&self, query: AstQuery, entity: &str, config: &BatchConfig) -> Result<BatchQueryResult, CompileError> {
-        if !config.enable_batch_processing {
-            // 如果不启用批量处理，需要有一个基础编译器来处理
-            // 这里我们临时创建一个简单的编译器
-            let basic_compiler = SqlCompiler::new();
-            let result = basic_compiler.compile(query, entity)?;
-            return Ok(BatchQueryResult {
-                queries: vec![result.sql],
-                optimizations: result.optimizations,
-                total_estimated_rows: None,
-            });
+    /// 设置编译批次查询使用的方言
+    pub fn with_dialect(mut self, dialect: SqlDialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// 设置编译批次查询使用的表映射
+    pub fn with_table_mapper(mut self, table_mapper: DefaultTableMapper) -> Self {
+        self.table_mapper = table_mapper;
+        self
+    }
+
+    /// 设置用于行数估算的表统计信息
+    pub fn with_table_stats(mut self, table_stats: Arc<dyn TableStats>) -> Self {
+        self.table_stats = Some(table_stats);
+        self
+    }
+
+    /// 替换用于行数估算的表统计信息
+    pub fn set_table_stats(&mut self, table_stats: Arc<dyn TableStats>) {
+        self.table_stats = Some(table_stats);
+    }
+
+    /// 使用给定的编译器编译批量查询，各批次沿用其方言、表映射等配置
+    pub fn compile_batch_with(&self, compiler: &SqlCompiler, mut query: AstQuery, entity: &str, config: &BatchConfig) -> Result<BatchQueryResult, CompileError> {
+        if config.enable_batch_processing && config.max_batch_size == 0 {
+            return Err(CompileError::new("max_batch_size 必须大于 0".to_string()));
         }
+        let estimated_rows = self.estimate_query_complexity_for(&query, entity, compiler.table_mapper()).estimated_rows;
 
-        // 分析查询以查看是否包含大型 IN 子句
-        let large_in_conditions = self.find_large_in_conditions(&query, config.max_batch_size);
-        
+        // 未启用批量处理，或没有大型 IN 条件时，使用标准编译
+        let large_in_conditions = if config.enable_batch_processing {
+            self.find_large_in_conditions(&query, config.max_batch_size)
+        } else {
+            Vec::new()
+        };
         if large_in_conditions.is_empty() {
-            // 没有大型 IN 条件，使用标准编译
-            let basic_compiler = SqlCompiler::new();
-            let result = basic_compiler.compile(query, entity)?;
+            let result = compiler.compile(query, entity)?;
             return Ok(BatchQueryResult {
                 queries: vec![result.sql],
                 optimizations: result.optimizations,
-                total_estimated_rows: None,
+                total_estimated_rows: estimated_rows,
             });
         }
 
+        // UNION 的各分支不能单独带 ORDER BY，排序在连接后统一应用
+        let union_sort = match config.output_mode {
+            BatchOutputMode::UnionAll => std::mem::take(&mut query.sort),
+            BatchOutputMode::Separate => Vec::new(),
+        };
+        if config.output_mode == BatchOutputMode::UnionAll && query.sample_percent.is_some() {
+            return Err(CompileError::new("UNION ALL 批次输出不支持采样子句".to_string()));
+        }
+        // 只拆分主实体上的一个 IN 列表时各批次互不相交，可以用 UNION ALL 连接；
+        // 拆分关联Filter中或多个字段上的 IN 时同一行可能出现在多个批次中，需要 UNION 去重
+        let disjoint = match large_in_conditions.as_slice() {
+            [(field, values)] => query.base_filters.iter().any(|filter| {
+                filter.field.0 == *field
+                    && self.extract_large_in_from_condition(field, &filter.condition, config.max_batch_size).as_ref()
+                        .is_some_and(|(_, found)| found == values)
+            }),
+            _ => false,
+        };
+
         // 拆分为批量查询
         let mut all_queries = Vec::new();
         let mut all_optimizations = Vec::new();
+        let mut batched_value_count = 0;
         
         for (field, values) in large_in_conditions {
             let batches = self.create_batches(&values, config.max_batch_size);
             
             for batch in batches {
+                batched_value_count += batch.len();
                 let mut batch_query = query.clone();
                 // 用批次替换大型 IN 条件
-                self.replace_in_condition_with_batch(&mut batch_query, &field, batch);
+                self.replace_in_condition_with_batch(&mut batch_query, &field, &values, batch);
                 
-                let basic_compiler = SqlCompiler::new();
-                let result = basic_compiler.compile(batch_query, entity)?;
+                // 连接为一条查询时只读副本提示在连接后统一添加一次
+                let result = match config.output_mode {
+                    BatchOutputMode::Separate => compiler.compile(batch_query, entity)?,
+                    BatchOutputMode::UnionAll => compiler.compile_unhinted(batch_query, entity)?,
+                };
                 all_queries.push(result.sql);
                 all_optimizations.extend(result.optimizations);
             }
@@ -167,81 +361,238 @@ impl BatchQueryCompiler for DefaultBatchProcessor {
             union_count: all_queries.len(),
         });
 
-        let query_count = all_queries.len();
+        // 各批次查询的 FROM/JOIN 与投影 (`SELECT *`) 完全相同，只有 IN 值不同，可以直接连接
+        if config.output_mode == BatchOutputMode::UnionAll {
+            let union = all_queries.join(if disjoint { " UNION ALL " } else { " UNION " });
+            all_queries = vec![compiler.with_replica_hint(compiler.order_union(&union, &union_sort)?)?];
+        }
+
         Ok(BatchQueryResult {
             queries: all_queries,
             optimizations: all_optimizations,
-            total_estimated_rows: Some(query_count * config.max_batch_size),
+            // 没有统计信息时退化为各批次实际值数量之和
+            total_estimated_rows: estimated_rows.or(Some(batched_value_count)),
         })
     }
+}
+
+impl Default for DefaultBatchProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BatchQueryCompiler for DefaultBatchProcessor {
+    /// 使用处理器的方言和表映射编译；需要沿用编译器的其他配置时使用 [`DefaultBatchProcessor::compile_batch_with`]
+    fn compile_batch(&self, query: AstQuery, entity: &str, config: &BatchConfig) -> Result<BatchQueryResult, CompileError> {
+        let mut compiler = SqlCompiler::from_config(CompilerConfig {
+            dialect: self.dialect,
+            batch_config: config.clone(),
+            ..Default::default()
+        });
+        *compiler.table_mapper_mut() = self.table_mapper.clone();
+        self.compile_batch_with(&compiler, query, entity, config)
+    }
     
     fn estimate_query_complexity(&self, query: &AstQuery) -> QueryComplexity {
-        let join_count = query.cross_filters.len();
-        let condition_count = query.base_filters.len() + 
-            query.cross_filters.iter().map(|f| f.filters.len()).sum::<usize>();
+        let join_count = query.branches().map(|branch| branch.cross_filters.len()).sum::<usize>();
+        let condition_count = query.field_filters().count();
         
         // 简单的复杂度评估算法
         let complexity_score = (join_count as f64 * 2.0) + (condition_count as f64 * 1.0);
         
         QueryComplexity {
-            estimated_rows: None, // 需要更复杂的统计信息来估算
+            estimated_rows: None,
             join_count,
             condition_count,
             complexity_score,
         }
     }
+
+    /// 有统计信息时额外估算结果行数，涉及的任一实体缺少行数时为 `None`
+    fn estimate_query_complexity_for(&self, query: &AstQuery, entity: &str, tables: &dyn TableMappingProvider) -> QueryComplexity {
+        let row_count = |entity: &str| {
+            self.table_stats.as_ref()
+                .and_then(|stats| stats.row_count(&tables.get_table_name(entity)))
+                .map(|rows| rows as f64)
+        };
+
+        let estimated_rows = row_count(entity).and_then(|base_rows| {
+            let fractions = query.branches()
+                .map(|branch| Self::branch_fraction(branch, base_rows, &row_count))
+                .collect::<Option<Vec<f64>>>()?;
+            let fraction = match fractions.as_slice() {
+                [single] => *single,
+                // 段落级 OR 的任一分支满足即可：1 - Π(1 - s)
+                _ => 1.0 - fractions.iter().map(|f| 1.0 - f.min(1.0)).product::<f64>(),
+            };
+            Some((base_rows * fraction).round() as usize)
+        });
+
+        QueryComplexity {
+            estimated_rows,
+            ..self.estimate_query_complexity(query)
+        }
+    }
 }
 
 impl DefaultBatchProcessor {
+    /// 估算不含段落级 OR 的单个分支的结果行数相对主实体总行数的比例
+    ///
+    /// 主实体的条件按选择率缩小比例；每个关联Filter的选择率作用于其目标实体的行数，
+    /// 满足条件的目标行数与主实体行数之比即为 JOIN 后行数的放大或缩小倍数
+    fn branch_fraction(branch: &AstQuery, base_rows: f64, row_count: &dyn Fn(&str) -> Option<f64>) -> Option<f64> {
+        let mut fraction = Self::branch_selectivity(branch);
+        for cross_filter in &branch.cross_filters {
+            let target_rows = row_count(&cross_filter.target_entity.0)?;
+            let selectivity: f64 = cross_filter.filters.iter()
+                .map(|filter| Self::condition_selectivity(&filter.condition))
+                .product();
+            fraction *= if base_rows > 0.0 { target_rows * selectivity / base_rows } else { 0.0 };
+        }
+        Some(fraction)
+    }
+
+    /// 估算不含段落级 OR 的单个分支中主实体条件的选择率
+    fn branch_selectivity(branch: &AstQuery) -> f64 {
+        let and_selectivity: f64 = branch.base_filters.iter()
+            .map(|filter| Self::condition_selectivity(&filter.condition))
+            .product();
+        // OR 分组内任一条件满足即可：1 - Π(1 - s)
+        let or_selectivity: f64 = branch.base_or_groups.iter()
+            .map(|group| {
+                1.0 - group.iter()
+                    .map(|filter| 1.0 - Self::condition_selectivity(&filter.condition))
+                    .product::<f64>()
+            })
+            .product();
+        and_selectivity * or_selectivity
+    }
+
+    /// 估算单个比较运算的选择率
+    fn comparison_selectivity(op: &CompOp) -> f64 {
+        match op {
+            CompOp::Eq | CompOp::NullSafeEq => EQUALITY_SELECTIVITY,
+            CompOp::NotEq => 1.0 - EQUALITY_SELECTIVITY,
+            CompOp::Gt | CompOp::Lt | CompOp::Gte | CompOp::Lte => RANGE_SELECTIVITY,
+        }
+    }
+
+    /// 估算单个条件的选择率 (满足条件的行所占比例)
+    fn condition_selectivity(condition: &Condition) -> f64 {
+        match condition {
+            Condition::Comparison { op, .. } => Self::comparison_selectivity(op),
+            Condition::In(values) => (values.len() as f64 * EQUALITY_SELECTIVITY).min(1.0),
+            Condition::InTuple { rows, .. } => (rows.len() as f64 * EQUALITY_SELECTIVITY).min(1.0),
+            // 按逐个比较以 OR / AND 连接估算
+            Condition::AnyOf { op, values } => 1.0 - (1.0 - Self::comparison_selectivity(op)).powi(values.len() as i32),
+            Condition::AllOf { op, values } => Self::comparison_selectivity(op).powi(values.len() as i32),
+            Condition::InSubquery { .. } => SUBQUERY_SELECTIVITY,
+            // 按两个范围比较以 AND 连接估算
+            Condition::Between { negated: false, .. } => RANGE_SELECTIVITY * RANGE_SELECTIVITY,
+            Condition::Between { negated: true, .. } => 1.0 - RANGE_SELECTIVITY * RANGE_SELECTIVITY,
+            Condition::Like { negated: false, .. } => RANGE_SELECTIVITY,
+            Condition::Like { negated: true, .. } => 1.0 - RANGE_SELECTIVITY,
+            Condition::IsNull => NULL_SELECTIVITY,
+            Condition::IsNotNull => 1.0 - NULL_SELECTIVITY,
+            Condition::IsEmpty => NULL_SELECTIVITY + EQUALITY_SELECTIVITY,
+            Condition::IsNotEmpty => 1.0 - NULL_SELECTIVITY - EQUALITY_SELECTIVITY,
+            Condition::And(left, right) => {
+                Self::condition_selectivity(left) * Self::condition_selectivity(right)
+            }
+            Condition::Or(left, right) => {
+                let (left, right) = (Self::condition_selectivity(left), Self::condition_selectivity(right));
+                left + right - left * right
+            }
+            Condition::Not(inner) => 1.0 - Self::condition_selectivity(inner),
+            Condition::Grouped(inner) => Self::condition_selectivity(inner),
+        }
+    }
+
     /// 查找超过批次大小阈值的 IN 条件
     fn find_large_in_conditions(&self, query: &AstQuery, max_batch_size: usize) -> Vec<(String, Vec<Literal>)> {
         let mut large_conditions = Vec::new();
         
-        // 检查基础Filter
-        for filter in &query.base_filters {
+        for filter in Self::conjunctive_filters(query) {
             if let Some((field, values)) = self.extract_large_in_from_condition(&filter.field.0, &filter.condition, max_batch_size) {
                 large_conditions.push((field, values));
             }
         }
         
-        // 检查关联Filter
-        for cross_filter in &query.cross_filters {
-            for filter in &cross_filter.filters {
-                if let Some((field, values)) = self.extract_large_in_from_condition(&filter.field.0, &filter.condition, max_batch_size) {
-                    large_conditions.push((field, values));
-                }
-            }
-        }
-        
         large_conditions
     }
 
+    /// 与整个查询以 AND 连接的字段Filter：基础Filter和关联Filter
+    ///
+    /// OR 分组、段落级 OR 分支和以 OR 连接的关联Filter中的 IN 拆分后，其余条件会在每个批次中重复匹配，
+    /// 因此查询带段落级 OR 或 `cross_combinator` 为 OR 时返回空列表，OR 分组也不包括在内
+    fn conjunctive_filters(query: &AstQuery) -> Vec<&FieldFilter> {
+        if !query.or_branches.is_empty() || query.cross_combinator == CrossCombinator::Or {
+            return Vec::new();
+        }
+        query.base_filters.iter()
+            .chain(query.cross_filters.iter().flat_map(|cf| cf.filters.iter()))
+            .collect()
+    }
+
     /// 从条件树中提取大型 IN 条件
+    ///
+    /// 只查找从根节点经 AND / 分组可达的 IN：各批次结果的并集等于原查询结果。
+    /// NOT 或 OR 下的 IN 拆分后并集不等价 (例如 `NOT IN` 的各批次几乎返回全部行)，不做批量处理
     fn extract_large_in_from_condition(&self, field: &str, condition: &Condition, max_batch_size: usize) -> Option<(String, Vec<Literal>)> {
         match condition {
             Condition::In(values) if values.len() > max_batch_size => {
                 Some((field.to_string(), values.clone()))
             }
-            Condition::And(left, right) | Condition::Or(left, right) => {
+            Condition::And(left, right) => {
                 self.extract_large_in_from_condition(field, left, max_batch_size)
                     .or_else(|| self.extract_large_in_from_condition(field, right, max_batch_size))
             }
-            Condition::Not(inner) | Condition::Grouped(inner) => {
+            Condition::Grouped(inner) => {
                 self.extract_large_in_from_condition(field, inner, max_batch_size)
             }
             _ => None,
         }
     }
 
-    /// 从值列表创建批次
+    /// 从值列表创建批次，重复的值只保留第一次出现，使同一值不会落入多个批次
     fn create_batches(&self, values: &[Literal], batch_size: usize) -> Vec<Vec<Literal>> {
-        values.chunks(batch_size)
+        let mut seen = HashSet::new();
+        let unique: Vec<Literal> = values.iter()
+            .filter(|value| seen.insert(*value))
+            .cloned()
+            .collect();
+        unique.chunks(batch_size)
             .map(|chunk| chunk.to_vec())
             .collect()
     }
 
     /// 用较小的批次替换大型 IN 条件
-    fn replace_in_condition_with_batch(&self, _query: &mut AstQuery, _field: &str, _batch: Vec<Literal>) {
+    fn replace_in_condition_with_batch(&self, query: &mut AstQuery, field: &str, original: &[Literal], batch: Vec<Literal>) {
+        let filters = query.base_filters.iter_mut()
+            .chain(query.cross_filters.iter_mut().flat_map(|cf| cf.filters.iter_mut()));
+        for filter in filters {
+            if filter.field.0 == field && Self::replace_in_values(&mut filter.condition, original, &batch) {
+                return;
+            }
+        }
+    }
+
+    /// 在条件树中查找值列表为 `original` 的 IN 条件并替换为 `batch`，返回是否已替换
+    ///
+    /// 与 [`DefaultBatchProcessor::extract_large_in_from_condition`] 相同，只查找经 AND / 分组可达的 IN
+    fn replace_in_values(condition: &mut Condition, original: &[Literal], batch: &[Literal]) -> bool {
+        match condition {
+            Condition::In(values) if values.as_slice() == original => {
+                *values = batch.to_vec();
+                true
+            }
+            Condition::And(left, right) => {
+                Self::replace_in_values(left, original, batch) || Self::replace_in_values(right, original, batch)
+            }
+            Condition::Grouped(inner) => Self::replace_in_values(inner, original, batch),
+            _ => false,
+        }
     }
 }
 
@@ -249,17 +600,41 @@ impl DefaultBatchProcessor {
 #[derive(Debug, Clone)]
 pub struct DefaultTableMapper {
     mappings: HashMap<String, String>,
+    naming_strategy: NamingStrategy,
 }
 
 impl DefaultTableMapper {
     pub fn new() -> Self {
         Self {
             mappings: HashMap::new(),
+            naming_strategy: NamingStrategy::default(),
         }
     }
     
     pub fn with_mappings(mappings: HashMap<String, String>) -> Self {
-        Self { mappings }
+        Self { mappings, naming_strategy: NamingStrategy::default() }
+    }
+
+    /// 实体是否配置了显式表映射
+    pub fn is_mapped(&self, entity: &str) -> bool {
+        self.mappings.contains_key(entity)
+    }
+
+    /// 设置未配置显式映射时的表名推导策略
+    pub fn with_naming_strategy(mut self, naming_strategy: NamingStrategy) -> Self {
+        self.naming_strategy = naming_strategy;
+        self
+    }
+
+    /// 替换未配置显式映射时的表名推导策略
+    pub fn set_naming_strategy(&mut self, naming_strategy: NamingStrategy) {
+        self.naming_strategy = naming_strategy;
+    }
+}
+
+impl Default for DefaultTableMapper {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -268,7 +643,7 @@ impl TableMappingProvider for DefaultTableMapper {
         self.mappings
             .get(entity)
             .cloned()
-            .unwrap_or_else(|| entity.to_lowercase())
+            .unwrap_or_else(|| self.naming_strategy.table_name(entity))
     }
     
     fn set_table_mapping(&mut self, mapping: HashMap<String, String>) {
@@ -282,6 +657,8 @@ impl TableMappingProvider for DefaultTableMapper {
 }
 
 /// SQL 方言枚举
+///
+/// sea-query 没有 MsSQL / Oracle 的构建器，[`SqlCompiler`] 以这两种方言编译时返回错误
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SqlDialect {
     PostgreSQL,
@@ -291,6 +668,17 @@ pub enum SqlDialect {
     Oracle,
 }
 
+impl SqlDialect {
+    /// 单条语句可绑定的参数数量上限
+    pub fn max_bind_params(self) -> usize {
+        match self {
+            SqlDialect::PostgreSQL | SqlDialect::MySQL | SqlDialect::Oracle => 65535,
+            SqlDialect::SQLite => 32766,
+            SqlDialect::MsSQL => 2100,
+        }
+    }
+}
+
 /// 查询复杂度评估
 #[derive(Debug, Clone, PartialEq)]
 pub struct QueryComplexity {
@@ -305,8 +693,124 @@ pub struct QueryComplexity {
 pub struct CompilerConfig {
     pub optimization_config: OptimizationConfig,
     pub batch_config: BatchConfig,
+    pub cache_config: CacheConfig,
     pub table_mapping: HashMap<String, String>,
+    /// 实体没有显式映射时的表名推导策略
+    pub naming_strategy: NamingStrategy,
+    /// 字段名到列名的转换方式
+    pub column_naming: ColumnNaming,
     pub dialect: SqlDialect,
+    /// 字符串相等比较是否忽略大小写，启用后编译为 `LOWER(col) = LOWER('val')`
+    ///
+    /// `IN` / `NOT IN` 同样编译为 `LOWER(col) IN (LOWER('a'), ...)`；字符串的 `= ANY (...)` 等无法忽略大小写，会编译失败
+    pub case_insensitive_strings: bool,
+    /// 关联Filter使用的 JOIN 类型，关联关系可选时可设为 `LeftJoin`
+    pub join_type: JoinType,
+    /// 以 `(前一个实体, 后一个实体)` 为键的连接列，未配置的关联默认使用 `id = id` 并产生警告诊断
+    pub join_keys: HashMap<(String, String), JoinKey>,
+    /// `join_keys` 中没有配置的关联使用的连接键推导策略
+    pub join_key_strategy: JoinKeyStrategy,
+    /// 发起查询的最终用户 id，设置后 `current_user` 编译为该绑定值而不是 SQL 的 `CURRENT_USER`
+    pub current_user_id: Option<Value>,
+    /// 复杂度评分上限，估算评分超过上限的查询拒绝编译，避免大量 JOIN 的查询压垮数据库
+    pub max_complexity_score: Option<f64>,
+    /// JOIN 数量上限，是比复杂度评分更直接的硬性限制；多跳关联链的每一跳各算一个 JOIN
+    pub max_joins: Option<usize>,
+    /// 基础Filter的列是否加表名前缀
+    pub qualify_columns: QualifyMode,
+    /// 参数化编译使用的占位符风格
+    pub param_style: ParamStyle,
+    /// FROM / JOIN 中表名的 schema 前缀，例如 `reporting` 生成 `"reporting"."tests"`
+    pub schema: Option<String>,
+    /// 按实体配置的隐式过滤条件，例如软删除的 `deleted_at[IS NULL]`
+    ///
+    /// 实体作为查询主实体或关联Filter的目标实体时自动以 AND 加入，即使查询本身没有任何Filter
+    pub default_filters: HashMap<String, Vec<FieldFilter>>,
+    /// 内联 (非参数化) SQL 中字符串字面值的引号风格
+    pub string_quote_style: StringQuoteStyle,
+    /// 按实体配置 `MINE_RECENTLY` 宏展开的字段，未配置的实体使用 [`MineRecentlyFields::default`]
+    pub mine_recently_fields: HashMap<String, MineRecentlyFields>,
+    /// 是否拒绝编译引用了未配置表映射的实体的查询，而不是按命名策略推导表名
+    ///
+    /// 主实体以及关联Filter需要 JOIN 的中间实体和目标实体都会检查
+    pub strict_entities: bool,
+    /// 附加在 SELECT 查询上、把查询路由到只读副本的提示，只作用于 `compile` 和 `compile_parameterized`
+    pub replica_hint: Option<ReplicaHint>,
+    /// 按实体配置的分区键列，例如按时间分区的 `created`
+    pub partition_keys: HashMap<String, String>,
+    /// 是否将分区键上的单日相等比较编译为便于分区裁剪的半开区间
+    ///
+    /// 例如 `created["2023-01-01"]` 编译为 `created >= '2023-01-01' AND created < '2023-01-02'`，
+    /// 分区键列不会被 `LOWER()` 等函数包裹；`!=` 编译为区间之外
+    pub partition_pruning: bool,
+}
+
+/// 把查询路由到只读副本的提示，内容原样写入 SQL 注释，不能包含 `*/`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplicaHint {
+    /// 位于 SQL 开头的注释，例如 `Comment("route=replica")` 生成 `/* route=replica */ SELECT ...`，
+    /// 供按注释路由的代理识别
+    Comment(String),
+    /// 紧跟 SELECT 关键字的优化器提示，例如 `SELECT /*+ READ_REPLICA */ ...`，只支持 MySQL
+    OptimizerHint(String),
+}
+
+/// `MINE_RECENTLY` 宏展开使用的字段，展开为 `owner_field = current_user AND modified_field > today-days`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MineRecentlyFields {
+    /// 与当前用户比较的负责人字段
+    pub owner_field: String,
+    /// 与相对日期比较的修改时间字段
+    pub modified_field: String,
+    /// "最近" 包含的天数
+    pub days: u32,
+}
+
+impl Default for MineRecentlyFields {
+    fn default() -> Self {
+        Self {
+            owner_field: "assignee".to_string(),
+            modified_field: "modified".to_string(),
+            days: 7,
+        }
+    }
+}
+
+/// 内联 SQL 中字符串字面值的引号风格
+///
+/// 两种风格都保证值不会跳出字面值，区别只在于转义写法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StringQuoteStyle {
+    /// 沿用 sea-query 各方言后端的写法：PostgreSQL 中含单引号或反斜杠的字符串输出为 `E'O\'Brien'`，
+    /// MySQL 用反斜杠转义，SQLite 将单引号写成两个
+    #[default]
+    Dialect,
+    /// 标准 SQL 写法，单引号写成两个，例如 `'O''Brien'`，便于嵌入只接受标准字面值的上下文
+    ///
+    /// 反斜杠原样输出；MySQL 默认把反斜杠视为转义字符，因此 MySQL 方言下反斜杠仍写成两个
+    Standard,
+}
+
+/// 参数化 SQL 的占位符风格
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParamStyle {
+    /// 方言默认的位置占位符，例如 PostgreSQL 的 `$1`、MySQL / SQLite 的 `?`
+    #[default]
+    Positional,
+    /// 以给定前缀命名的占位符，例如 `Named(':')` 生成 `:p0, :p1`，`Named('@')` 生成 `@p0`
+    Named(char),
+}
+
+/// 基础Filter列名的表名限定方式，关联Filter的列总是以 JOIN 别名限定
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QualifyMode {
+    /// 总是加表名前缀，例如 `"tests"."status"`
+    #[default]
+    Always,
+    /// 只在存在关联Filter (JOIN) 时加表名前缀
+    OnlyWithJoins,
+    /// 从不加表名前缀，例如 `"status"`
+    Never,
 }
 
 impl Default for CompilerConfig {
@@ -314,8 +818,28 @@ impl Default for CompilerConfig {
         Self {
             optimization_config: OptimizationConfig::default(),
             batch_config: BatchConfig::default(),
+            cache_config: CacheConfig::default(),
             table_mapping: HashMap::new(),
+            naming_strategy: NamingStrategy::default(),
+            column_naming: ColumnNaming::default(),
             dialect: SqlDialect::PostgreSQL,
+            case_insensitive_strings: false,
+            join_type: JoinType::InnerJoin,
+            join_keys: HashMap::new(),
+            join_key_strategy: JoinKeyStrategy::default(),
+            current_user_id: None,
+            max_complexity_score: None,
+            max_joins: None,
+            qualify_columns: QualifyMode::default(),
+            param_style: ParamStyle::default(),
+            schema: None,
+            default_filters: HashMap::new(),
+            string_quote_style: StringQuoteStyle::default(),
+            mine_recently_fields: HashMap::new(),
+            strict_entities: false,
+            replica_hint: None,
+            partition_keys: HashMap::new(),
+            partition_pruning: false,
         }
     }
 }
@@ -327,6 +851,51 @@ pub struct OptimizationConfig {
     pub max_or_conditions_for_in: usize,
     /// 拆分为 UNION 前的最大 IN 值数量
     pub max_in_values: usize,
+    /// 是否在编译前将 NOT 下推到叶子条件 (De Morgan 定律)
+    pub push_down_not: bool,
+    /// 在 PostgreSQL 上将 IN 列表编译为 `= ANY(ARRAY[...])`，
+    /// 参数化编译时整个列表只绑定为一个数组参数
+    pub use_any_for_in: bool,
+}
+
+impl OptimizationConfig {
+    /// 检查优化阈值与批量配置中不合理的组合，返回警告诊断
+    ///
+    /// 这些组合不会使编译失败，但会让 OR 转 IN、IN 拆分为 UNION、批量拆分等优化产生意外的结果
+    pub fn validate(&self, batch_config: &BatchConfig, dialect: SqlDialect) -> Vec<Diagnostic> {
+        let mut warnings = Vec::new();
+        let param_limit = dialect.max_bind_params();
+
+        if self.max_in_values == 0 {
+            warnings.push(Diagnostic::warning(
+                "max_in_values 为 0，编译任何非空的 IN 列表都会失败".to_string(),
+            ));
+        } else if self.max_or_conditions_for_in > self.max_in_values {
+            warnings.push(Diagnostic::warning(format!(
+                "max_or_conditions_for_in ({}) 大于 max_in_values ({})，OR 转换得到的 IN 列表总会再被拆分为 UNION",
+                self.max_or_conditions_for_in, self.max_in_values
+            )));
+        }
+        if self.max_in_values > param_limit {
+            warnings.push(Diagnostic::warning(format!(
+                "max_in_values ({}) 超过 {:?} 的参数数量上限 {}，参数化编译的 IN 列表可能无法执行",
+                self.max_in_values, dialect, param_limit
+            )));
+        }
+        if batch_config.enable_batch_processing {
+            if batch_config.max_batch_size == 0 {
+                warnings.push(Diagnostic::warning(
+                    "max_batch_size 为 0，批量编译任何查询都会失败".to_string(),
+                ));
+            } else if batch_config.max_batch_size > param_limit {
+                warnings.push(Diagnostic::warning(format!(
+                    "max_batch_size ({}) 超过 {:?} 的参数数量上限 {}，单个批次可能无法执行",
+                    batch_config.max_batch_size, dialect, param_limit
+                )));
+            }
+        }
+        warnings
+    }
 }
 
 impl Default for OptimizationConfig {
@@ -334,6 +903,8 @@ impl Default for OptimizationConfig {
         Self {
             max_or_conditions_for_in: 5,
             max_in_values: 1000,
+            push_down_not: true,
+            use_any_for_in: false,
         }
     }
 }
@@ -345,6 +916,8 @@ pub struct BatchConfig {
     pub max_batch_size: usize,
     /// 是否为大型 IN 子句启用批量处理
     pub enable_batch_processing: bool,
+    /// 批次查询的输出方式
+    pub output_mode: BatchOutputMode,
 }
 
 impl Default for BatchConfig {
@@ -352,6 +925,121 @@ impl Default for BatchConfig {
         Self {
             max_batch_size: 500,
             enable_batch_processing: true,
+            output_mode: BatchOutputMode::default(),
+        }
+    }
+}
+
+/// 批量处理结果的输出方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BatchOutputMode {
+    /// 每个批次输出一条独立的查询
+    #[default]
+    Separate,
+    /// 将各批次查询连接为一条查询，有排序时包装为子查询后统一排序，只读副本提示只添加一次
+    ///
+    /// 只拆分主实体上的一个 IN 列表时各批次互不相交，以 `UNION ALL` 连接；
+    /// 否则同一行可能出现在多个批次中，以 `UNION` 连接去重。不支持采样子句
+    UnionAll,
+}
+
+/// 编译缓存配置
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    /// 是否启用编译结果缓存
+    pub enable_compile_cache: bool,
+    /// 缓存的最大条目数，超出后淘汰最久未使用的条目
+    pub max_entries: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enable_compile_cache: false,
+            max_entries: 256,
+        }
+    }
+}
+
+/// 编译缓存的统计信息
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+}
+
+/// 以 `(Query, entity)` 为键的 LRU 编译缓存
+#[derive(Debug)]
+struct CompileCache {
+    max_entries: usize,
+    entries: HashMap<(AstQuery, String), CacheEntry>,
+    /// 单调递增的访问计数，用于确定最久未使用的条目
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    result: CompileResult,
+    last_used: u64,
+}
+
+impl CompileCache {
+    fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries,
+            entries: HashMap::new(),
+            clock: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    fn get(&mut self, key: &(AstQuery, String)) -> Option<CompileResult> {
+        self.clock += 1;
+        match self.entries.get_mut(key) {
+            Some(entry) => {
+                entry.last_used = self.clock;
+                self.hits += 1;
+                Some(entry.result.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    fn insert(&mut self, key: (AstQuery, String), result: CompileResult) {
+        if self.max_entries == 0 {
+            return;
+        }
+
+        if self.entries.len() >= self.max_entries && !self.entries.contains_key(&key) {
+            // 淘汰最久未使用的条目
+            let oldest = self.entries.iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+
+        self.clock += 1;
+        self.entries.insert(key, CacheEntry { result, last_used: self.clock });
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            entries: self.entries.len(),
         }
     }
 }
@@ -368,41 +1056,371 @@ impl CompileError {
     }
 }
 
-/// 代表编译期间应用的优化
+/// 查询校验错误，指出引用了结构中不存在的字段，或对字段使用了其类型不支持的运算符
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    /// 字段所属的实体
+    pub entity: String,
+    pub field: String,
+    /// 字段过滤条件在源文本中的位置
+    pub span: Option<Span>,
+    pub message: String,
+}
+
+/// 静态检查发现的问题类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintKind {
+    /// 同一字段的上下界不相交，例如 `>10 AND <5`
+    EmptyRange,
+    /// 空的 IN 列表，例如 `IN ()`
+    EmptyInList,
+    /// `IS NULL` 与值比较同时成立，例如 `IS NULL AND "x"`
+    NullComparison,
+}
+
+/// 静态检查结果，指出永远不会匹配的字段过滤条件
 #[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+    pub kind: LintKind,
+    /// 字段所属的实体
+    pub entity: String,
+    pub field: String,
+    /// 字段过滤条件在源文本中的位置
+    pub span: Option<Span>,
+    pub message: String,
+}
+
+/// 代表编译期间应用的优化
+///
+/// 序列化为 JSON 时以 `type` 字段标记变体名称
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type")]
 pub enum Optimization {
     OrToIn { field: String, value_count: usize },
+    AndNotToNotIn { field: String, value_count: usize },
     InToUnion { field: String, total_values: usize, union_count: usize },
     ConditionSimplification { original: String, simplified: String },
     RedundantConditionRemoval { removed_condition: String },
+    /// IN 列表中的 `null` 改写为 `IS NULL` 检查，SQL 的 `IN (..., NULL)` 不会匹配 NULL
+    InNullToIsNull { field: String },
+}
+
+impl Optimization {
+    /// 生成面向用户的优化说明，例如 `Converted 5 OR conditions on 'status' into an IN clause`
+    pub fn describe(&self) -> String {
+        match self {
+            Optimization::OrToIn { field, value_count } => format!(
+                "Converted {} OR conditions on '{}' into an IN clause",
+                value_count, field
+            ),
+            Optimization::AndNotToNotIn { field, value_count } => format!(
+                "Converted {} AND NOT conditions on '{}' into a NOT IN clause",
+                value_count, field
+            ),
+            Optimization::InToUnion { field, total_values, union_count } => format!(
+                "Split an IN list of {} values on '{}' into {} smaller IN clauses",
+                total_values, field, union_count
+            ),
+            Optimization::ConditionSimplification { original, simplified } => format!(
+                "Simplified '{}' to '{}'",
+                original, simplified
+            ),
+            Optimization::RedundantConditionRemoval { removed_condition } => format!(
+                "Removed redundant condition '{}'",
+                removed_condition
+            ),
+            Optimization::InNullToIsNull { field } => format!(
+                "Rewrote null in the IN list on '{}' as an IS NULL check",
+                field
+            ),
+        }
+    }
+}
+
+/// 诊断信息的严重程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum DiagnosticSeverity {
+    Warning,
+}
+
+/// 编译期间产生的诊断信息，不会使编译失败
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn warning(message: String) -> Self {
+        Self {
+            severity: DiagnosticSeverity::Warning,
+            message,
+        }
+    }
 }
 
 /// SQL 编译结果，包含优化信息
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
 pub struct CompileResult {
     pub sql: String,
     pub optimizations: Vec<Optimization>,
+    pub diagnostics: Vec<Diagnostic>,
 }
 
-/// 处理大型数据集的批量查询结果
-#[derive(Debug)]
-pub struct BatchQueryResult {
-    pub queries: Vec<String>,
-    pub optimizations: Vec<Optimization>,
-    pub total_estimated_rows: Option<usize>,
-}
-
-/// 代表 sea-query 的表标识符
-#[derive(Debug, Clone)]
-pub struct TableName(pub String);
+impl CompileResult {
+    /// 转换为机器可读的 JSON 表示
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("CompileResult 序列化不会失败")
+    }
 
-impl Iden for TableName {
-    fn unquoted(&self, s: &mut dyn std::fmt::Write) {
-        write!(s, "{}", self.0).unwrap();
+    /// 按主要子句换行缩进后的 SQL，仅用于展示
+    ///
+    /// 在 `FROM`、`JOIN`、`WHERE` 等子句以及 `AND`/`OR` 前换行，子查询按括号层级缩进；
+    /// 字符串和带引号的标识符中的内容保持不变
+    pub fn pretty_sql(&self) -> String {
+        pretty_print_sql(&self.sql)
     }
 }
 
-/// 列标识符包装器
+/// 分页查询的两条 SQL，FROM、JOIN 和 WHERE 部分完全相同
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PaginatedQueries {
+    /// 当前页的数据查询，保留排序并附加 `LIMIT`/`OFFSET`
+    pub data_sql: String,
+    /// 满足Filter的总行数查询 `SELECT COUNT(*)`，不含排序和分页
+    pub count_sql: String,
+}
+
+/// 参数化编译结果，SQL 中的值以占位符表示
+#[derive(Debug, Clone)]
+pub struct ParameterizedQuery {
+    pub sql: String,
+    /// 按占位符顺序排列的绑定参数
+    pub values: Vec<Value>,
+    /// 命名占位符风格下参数名（不含前缀，例如 `p0`）到绑定值的映射，位置占位符风格下为空
+    pub named_values: HashMap<String, Value>,
+    pub optimizations: Vec<Optimization>,
+}
+
+impl ParameterizedQuery {
+    /// 转换为机器可读的 JSON 表示，绑定参数输出在 `params` 中
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "sql": self.sql,
+            "params": self.values.iter().map(value_to_json).collect::<Vec<_>>(),
+            "optimizations": self.optimizations,
+        })
+    }
+}
+
+/// 输出命名占位符的 SQL 写入器，参数按出现顺序命名为 `p0, p1, ...`
+struct NamedParamWriter {
+    prefix: char,
+    sql: String,
+    values: Vec<Value>,
+}
+
+impl NamedParamWriter {
+    fn new(prefix: char) -> Self {
+        Self { prefix, sql: String::new(), values: Vec::new() }
+    }
+}
+
+impl std::fmt::Write for NamedParamWriter {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.sql.push_str(s);
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for NamedParamWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.sql)
+    }
+}
+
+impl SqlWriter for NamedParamWriter {
+    fn push_param(&mut self, value: Value, _: &dyn QueryBuilder) {
+        // 写入 String 不会失败
+        let _ = write!(self.sql, "{}p{}", self.prefix, self.values.len());
+        self.values.push(value);
+    }
+
+    fn as_writer(&mut self) -> &mut dyn std::fmt::Write {
+        self as _
+    }
+}
+
+/// 内联值的 SQL 写入器，字符串字面值按配置的引号风格输出，其余值沿用方言后端的格式
+struct InlineWriter {
+    sql: String,
+    dialect: SqlDialect,
+    quote_style: StringQuoteStyle,
+}
+
+impl InlineWriter {
+    fn literal(&self, value: &Value, query_builder: &dyn QueryBuilder) -> String {
+        match (self.quote_style, value) {
+            (StringQuoteStyle::Standard, Value::String(Some(s))) => self.standard_quoted(s),
+            (StringQuoteStyle::Standard, Value::Char(Some(c))) => self.standard_quoted(&c.to_string()),
+            (StringQuoteStyle::Standard, Value::Array(_, Some(items))) if !items.is_empty() => {
+                let items: Vec<_> = items.iter().map(|item| self.literal(item, query_builder)).collect();
+                format!("ARRAY [{}]", items.join(","))
+            }
+            _ => query_builder.value_to_string(value),
+        }
+    }
+
+    fn standard_quoted(&self, s: &str) -> String {
+        let escaped = if self.dialect == SqlDialect::MySQL { s.replace('\\', "\\\\") } else { s.to_string() };
+        format!("'{}'", escaped.replace('\'', "''"))
+    }
+}
+
+impl std::fmt::Write for InlineWriter {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.sql.push_str(s);
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for InlineWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.sql)
+    }
+}
+
+impl SqlWriter for InlineWriter {
+    fn push_param(&mut self, value: Value, query_builder: &dyn QueryBuilder) {
+        let literal = self.literal(&value, query_builder);
+        self.sql.push_str(&literal);
+    }
+
+    fn as_writer(&mut self) -> &mut dyn std::fmt::Write {
+        self as _
+    }
+}
+
+/// 将 sea-query 绑定值转换为 JSON 值，不支持的类型输出为 `null`
+fn value_to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Bool(Some(b)) => serde_json::Value::from(*b),
+        Value::Int(Some(n)) => serde_json::Value::from(*n),
+        Value::BigInt(Some(n)) => serde_json::Value::from(*n),
+        Value::Double(Some(n)) => serde_json::Value::from(*n),
+        Value::String(Some(s)) => serde_json::Value::from(s.as_str()),
+        Value::Array(_, Some(items)) => items.iter().map(value_to_json).collect(),
+        _ => serde_json::Value::Null,
+    }
+}
+
+/// 在这些关键字前换行，较长的关键字排在前面，避免 `LEFT JOIN` 被拆成 `LEFT` 和 `JOIN`
+const PRETTY_CLAUSE_KEYWORDS: &[&str] = &[
+    "INNER JOIN", "LEFT JOIN", "RIGHT JOIN", "JOIN",
+    "FROM", "WHERE", "GROUP BY", "ORDER BY", "LIMIT",
+];
+
+/// 在这些逻辑运算符前换行，并比所在子句多缩进一级
+const PRETTY_LOGICAL_KEYWORDS: &[&str] = &["AND", "OR"];
+
+/// 为 sea-query 生成的单行 SQL 插入换行和缩进
+fn pretty_print_sql(sql: &str) -> String {
+    let bytes = sql.as_bytes();
+    let mut out = String::with_capacity(sql.len() + 32);
+    let mut quote: Option<u8> = None;
+    let mut depth = 0usize;
+    // `BETWEEN x AND y` 中的 AND 不换行，按括号层级记录尚未消耗的 BETWEEN
+    let mut pending_between: Vec<usize> = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let c = bytes[i];
+        if let Some(q) = quote {
+            let end = if q == b'\'' && c == b'\\' { (i + 2).min(bytes.len()) } else { i + 1 };
+            out.push_str(&sql[i..end]);
+            if c == q {
+                quote = None;
+            }
+            i = end;
+            continue;
+        }
+
+        match c {
+            b'\'' | b'"' | b'`' => quote = Some(c),
+            b'(' => depth += 1,
+            b')' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+
+        let at_word_start = i == 0 || !(bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'_');
+        if at_word_start && c.is_ascii_uppercase() {
+            let keyword_at = |keyword: &str| {
+                sql[i..].starts_with(keyword)
+                    && bytes.get(i + keyword.len()).is_none_or(|b| !(b.is_ascii_alphanumeric() || *b == b'_'))
+            };
+            if keyword_at("BETWEEN") {
+                pending_between.push(depth);
+            }
+            let clause = PRETTY_CLAUSE_KEYWORDS.iter().find(|k| keyword_at(k));
+            let logical = PRETTY_LOGICAL_KEYWORDS.iter().find(|k| keyword_at(k));
+            let between_and = logical == Some(&"AND") && pending_between.last() == Some(&depth);
+            if between_and {
+                pending_between.pop();
+            }
+            if let Some(keyword) = clause.or(logical.filter(|_| !between_and)) {
+                let extra = usize::from(logical.is_some());
+                out.truncate(out.trim_end().len());
+                out.push('\n');
+                out.push_str(&"  ".repeat(depth + extra));
+                out.push_str(keyword);
+                i += keyword.len();
+                continue;
+            }
+        }
+
+        let len = sql[i..].chars().next().map_or(1, char::len_utf8);
+        out.push_str(&sql[i..i + len]);
+        i += len;
+    }
+    out
+}
+
+/// 处理大型数据集的批量查询结果
+#[derive(Debug)]
+pub struct BatchQueryResult {
+    pub queries: Vec<String>,
+    pub optimizations: Vec<Optimization>,
+    pub total_estimated_rows: Option<usize>,
+}
+
+/// 代表 sea-query 的表标识符
+#[derive(Debug, Clone)]
+pub struct TableName(pub String);
+
+impl Iden for TableName {
+    fn unquoted(&self, s: &mut dyn std::fmt::Write) {
+        write!(s, "{}", self.0).unwrap();
+    }
+}
+
+/// 带采样子句的表标识符，例如 `"tests" TABLESAMPLE BERNOULLI (10)`
+#[derive(Debug, Clone)]
+struct SampledTableName {
+    table: String,
+    sample_clause: String,
+}
+
+impl Iden for SampledTableName {
+    fn prepare(&self, s: &mut dyn std::fmt::Write, q: Quote) {
+        write!(s, "{}{}{} {}", q.left(), self.quoted(q), q.right(), self.sample_clause).unwrap();
+    }
+
+    fn unquoted(&self, s: &mut dyn std::fmt::Write) {
+        write!(s, "{}", self.table).unwrap();
+    }
+}
+
+/// 列标识符包装器
 #[derive(Debug, Clone)]
 pub struct ColumnName(pub String);
 
@@ -417,24 +1435,113 @@ pub struct SqlCompiler {
     optimizer: DefaultQueryOptimizer,
     batch_processor: DefaultBatchProcessor,
     table_mapper: DefaultTableMapper,
+    dialect: SqlDialect,
+    column_naming: ColumnNaming,
+    case_insensitive_strings: bool,
+    join_type: JoinType,
+    join_keys: HashMap<(String, String), JoinKey>,
+    join_key_strategy: JoinKeyStrategy,
+    current_user_id: Option<Value>,
+    max_complexity_score: Option<f64>,
+    max_joins: Option<usize>,
+    qualify_columns: QualifyMode,
+    param_style: ParamStyle,
+    schema: Option<String>,
+    default_filters: HashMap<String, Vec<FieldFilter>>,
+    string_quote_style: StringQuoteStyle,
+    mine_recently_fields: HashMap<String, MineRecentlyFields>,
+    strict_entities: bool,
+    replica_hint: Option<ReplicaHint>,
+    partition_keys: HashMap<String, String>,
+    partition_pruning: bool,
+    /// 创建时 [`OptimizationConfig::validate`] 给出的配置警告
+    config_warnings: Vec<Diagnostic>,
+    /// 通过 [`SqlCompiler::add_raw_join`] 注册的 `(源实体, 目标实体)` 到原始 ON 子句的映射
+    raw_joins: HashMap<(String, String), String>,
+    compile_cache: Option<Mutex<CompileCache>>,
+    on_optimization: Option<Mutex<OptimizationHook>>,
 }
 
+/// 优化回调，见 [`SqlCompiler::set_on_optimization`]
+type OptimizationHook = Box<dyn FnMut(&Optimization) + Send>;
+
 impl SqlCompiler {
     /// 创建新的编译器实例
     pub fn new() -> Self {
-        Self {
-            optimizer: DefaultQueryOptimizer::new(),
-            batch_processor: DefaultBatchProcessor::new(),
-            table_mapper: DefaultTableMapper::new(),
-        }
+        Self::from_config(CompilerConfig::default())
     }
     
     /// 从完整配置创建编译器
     pub fn from_config(config: CompilerConfig) -> Self {
+        let config_warnings = config.optimization_config.validate(&config.batch_config, config.dialect);
+        let table_mapper = DefaultTableMapper::with_mappings(config.table_mapping)
+            .with_naming_strategy(config.naming_strategy);
         Self {
             optimizer: DefaultQueryOptimizer::with_config(config.optimization_config),
-            batch_processor: DefaultBatchProcessor::with_config(config.batch_config),
-            table_mapper: DefaultTableMapper::with_mappings(config.table_mapping),
+            batch_processor: DefaultBatchProcessor::with_config(config.batch_config)
+                .with_dialect(config.dialect)
+                .with_table_mapper(table_mapper.clone()),
+            table_mapper,
+            dialect: config.dialect,
+            column_naming: config.column_naming,
+            case_insensitive_strings: config.case_insensitive_strings,
+            join_type: config.join_type,
+            join_keys: config.join_keys,
+            join_key_strategy: config.join_key_strategy,
+            current_user_id: config.current_user_id,
+            max_complexity_score: config.max_complexity_score,
+            max_joins: config.max_joins,
+            qualify_columns: config.qualify_columns,
+            param_style: config.param_style,
+            schema: config.schema,
+            default_filters: config.default_filters,
+            string_quote_style: config.string_quote_style,
+            mine_recently_fields: config.mine_recently_fields,
+            strict_entities: config.strict_entities,
+            replica_hint: config.replica_hint,
+            partition_keys: config.partition_keys,
+            partition_pruning: config.partition_pruning,
+            config_warnings,
+            raw_joins: HashMap::new(),
+            compile_cache: config.cache_config.enable_compile_cache
+                .then(|| Mutex::new(CompileCache::new(config.cache_config.max_entries))),
+            on_optimization: None,
+        }
+    }
+
+    /// 创建编译器时检查出的配置警告，例如 `max_in_values` 为 0 或批次大小超过方言的参数上限
+    pub fn config_warnings(&self) -> &[Diagnostic] {
+        &self.config_warnings
+    }
+
+    /// 为关联链中相邻的两个实体注册手写的 ON 子句，用于连接键无法表达的关联
+    ///
+    /// 关联Filter经过这一跳时，ON 子句原样写入 SQL 并优先于连接键配置，目标表不加 `joined_table_N` 别名，
+    /// 因此 ON 子句和该关联Filter的条件都以实际表名引用目标表，例如
+    /// `compiler.add_raw_join("Test", "Run", "tests.id = test_runs.test_id AND test_runs.active")`。
+    /// ON 子句不会经过任何转义或校验，只能传入调用方预先确认过的文本。
+    /// 由于目标表没有别名，JOIN 形式的查询中同一张表只能经原始连接关联一次，重复时编译失败
+    pub fn add_raw_join(&mut self, source: &str, target: &str, on: &str) {
+        self.raw_joins.insert((source.to_string(), target.to_string()), on.to_string());
+        self.clear_compile_cache();
+    }
+
+    /// 设置优化回调，编译条件时每产生一项优化即调用一次，便于在整个编译过程中统一记录
+    ///
+    /// 命中编译缓存的结果不会再次触发回调；[`SqlCompiler::plan`] 等只做预览的方法不会触发回调
+    pub fn set_on_optimization(&mut self, hook: impl FnMut(&Optimization) + Send + 'static) {
+        self.on_optimization = Some(Mutex::new(Box::new(hook)));
+    }
+
+    /// 将实际编译出 SQL 时应用的优化逐项通知给优化回调
+    ///
+    /// 回调曾经 panic 导致互斥锁中毒时仍然继续调用，不让一次失败的回调影响之后的编译
+    fn notify_optimizations(&self, optimizations: &[Optimization]) {
+        if let Some(hook) = &self.on_optimization {
+            let mut hook = hook.lock().unwrap_or_else(PoisonError::into_inner);
+            for optimization in optimizations {
+                (hook)(optimization);
+            }
         }
     }
 
@@ -454,7 +1561,10 @@ impl SqlCompiler {
     }
 
     /// 获取优化器的可变引用
+    ///
+    /// 配置可能因此改变，已缓存的编译结果会被清空
     pub fn optimizer_mut(&mut self) -> &mut DefaultQueryOptimizer {
+        self.clear_compile_cache();
         &mut self.optimizer
     }
 
@@ -464,10 +1574,25 @@ impl SqlCompiler {
     }
 
     /// 获取表映射器的可变引用
+    ///
+    /// 映射可能因此改变，已缓存的编译结果会被清空；批量处理器的表映射不会随之改变
     pub fn table_mapper_mut(&mut self) -> &mut DefaultTableMapper {
+        self.clear_compile_cache();
         &mut self.table_mapper
     }
 
+    /// 获取编译缓存的统计信息，未启用缓存时返回 `None`
+    pub fn compile_cache_stats(&self) -> Option<CacheStats> {
+        self.compile_cache.as_ref().map(|cache| cache.lock().unwrap().stats())
+    }
+
+    /// 清空编译缓存
+    pub fn clear_compile_cache(&self) {
+        if let Some(cache) = &self.compile_cache {
+            cache.lock().unwrap().clear();
+        }
+    }
+
     /// 编译并优化查询的便捷方法
     pub fn compile_optimized(&mut self, mut query: AstQuery, entity: &str) -> Result<CompileResult, CompileError> {
         let optimizations = self.optimizer.optimize(&mut query);
@@ -476,574 +1601,3991 @@ impl SqlCompiler {
         Ok(result)
     }
 
-    /// 编译批量查询的便捷方法
-    pub fn compile_batch_query(&self, query: AstQuery, entity: &str) -> Result<BatchQueryResult, CompileError> {
-        let batch_config = &self.batch_processor.config;
-        self.batch_processor.compile_batch(query, entity, batch_config)
+    /// 估算查询复杂度的便捷方法，自动将实体名映射为表名
+    pub fn estimate_query_complexity(&self, query: &AstQuery, entity: &str) -> QueryComplexity {
+        self.batch_processor.estimate_query_complexity_for(query, entity, &self.table_mapper)
     }
 
-    /// 将 "table.column" 格式的字符串转换为 sea-query 的列引用表达式
-    fn field_to_col_expr(&self, field: &str) -> Expr {
-        let parts: Vec<&str> = field.splitn(2, '.').collect();
-        if parts.len() == 2 {
-            Expr::col((TableName(parts[0].to_string()), ColumnName(parts[1].to_string())))
-        } else {
-            Expr::col(ColumnName(field.to_string()))
-        }
+    /// 将查询编译为 sea-query 的 `SelectStatement`，调用方可以在构建 SQL 前继续追加条件、JOIN 或分页
+    ///
+    /// 按编译器方言构建该语句得到的 SQL 与 [`QueryCompiler::compile`] 的结果一致 (不含只读副本提示)
+    pub fn compile_to_statement(&self, query: AstQuery, entity: &str) -> Result<SelectStatement, CompileError> {
+        self.build_select(query, entity).map(|(select, _)| select)
     }
-}
 
-impl Default for SqlCompiler {
-    fn default() -> Self {
-        Self::new()
+    /// 只分析编译时会应用的优化 (OR 转 IN、大型 IN 拆分为 UNION 等)，不渲染 SQL，便于界面预览
+    ///
+    /// 返回的优化与 [`QueryCompiler::compile`] 结果中的 `optimizations` 一致
+    pub fn plan(&self, query: AstQuery, entity: &str) -> Result<Vec<Optimization>, CompileError> {
+        self.build_select_quiet(query, entity).map(|(_, optimizations)| optimizations)
     }
-}
 
-impl QueryCompiler for SqlCompiler {
-    fn compile(&self, query: AstQuery, entity: &str) -> Result<CompileResult, CompileError> {
-        let mut optimizations = Vec::new();
-        
-        // 获取实际的表名
-        let table_name = self.table_mapper.get_table_name(entity);
-        
-        // 从基本 SELECT 查询开始
-        let mut select = SelectStatement::new();
-        select.from(TableName(table_name));
-        select.column(Asterisk);
+    /// 只编译基础Filter得到 WHERE 条件片段（不含 SELECT/FROM），便于嵌入手写的查询
+    ///
+    /// 没有基础Filter时返回 `TRUE`；关联Filter需要 JOIN、采样需要修改 FROM，都无法表示为单独的条件片段
+    pub fn compile_where(&self, query: AstQuery, entity: &str) -> Result<String, CompileError> {
+        if query.branches().any(|branch| !branch.cross_filters.is_empty()) || query.sample_percent.is_some() {
+            return Err(CompileError::new(
+                "WHERE 片段只支持基础Filter，不支持关联Filter和采样子句".to_string(),
+            ));
+        }
+        self.check_entities(&query, entity)?;
+        let query = self.prepare_query(query, entity);
+        if query.field_filters().next().is_none() {
+            return Ok("TRUE".to_string());
+        }
 
-        // 处理基础Filter
-        if !query.base_filters.is_empty() {
-            let (conditions, mut filter_opts) = self.compile_field_filters(&query.base_filters, entity)?;
-            optimizations.append(&mut filter_opts);
-            select.and_where(conditions);
+        // 段落级 OR 的各分支以 OR 连接
+        let mut optimizations = Vec::new();
+        let mut branch_conditions = Vec::new();
+        for branch in query.branches() {
+            let (condition, mut branch_opts) = self.compile_base_filters(branch, entity, false)?;
+            optimizations.append(&mut branch_opts);
+            branch_conditions.push(condition);
         }
+        self.notify_optimizations(&optimizations);
+        let condition = branch_conditions.into_iter().reduce(|acc, expr| acc.or(expr)).expect("至少有一个分支");
+        self.render_inline(|query_builder, sql| query_builder.prepare_simple_expr(&condition, sql))
+    }
 
-        // 处理关联Filter (JOINs)
-        let mut join_index = 0;
-        for cross_filter in query.cross_filters {
-            let (join_conditions, mut cross_opts) = self.compile_cross_filter(&cross_filter, &mut join_index, &cross_filter.target_entity.0)?;
-            optimizations.append(&mut cross_opts);
-            
-            // 获取关联表的实际名称
-            let join_table_name = self.table_mapper.get_table_name(&cross_filter.target_entity.0);
-            
-            // 添加 JOIN
-            select.join(
-                JoinType::InnerJoin,
-                TableName(format!("{} AS joined_table_{}", join_table_name, join_index)),
-                Expr::col((TableName(self.table_mapper.get_table_name(entity)), ColumnName("id".to_string())))
-                    .equals((TableName(format!("joined_table_{}", join_index)), ColumnName("id".to_string())))
-            );
+    /// 用同一份配置和编译缓存依次编译多个查询，单个查询失败不影响其余查询
+    ///
+    /// 返回的结果与输入按顺序一一对应
+    pub fn compile_many(&self, queries: &[(AstQuery, &str)]) -> Vec<Result<CompileResult, CompileError>> {
+        queries.iter()
+            .map(|(query, entity)| self.compile(query.clone(), entity))
+            .collect()
+    }
+
+    /// 单独预览一个关联Filter生成的 SQL 片段：JOIN 链以及 WHERE 条件，不含 `SELECT ... FROM`
+    ///
+    /// 片段中的别名与该关联Filter作为查询中第一个关联Filter时一致
+    pub fn compile_cross_filter_preview(&self, cross_filter: &CrossFilter, base_entity: &str) -> Result<String, CompileError> {
+        let table_name = self.table_mapper.get_table_name(base_entity);
+        let mut select = SelectStatement::new();
+        select.from(self.table_ref(TableName(table_name.clone()))).column(Asterisk);
+        let prefix = self.render(&select)?;
 
-            select.and_where(join_conditions);
+        self.apply_cross_filter(&mut select, cross_filter, &table_name, &mut 0)?;
+        let sql = self.render(&select)?;
+        Ok(sql[prefix.len()..].trim_start().to_string())
+    }
+
+    /// 将查询编译为参数化 SQL，值通过占位符绑定而不是内联到 SQL 中
+    ///
+    /// 占位符风格由 [`CompilerConfig::param_style`] 决定，参数化结果不经过编译缓存。
+    /// 参数在渲染 SQL 的同时收集，`values` 的顺序总是与占位符在最终 SQL 中从左到右出现的顺序一致
+    /// (包括 JOIN、子查询、ORDER BY 以及优化改写后的条件)，可以直接按顺序绑定，例如 sqlx 的 `query_with`
+    pub fn compile_parameterized(&self, query: AstQuery, entity: &str) -> Result<ParameterizedQuery, CompileError> {
+        let (select, optimizations) = self.build_select(query, entity)?;
+        let query_builder = self.query_builder()?;
+        match self.param_style {
+            ParamStyle::Positional => {
+                let (sql, values) = select.build_any(query_builder.as_ref());
+                Ok(ParameterizedQuery {
+                    sql: self.with_replica_hint(sql)?,
+                    values: values.into_iter().collect(),
+                    named_values: HashMap::new(),
+                    optimizations,
+                })
+            }
+            ParamStyle::Named(prefix) => {
+                let mut writer = NamedParamWriter::new(prefix);
+                select.build_collect_any_into(query_builder.as_ref(), &mut writer);
+                let named_values = writer.values.iter().cloned().enumerate()
+                    .map(|(i, value)| (format!("p{}", i), value))
+                    .collect();
+                Ok(ParameterizedQuery {
+                    sql: self.with_replica_hint(writer.sql)?,
+                    values: writer.values,
+                    named_values,
+                    optimizations,
+                })
+            }
         }
+    }
 
-        // 构建最终 SQL
-        let sql = select.to_string(PostgresQueryBuilder);
+    /// 将查询编译为 `SELECT COUNT(*)`，统计满足Filter的行数
+    ///
+    /// FROM、JOIN 和 WHERE 与 [`QueryCompiler::compile`] 的结果一致，排序被去掉
+    pub fn compile_count(&self, query: AstQuery, entity: &str) -> Result<String, CompileError> {
+        let (select, _) = self.build_select(query, entity)?;
+        self.with_replica_hint(self.render(&Self::count_select(select))?)
+    }
 
-        Ok(CompileResult {
-            sql,
-            optimizations,
+    /// 为分页界面同时编译当前页的数据查询和总行数查询，两者由同一个 SELECT 语句派生
+    ///
+    /// `offset` 为跳过的行数，而不是页码
+    pub fn compile_paginated(&self, query: AstQuery, entity: &str, limit: u64, offset: u64) -> Result<PaginatedQueries, CompileError> {
+        let (mut select, _) = self.build_select(query, entity)?;
+        let count_sql = self.with_replica_hint(self.render(&Self::count_select(select.clone()))?)?;
+        select.limit(limit).offset(offset);
+        Ok(PaginatedQueries {
+            data_sql: self.with_replica_hint(self.render(&select)?)?,
+            count_sql,
         })
     }
-    
-    fn name(&self) -> &'static str {
-        "SeaQuerySqlCompiler"
-    }
-    
-    fn supported_dialect(&self) -> SqlDialect {
-        SqlDialect::PostgreSQL
+
+    /// 将 SELECT 列替换为 `COUNT(*)` 并去掉排序
+    fn count_select(mut select: SelectStatement) -> SelectStatement {
+        select.clear_selects().clear_order_by().expr(Func::count(Expr::col(Asterisk)));
+        select
     }
-}
 
-impl SqlCompiler {
-    /// 编译字段Filter并进行优化
-    fn compile_field_filters(&self, filters: &[FieldFilter], entity: &str) -> Result<(SimpleExpr, Vec<Optimization>), CompileError> {
-        let mut optimizations = Vec::new();
-        let mut conditions = Vec::new();
+    /// 将查询编译为 `DELETE FROM ... WHERE ...`，删除满足Filter的主实体行
+    ///
+    /// DELETE 无法可移植地使用 JOIN，关联Filter编译为相关的 EXISTS 子查询。
+    /// 没有任何条件 (包括段落级 OR 的某个分支为空) 时会删除整张表，因此返回错误
+    pub fn compile_delete(&self, query: AstQuery, entity: &str) -> Result<CompileResult, CompileError> {
+        let diagnostics = self.join_key_diagnostics(&query);
+        let (condition, optimizations) = self.build_predicate(query, entity)?;
+        self.notify_optimizations(&optimizations);
 
-        for filter in filters {
-            // 使用实际的表名前缀
-            let table_name = self.table_mapper.get_table_name(entity);
-            let qualified_field = format!("{}.{}", table_name, filter.field.0);
-            let (condition, mut opts) = self.compile_condition(&qualified_field, &filter.condition)?;
-            optimizations.append(&mut opts);
-            conditions.push(condition);
-        }
+        let mut delete = DeleteStatement::new();
+        delete.from_table(self.table_ref(TableName(self.table_mapper.get_table_name(entity))))
+            .and_where(condition);
 
-        // 用 AND 组合所有条件
-        let combined = self.combine_conditions_with_and(conditions);
-        
-        Ok((combined, optimizations))
+        let sql = self.render_inline(|query_builder, sql| delete.build_collect_any_into(query_builder, sql))?;
+        Ok(CompileResult { sql, optimizations, diagnostics })
     }
 
-    /// 编译关联Filter并进行优化
-    fn compile_cross_filter(&self, cross_filter: &CrossFilter, join_index: &mut usize, _join_entity: &str) -> Result<(SimpleExpr, Vec<Optimization>), CompileError> {
-        *join_index += 1;
-        
-        let mut optimizations = Vec::new();
-        let mut conditions = Vec::new();
-
-        for filter in &cross_filter.filters {
-            // 为字段引用使用连接表的实际名称
-            let qualified_field = format!("joined_table_{}.{}", join_index, filter.field.0);
-            let (condition, mut opts) = self.compile_condition(&qualified_field, &filter.condition)?;
-            optimizations.append(&mut opts);
-            conditions.push(condition);
+    /// 将查询编译为 `UPDATE ... SET ... WHERE ...`，`set` 为要更新的 `(字段, 新值)`
+    ///
+    /// 条件的编译方式与 [`SqlCompiler::compile_delete`] 相同，字段名按列命名方式转换
+    pub fn compile_update(&self, query: AstQuery, entity: &str, set: Vec<(String, Value)>) -> Result<CompileResult, CompileError> {
+        if set.is_empty() {
+            return Err(CompileError::new("UPDATE 至少需要设置一个字段".to_string()));
         }
+        let diagnostics = self.join_key_diagnostics(&query);
+        let (condition, optimizations) = self.build_predicate(query, entity)?;
+        self.notify_optimizations(&optimizations);
 
-        let combined = self.combine_conditions_with_and(conditions);
-        Ok((combined, optimizations))
+        let mut update = UpdateStatement::new();
+        update.table(self.table_ref(TableName(self.table_mapper.get_table_name(entity))))
+            .values(set.into_iter().map(|(field, value)| {
+                (ColumnName(self.column_naming.column_name(&field)), SimpleExpr::from(value))
+            }))
+            .and_where(condition);
+
+        let sql = self.render_inline(|query_builder, sql| update.build_collect_any_into(query_builder, sql))?;
+        Ok(CompileResult { sql, optimizations, diagnostics })
     }
 
-    /// 编译单个条件并进行优化
-    fn compile_condition(&self, field: &str, condition: &Condition) -> Result<(SimpleExpr, Vec<Optimization>), CompileError> {
-        let mut optimizations = Vec::new();
-        let optimizer_config = self.optimizer.optimization_config();
-        
-        let expr = match condition {
-            Condition::Comparison { op, value } => {
-                self.compile_comparison(field, op, value)?
-            }
-            Condition::And(left, right) => {
-                let (left_expr, mut left_opts) = self.compile_condition(field, left)?;
-                let (right_expr, mut right_opts) = self.compile_condition(field, right)?;
-                optimizations.append(&mut left_opts);
-                optimizations.append(&mut right_opts);
-                left_expr.and(right_expr)
+    /// 返回查询引用的全部 `(实体, 字段)`，过滤宏按本编译器对各实体的配置展开，见 [`AstQuery::referenced_fields`]
+    pub fn referenced_fields(&self, query: &AstQuery, entity: &str) -> Vec<(String, String)> {
+        query.referenced_fields_with(entity, &|filter_macro, entity| self.macro_filters(filter_macro, entity))
+    }
+
+    /// 依据结构信息校验查询中引用的字段，只检查 AST 而不进行编译
+    ///
+    /// 基础Filter针对 `entity` 校验，关联Filter针对其目标实体校验。
+    /// 结构信息提供了字段类型时，还会检查运算符是否适用于该类型，例如数字字段上的 `LIKE`
+    pub fn validate(&self, query: &AstQuery, entity: &str, schema: &dyn SchemaProvider) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+
+        for (entity, filter) in query.filters_by_entity(entity) {
+            for column in filter.columns() {
+                if !schema.has_column(entity, &column.0) {
+                    errors.push(ValidationError {
+                        entity: entity.to_string(),
+                        field: column.0.clone(),
+                        span: filter.span,
+                        message: format!("实体 {} 中不存在字段 {}", entity, column.0),
+                    });
+                }
             }
-            Condition::Or(left, right) => {
-                // 检查 OR 优化机会
-                if let Some((in_expr, opt)) = self.try_optimize_or_to_in(field, condition, optimizer_config)? {
-                    optimizations.push(opt);
-                    in_expr
-                } else {
-                    let (left_expr, mut left_opts) = self.compile_condition(field, left)?;
-                    let (right_expr, mut right_opts) = self.compile_condition(field, right)?;
-                    optimizations.append(&mut left_opts);
-                    optimizations.append(&mut right_opts);
-                    left_expr.or(right_expr)
+
+            if !matches!(filter.condition, Condition::InTuple { .. }) {
+                if let Some(column_type) = schema.column_type(entity, &filter.field.0) {
+                    let mut operators = Vec::new();
+                    Self::collect_operators(&filter.condition, &mut operators);
+                    for operator in operators.into_iter().filter(|operator| !column_type.supports(operator)) {
+                        errors.push(ValidationError {
+                            entity: entity.to_string(),
+                            field: filter.field.0.clone(),
+                            span: filter.span,
+                            message: format!(
+                                "实体 {} 的字段 {} 类型为 {:?}，不支持 {} 运算",
+                                entity, filter.field.0, column_type, operator
+                            ),
+                        });
+                    }
                 }
             }
-            Condition::Not(inner) => {
-                let (inner_expr, mut inner_opts) = self.compile_condition(field, inner)?;
-                optimizations.append(&mut inner_opts);
-                inner_expr.not()
+
+            // 子查询中的字段针对子查询实体校验
+            for (sub_entity, sub_query) in filter.condition.subqueries() {
+                errors.extend(self.validate(sub_query, &sub_entity.0, schema));
             }
-            Condition::Grouped(inner) => {
-                self.compile_condition(field, inner)?.0
+        }
+
+        errors
+    }
+
+    /// 按首次出现的顺序收集条件中受字段类型限制的运算符，`NOT LIKE` 等否定形式归入对应的运算符
+    fn collect_operators(condition: &Condition, operators: &mut Vec<&'static str>) {
+        let operator = match condition {
+            Condition::And(left, right) | Condition::Or(left, right) => {
+                Self::collect_operators(left, operators);
+                Self::collect_operators(right, operators);
+                return;
             }
-            Condition::In(values) => {
-                let in_values: Vec<Value> = values.iter()
-                    .map(|v| self.literal_to_value(v))
-                    .collect::<Result<Vec<_>, _>>()?;
-                
-                // 检查是否需要将大型 IN 子句拆分为 UNION
-                if in_values.len() > optimizer_config.max_in_values {
-                    let (expr, opt) = self.split_large_in_to_union(field, &in_values, optimizer_config);
-                    optimizations.push(opt);
-                    expr
-                } else {
-                    self.field_to_col_expr(field).is_in(in_values)
-                }
+            Condition::Not(inner) | Condition::Grouped(inner) => {
+                Self::collect_operators(inner, operators);
+                return;
             }
-            Condition::IsNull => {
-                self.field_to_col_expr(field).is_null()
+            Condition::Comparison { op, .. } | Condition::AnyOf { op, .. } | Condition::AllOf { op, .. } => match op {
+                CompOp::Gt => ">",
+                CompOp::Lt => "<",
+                CompOp::Gte => ">=",
+                CompOp::Lte => "<=",
+                CompOp::Eq | CompOp::NotEq | CompOp::NullSafeEq => return,
+            },
+            Condition::Between { .. } => "BETWEEN",
+            Condition::Like { .. } => "LIKE",
+            Condition::IsEmpty | Condition::IsNotEmpty => "IS EMPTY",
+            Condition::In(_) | Condition::InSubquery { .. } | Condition::InTuple { .. }
+            | Condition::IsNull | Condition::IsNotNull => return,
+        };
+        if !operators.contains(&operator) {
+            operators.push(operator);
+        }
+    }
+
+    /// 检查永远不会匹配的字段过滤条件，只检查 AST 而不进行编译
+    ///
+    /// 以 AND 连接的条件中出现空范围、空 IN 列表或 `IS NULL` 与值比较并存时报告；
+    /// OR 的每一侧分别检查，NOT 之下的条件不检查
+    pub fn lint(&self, query: &AstQuery, entity: &str) -> Vec<Lint> {
+        let mut lints = Vec::new();
+
+        for (entity, filter) in query.filters_by_entity(entity) {
+            let mut kinds = Vec::new();
+            Self::lint_condition(&filter.condition, &mut kinds);
+            for kind in kinds {
+                let field = &filter.field.0;
+                let message = match kind {
+                    LintKind::EmptyRange => format!("字段 {} 的取值范围为空，条件永远不会匹配", field),
+                    LintKind::EmptyInList => format!("字段 {} 的 IN 列表为空，条件永远不会匹配", field),
+                    LintKind::NullComparison => format!("字段 {} 同时要求 IS NULL 和值比较，条件永远不会匹配", field),
+                };
+                lints.push(Lint {
+                    kind,
+                    entity: entity.to_string(),
+                    field: field.clone(),
+                    span: filter.span,
+                    message,
+                });
             }
-            Condition::IsNotNull => {
-                self.field_to_col_expr(field).is_not_null()
+
+            for (sub_entity, sub_query) in filter.condition.subqueries() {
+                lints.extend(self.lint(sub_query, &sub_entity.0));
             }
-        };
+        }
 
-        Ok((expr, optimizations))
+        lints
     }
 
-    /// 将大型 IN 子句拆分为 UNION 查询
-    fn split_large_in_to_union(&self, field: &str, values: &[Value], config: &OptimizationConfig) -> (SimpleExpr, Optimization) {
-        let chunk_size = config.max_in_values;
-        let chunks: Vec<&[Value]> = values.chunks(chunk_size).collect();
-        let union_count = chunks.len();
-        
-        // 为每个块创建单独的 IN 表达式
-        let mut conditions = Vec::new();
-        for chunk in chunks {
-            let in_expr = self.field_to_col_expr(field).is_in(chunk.to_vec());
-            conditions.push(in_expr);
+    /// 检查以 AND 连接的一组条件，再分别检查其中 OR 的每一侧
+    fn lint_condition(condition: &Condition, kinds: &mut Vec<LintKind>) {
+        let mut conjuncts = Vec::new();
+        Self::collect_conjuncts(condition, &mut conjuncts);
+
+        let mut found = Vec::new();
+        if conjuncts.iter().any(|c| matches!(c, Condition::In(values) if values.is_empty())) {
+            found.push(LintKind::EmptyInList);
+        }
+        if Self::is_empty_range(&conjuncts) {
+            found.push(LintKind::EmptyRange);
+        }
+        // 与 NULL 的任何值比较都不成立
+        let has_is_null = conjuncts.iter().any(|c| matches!(c, Condition::IsNull));
+        let has_value_check = conjuncts.iter().any(|c| matches!(
+            c,
+            Condition::Comparison { .. } | Condition::In(_) | Condition::AnyOf { .. } | Condition::AllOf { .. }
+                | Condition::Between { .. } | Condition::Like { .. } | Condition::IsNotNull | Condition::IsNotEmpty
+        ));
+        if has_is_null && has_value_check {
+            found.push(LintKind::NullComparison);
+        }
+        for kind in found {
+            if !kinds.contains(&kind) {
+                kinds.push(kind);
+            }
         }
-        
-        // 用 OR 组合（在顶层有效地创建 UNION）
-        let combined = conditions.into_iter().reduce(|acc, expr| acc.or(expr)).unwrap();
-        
-        let optimization = Optimization::InToUnion {
-            field: field.to_string(),
-            total_values: values.len(),
-            union_count,
-        };
-        
-        (combined, optimization)
-    }
 
-    /// 尝试将 OR 条件优化为 IN 子句
-    fn try_optimize_or_to_in(&self, field: &str, condition: &Condition, config: &OptimizationConfig) -> Result<Option<(SimpleExpr, Optimization)>, CompileError> {
-        let equality_values = self.extract_equality_values_from_or(field, condition);
-        
-        if equality_values.len() >= config.max_or_conditions_for_in {
-            let in_values: Vec<Value> = equality_values.iter()
-                .map(|v| self.literal_to_value(v))
-                .collect::<Result<Vec<_>, _>>()?;
-            
-            let in_expr = self.field_to_col_expr(field).is_in(in_values);
-            let optimization = Optimization::OrToIn {
-                field: field.to_string(),
-                value_count: equality_values.len(),
-            };
-            
-            return Ok(Some((in_expr, optimization)));
+        for conjunct in conjuncts {
+            if let Condition::Or(left, right) = conjunct {
+                Self::lint_condition(left, kinds);
+                Self::lint_condition(right, kinds);
+            }
         }
-        
-        Ok(None)
     }
 
-    /// 从同一字段的 OR 条件中提取相等值
-    fn extract_equality_values_from_or<'a>(&self, _target_field: &str, condition: &'a Condition) -> Vec<&'a Literal> {
-        let mut values = Vec::new();
-        self.collect_equality_values(condition, &mut values);
-        values
+    /// 展开以 AND 连接 (包括括号分组) 的条件
+    fn collect_conjuncts<'a>(condition: &'a Condition, conjuncts: &mut Vec<&'a Condition>) {
+        match condition {
+            Condition::And(left, right) => {
+                Self::collect_conjuncts(left, conjuncts);
+                Self::collect_conjuncts(right, conjuncts);
+            }
+            Condition::Grouped(inner) => Self::collect_conjuncts(inner, conjuncts),
+            other => conjuncts.push(other),
+        }
     }
 
-    /// 递归收集 OR 条件中的相等值
-    fn collect_equality_values<'a>(&self, condition: &'a Condition, values: &mut Vec<&'a Literal>) {
-        match condition {
-            Condition::Comparison { op: CompOp::Eq, value } => {
-                values.push(value);
+    /// 数字比较构成的上下界是否不相交，边界为 `(值, 是否为开区间)`
+    fn is_empty_range(conjuncts: &[&Condition]) -> bool {
+        let mut lower: Option<(i64, bool)> = None;
+        let mut upper: Option<(i64, bool)> = None;
+
+        for conjunct in conjuncts {
+            let (low, high) = match conjunct {
+                Condition::Comparison { op, value: Literal::Number(n) } => match op {
+                    CompOp::Gt => (Some((*n, true)), None),
+                    CompOp::Gte => (Some((*n, false)), None),
+                    CompOp::Lt => (None, Some((*n, true))),
+                    CompOp::Lte => (None, Some((*n, false))),
+                    CompOp::Eq | CompOp::NullSafeEq => (Some((*n, false)), Some((*n, false))),
+                    CompOp::NotEq => continue,
+                },
+                Condition::Between { low: Literal::Number(low), high: Literal::Number(high), negated: false } => {
+                    (Some((*low, false)), Some((*high, false)))
+                }
+                _ => continue,
+            };
+
+            // 保留最紧的边界，同值时开区间更紧
+            if let Some((n, strict)) = low {
+                if lower.is_none_or(|(m, m_strict)| n > m || (n == m && strict && !m_strict)) {
+                    lower = Some((n, strict));
+                }
             }
-            Condition::Or(left, right) => {
-                self.collect_equality_values(left, values);
-                self.collect_equality_values(right, values);
+            if let Some((n, strict)) = high {
+                if upper.is_none_or(|(m, m_strict)| n < m || (n == m && strict && !m_strict)) {
+                    upper = Some((n, strict));
+                }
             }
-            Condition::Grouped(inner) => {
-                self.collect_equality_values(inner, values);
+        }
+
+        match (lower, upper) {
+            (Some((low, low_strict)), Some((high, high_strict))) => {
+                low > high || (low == high && (low_strict || high_strict))
             }
-            _ => {} // 其他条件类型会破坏相等模式
+            _ => false,
         }
     }
 
-    /// 用 AND 组合多个条件
-    fn combine_conditions_with_and(&self, conditions: Vec<SimpleExpr>) -> SimpleExpr {
-        if conditions.is_empty() {
-            return Expr::val(true).into();
+    /// 编译批量查询的便捷方法
+    pub fn compile_batch_query(&self, query: AstQuery, entity: &str) -> Result<BatchQueryResult, CompileError> {
+        let batch_config = &self.batch_processor.config;
+        self.batch_processor.compile_batch_with(self, query, entity, batch_config)
+    }
+
+    /// 获取目标方言对应的 sea-query 构建器
+    ///
+    /// sea-query 未提供 MsSQL / Oracle 后端，这两种方言返回错误，而不是生成其他方言的 SQL
+    fn query_builder(&self) -> Result<Box<dyn QueryBuilder>, CompileError> {
+        match self.dialect {
+            SqlDialect::PostgreSQL => Ok(Box::new(PostgresQueryBuilder)),
+            SqlDialect::MySQL => Ok(Box::new(MysqlQueryBuilder)),
+            SqlDialect::SQLite => Ok(Box::new(SqliteQueryBuilder)),
+            SqlDialect::MsSQL | SqlDialect::Oracle => Err(CompileError::new(format!(
+                "{:?} 方言没有对应的 SQL 构建器，无法生成 SQL",
+                self.dialect
+            ))),
         }
-        
-        conditions.into_iter().reduce(|acc, expr| acc.and(expr)).unwrap()
     }
 
-    /// 编译比较操作
-    fn compile_comparison(&self, field: &str, op: &CompOp, value: &Literal) -> Result<SimpleExpr, CompileError> {
-        let col = self.field_to_col_expr(field);
-        let val = self.literal_to_value(value)?;
+    /// 按配置为表名加上 schema 前缀，schema 与表名分别作为两个标识符加引号
+    fn table_ref(&self, table: impl IntoIden) -> TableRef {
+        match &self.schema {
+            Some(schema) => TableRef::SchemaTable(Alias::new(schema).into_iden(), table.into_iden()),
+            None => TableRef::Table(table.into_iden()),
+        }
+    }
 
-        let expr = match op {
-            CompOp::Eq => col.eq(val),
-            CompOp::NotEq => col.ne(val),
-            CompOp::Gt => col.gt(val),
-            CompOp::Lt => col.lt(val),
-            CompOp::Gte => col.gte(val),
-            CompOp::Lte => col.lte(val),
-        };
+    /// 按照配置的方言将查询渲染为 SQL 字符串
+    fn render(&self, select: &SelectStatement) -> Result<String, CompileError> {
+        self.render_inline(|query_builder, sql| select.build_collect_any_into(query_builder, sql))
+    }
 
-        Ok(expr)
+    /// 以内联值的方式渲染 SQL，字符串字面值按 [`CompilerConfig::string_quote_style`] 加引号
+    fn render_inline(&self, build: impl FnOnce(&dyn QueryBuilder, &mut dyn SqlWriter)) -> Result<String, CompileError> {
+        let query_builder = self.query_builder()?;
+        let mut writer = InlineWriter {
+            sql: String::new(),
+            dialect: self.dialect,
+            quote_style: self.string_quote_style,
+        };
+        build(query_builder.as_ref(), &mut writer);
+        Ok(writer.sql)
     }
 
-    /// 将 AST 字面量转换为 sea-query 值
-    fn literal_to_value(&self, literal: &Literal) -> Result<Value, CompileError> {
-        match literal {
-            Literal::String(s) => Ok(Value::String(Some(Box::new(s.clone())))),
-            Literal::Number(n) => Ok(Value::BigInt(Some(*n))),
-            Literal::Date(d) => {
-                // 处理特殊日期关键字
-                match d.as_str() {
-                    "today" => Ok(Value::String(Some(Box::new("CURRENT_DATE".to_string())))),
-                    "yesterday" => Ok(Value::String(Some(Box::new("CURRENT_DATE - INTERVAL '1 day'".to_string())))),
-                    "tomorrow" => Ok(Value::String(Some(Box::new("CURRENT_DATE + INTERVAL '1 day'".to_string())))),
-                    _ => Ok(Value::String(Some(Box::new(d.clone())))),
-                }
-            }
-            Literal::CurrentUser => Ok(Value::String(Some(Box::new("CURRENT_USER".to_string())))),
+    /// 生成目标方言的采样子句，不支持 TABLESAMPLE 的方言返回错误
+    fn sample_clause(&self, percent: u32) -> Result<String, CompileError> {
+        match self.dialect {
+            SqlDialect::PostgreSQL => Ok(format!("TABLESAMPLE BERNOULLI ({})", percent)),
+            _ => Err(CompileError::new(format!(
+                "{:?} 方言不支持 TABLESAMPLE 采样查询",
+                self.dialect
+            ))),
         }
     }
-}
-
-/// SqlCompiler 的工厂实现
-pub struct SqlCompilerFactory;
 
-impl CompilerFactory for SqlCompilerFactory {
-    type Compiler = SqlCompiler;
-    
-    fn create_default() -> Self::Compiler {
-        SqlCompiler::new()
-    }
-    
-    fn create_with_config(config: CompilerConfig) -> Result<Self::Compiler, CompileError> {
-        Ok(SqlCompiler::from_config(config))
+    /// 将 "table.column" 格式的字符串转换为 sea-query 的列引用表达式
+    fn field_to_col_expr(&self, field: &str) -> Expr {
+        let parts: Vec<&str> = field.splitn(2, '.').collect();
+        if parts.len() == 2 {
+            Expr::col((TableName(parts[0].to_string()), ColumnName(self.column_naming.column_name(parts[1]))))
+        } else {
+            Expr::col(ColumnName(self.column_naming.column_name(field)))
+        }
     }
 }
 
-/// 编译器注册表，用于管理不同的编译器实现
-pub struct CompilerRegistry {
-    compilers: HashMap<String, Box<dyn Fn() -> Box<dyn QueryCompiler>>>,
+impl Default for SqlCompiler {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl CompilerRegistry {
-    pub fn new() -> Self {
-        let mut registry = Self {
-            compilers: HashMap::new(),
+impl QueryCompiler for SqlCompiler {
+    fn compile(&self, query: AstQuery, entity: &str) -> Result<CompileResult, CompileError> {
+        let Some(cache) = &self.compile_cache else {
+            return self.compile_uncached(query, entity);
         };
-        
-        // 注册默认的 SqlCompiler
-        registry.register("sql", || Box::new(SqlCompiler::new()));
-        registry.register("default", || Box::new(SqlCompiler::new()));
-        
-        registry
-    }
-    
-    /// 注册新的编译器
-    pub fn register<F>(&mut self, name: &str, factory: F)
-    where
-        F: Fn() -> Box<dyn QueryCompiler> + 'static,
-    {
-        self.compilers.insert(name.to_string(), Box::new(factory));
+
+        let key = (query, entity.to_string());
+        if let Some(result) = cache.lock().unwrap().get(&key) {
+            return Ok(result);
+        }
+
+        let result = self.compile_uncached(key.0.clone(), entity)?;
+        cache.lock().unwrap().insert(key, result.clone());
+        Ok(result)
     }
     
-    /// 创建指定类型的编译器
-    pub fn create(&self, name: &str) -> Option<Box<dyn QueryCompiler>> {
-        self.compilers.get(name).map(|factory| factory())
+    fn name(&self) -> &'static str {
+        "SeaQuerySqlCompiler"
     }
     
-    /// 获取所有已注册的编译器名称
-    pub fn available_compilers(&self) -> Vec<String> {
-        self.compilers.keys().cloned().collect()
+    fn supported_dialect(&self) -> SqlDialect {
+        self.dialect
     }
 }
 
-impl Default for CompilerRegistry {
-    fn default() -> Self {
-        Self::new()
+impl SqlCompiler {
+    /// 不经过缓存的编译实现
+    fn compile_uncached(&self, query: AstQuery, entity: &str) -> Result<CompileResult, CompileError> {
+        let mut result = self.compile_unhinted(query, entity)?;
+        result.sql = self.with_replica_hint(result.sql)?;
+        Ok(result)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::ast::*;
+    /// 编译但不添加只读副本提示，供需要在外层统一添加提示的调用方使用
+    fn compile_unhinted(&self, query: AstQuery, entity: &str) -> Result<CompileResult, CompileError> {
+        let mut diagnostics = self.join_key_diagnostics(&query);
+        diagnostics.extend(self.partition_diagnostics(&query, entity));
+        let (select, optimizations) = self.build_select(query, entity)?;
+        Ok(CompileResult {
+            sql: self.render(&select)?,
+            optimizations,
+            diagnostics,
+        })
+    }
 
-    fn create_test_compiler() -> SqlCompiler {
-        let mut compiler = SqlCompiler::new();
-        let mut mapping = HashMap::new();
-        mapping.insert("Test".to_string(), "tests".to_string());
-        mapping.insert("Run".to_string(), "test_runs".to_string());
-        compiler.table_mapper_mut().set_table_mapping(mapping);
-        compiler
+    /// 按配置为 SELECT 查询加上只读副本提示
+    fn with_replica_hint(&self, sql: String) -> Result<String, CompileError> {
+        let Some(hint) = &self.replica_hint else {
+            return Ok(sql);
+        };
+        let (ReplicaHint::Comment(text) | ReplicaHint::OptimizerHint(text)) = hint;
+        if text.contains("*/") {
+            return Err(CompileError::new("只读副本提示不能包含 `*/`".to_string()));
+        }
+        match hint {
+            ReplicaHint::Comment(text) => Ok(format!("/* {} */ {}", text, sql)),
+            ReplicaHint::OptimizerHint(text) => match self.dialect {
+                SqlDialect::MySQL => {
+                    Ok(sql.replacen("SELECT ", &format!("SELECT /*+ {} */ ", text), 1))
+                }
+                _ => Err(CompileError::new(format!("{:?} 方言不支持优化器提示", self.dialect))),
+            },
+        }
     }
 
-    struct CustomCompiler {
-        name: String,
-        dialect: SqlDialect,
-        config: OptimizationConfig,
+    /// 配置了复杂度上限时，拒绝估算评分超过上限的查询
+    fn check_complexity(&self, query: &AstQuery, entity: &str) -> Result<(), CompileError> {
+        let Some(limit) = self.max_complexity_score else {
+            return Ok(());
+        };
+
+        let score = self.estimate_query_complexity(query, entity).complexity_score;
+        if score > limit {
+            return Err(CompileError::new(format!(
+                "查询复杂度评分 {} 超过上限 {}",
+                score, limit
+            )));
+        }
+        Ok(())
     }
 
-    impl CustomCompiler {
-        fn new(name: String, dialect: SqlDialect) -> Self {
-            Self { 
-                name, 
-                dialect,
-                config: OptimizationConfig::default(),
+    /// 编译前的查询预处理，所有编译路径共用：展开 OR 连接方式、过滤宏和默认Filter，再按分区键改写条件
+    fn prepare_query(&self, query: AstQuery, entity: &str) -> AstQuery {
+        let query = self.expand_macros(query.expand_cross_combinator(), entity);
+        let query = self.with_default_filters(query, entity);
+        self.prune_partitions(query, entity)
+    }
+
+    /// 将每个分支中的过滤宏展开为主实体的基础Filter，展开得到的Filter与已有Filter重复时只保留一份
+    fn expand_macros(&self, mut query: AstQuery, entity: &str) -> AstQuery {
+        let branches = std::iter::once(&mut query.base_filters).zip(std::iter::once(&mut query.macros))
+            .chain(query.or_branches.iter_mut().map(|branch| (&mut branch.base_filters, &mut branch.macros)));
+        for (base_filters, macros) in branches {
+            for filter_macro in macros.drain(..) {
+                for filter in self.macro_filters(filter_macro, entity) {
+                    if !base_filters.contains(&filter) {
+                        base_filters.push(filter);
+                    }
+                }
             }
         }
+        query
     }
 
-    impl QueryCompiler for CustomCompiler {
-        fn compile(&self, _query: AstQuery, _entity: &str) -> Result<CompileResult, CompileError> {
-            Ok(CompileResult {
-                sql: format!("-- Generated by {} for {:?}\nSELECT * FROM custom_table;", self.name, self.dialect),
-                optimizations: vec![],
-            })
-        }
-        
-        fn name(&self) -> &'static str {
-            "CustomCompiler"
-        }
-        
-        fn supported_dialect(&self) -> SqlDialect {
-            self.dialect
+    /// 过滤宏针对 `entity` 展开得到的字段Filter
+    fn macro_filters(&self, filter_macro: FilterMacro, entity: &str) -> Vec<FieldFilter> {
+        let comparison = |field: &str, op, value| FieldFilter {
+            field: Identifier(field.to_string()),
+            condition: Condition::Comparison { op, value },
+            span: None,
+        };
+        match filter_macro {
+            FilterMacro::MineRecently => {
+                let default_fields = MineRecentlyFields::default();
+                let fields = self.mine_recently_fields.get(entity).unwrap_or(&default_fields);
+                vec![
+                    comparison(&fields.owner_field, CompOp::Eq, Literal::CurrentUser),
+                    comparison(
+                        &fields.modified_field,
+                        CompOp::Gt,
+                        Literal::RelativeDate { base: "today".to_string(), offset_days: -i64::from(fields.days) },
+                    ),
+                ]
+            }
         }
     }
 
-    impl QueryOptimizer for CustomCompiler {
-        fn optimize(&self, _query: &mut AstQuery) -> Vec<Optimization> {
-            vec![Optimization::ConditionSimplification {
-                original: "custom_original".to_string(),
-                simplified: "custom_simplified".to_string(),
-            }]
+    /// 启用 `partition_pruning` 时，把分区键上 `YYYY-MM-DD` 字符串的相等比较改写为半开区间
+    fn prune_partitions(&self, mut query: AstQuery, entity: &str) -> AstQuery {
+        if !self.partition_pruning || self.partition_keys.is_empty() {
+            return query;
         }
-        
-        fn optimization_config(&self) -> &OptimizationConfig {
-            &self.config
+
+        let rewrite = |filters: &mut dyn Iterator<Item = &mut FieldFilter>, entity: &str| {
+            let Some(key) = self.partition_keys.get(entity) else {
+                return;
+            };
+            for filter in filters.filter(|filter| &filter.field.0 == key) {
+                Self::rewrite_day_equality(&mut filter.condition);
+            }
+        };
+        let branches = std::iter::once((&mut query.base_filters, &mut query.base_or_groups, &mut query.cross_filters))
+            .chain(query.or_branches.iter_mut().map(|branch| {
+                (&mut branch.base_filters, &mut branch.base_or_groups, &mut branch.cross_filters)
+            }));
+        for (base_filters, base_or_groups, cross_filters) in branches {
+            rewrite(&mut base_filters.iter_mut().chain(base_or_groups.iter_mut().flatten()), entity);
+            for cross_filter in cross_filters {
+                rewrite(&mut cross_filter.filters.iter_mut(), &cross_filter.target_entity.0);
+            }
         }
-        
-        fn set_optimization_config(&mut self, _config: OptimizationConfig) {
+        query
+    }
+
+    /// 将条件中 `= 'YYYY-MM-DD'` 改写为 `>= 当天 AND < 次日`，`!=` 改写为区间之外
+    fn rewrite_day_equality(condition: &mut Condition) {
+        match condition {
+            Condition::Comparison { op: op @ (CompOp::Eq | CompOp::NotEq), value: Literal::String(date) } => {
+                let Some(next_day) = next_day(date) else {
+                    return;
+                };
+                let bound = |op, date: &str| Box::new(Condition::Comparison { op, value: Literal::String(date.to_string()) });
+                let range = if *op == CompOp::Eq {
+                    Condition::And(bound(CompOp::Gte, date), bound(CompOp::Lt, &next_day))
+                } else {
+                    Condition::Or(bound(CompOp::Lt, date), bound(CompOp::Gte, &next_day))
+                };
+                *condition = Condition::Grouped(Box::new(range));
+            }
+            Condition::And(left, right) | Condition::Or(left, right) => {
+                Self::rewrite_day_equality(left);
+                Self::rewrite_day_equality(right);
+            }
+            Condition::Not(inner) | Condition::Grouped(inner) => Self::rewrite_day_equality(inner),
+            _ => {}
         }
     }
 
-    impl BatchQueryCompiler for CustomCompiler {
-        fn compile_batch(&self, query: AstQuery, entity: &str, _config: &BatchConfig) -> Result<BatchQueryResult, CompileError> {
-            let result = self.compile(query, entity)?;
-            Ok(BatchQueryResult {
-                queries: vec![result.sql],
-                optimizations: result.optimizations,
-                total_estimated_rows: Some(100),
-            })
+    /// 分区键列会被 `LOWER()` 包裹而无法分区裁剪时给出警告
+    fn partition_diagnostics(&self, query: &AstQuery, entity: &str) -> Vec<Diagnostic> {
+        if !self.case_insensitive_strings {
+            return Vec::new();
         }
-        
-        fn estimate_query_complexity(&self, _query: &AstQuery) -> QueryComplexity {
-            QueryComplexity {
-                estimated_rows: Some(100),
-                join_count: 0,
-                condition_count: 1,
-                complexity_score: 1.0,
+
+        let mut diagnostics = Vec::new();
+        for (filter_entity, filter) in query.filters_by_entity(entity) {
+            let Some(key) = self.partition_keys.get(filter_entity) else {
+                continue;
+            };
+            if &filter.field.0 == key && self.wraps_partition_key(&filter.condition) {
+                let diagnostic = Diagnostic::warning(format!(
+                    "实体 {} 的分区键 {} 会被 LOWER() 包裹，无法利用分区裁剪",
+                    filter_entity, key
+                ));
+                if !diagnostics.contains(&diagnostic) {
+                    diagnostics.push(diagnostic);
+                }
             }
         }
+        diagnostics
     }
 
-    impl TableMappingProvider for CustomCompiler {
-        fn get_table_name(&self, entity: &str) -> String {
-            format!("custom_{}", entity.to_lowercase())
+    /// 条件中是否有会编译为 `LOWER(col)` 的字符串相等比较 (启用分区裁剪时改写为区间的单日比较除外)
+    fn wraps_partition_key(&self, condition: &Condition) -> bool {
+        match condition {
+            Condition::Comparison { op: CompOp::Eq | CompOp::NotEq, value: Literal::String(s) } => {
+                !(self.partition_pruning && next_day(s).is_some())
+            }
+            Condition::In(values) => values.iter().any(|v| matches!(v, Literal::String(_))),
+            Condition::And(left, right) | Condition::Or(left, right) => {
+                self.wraps_partition_key(left) || self.wraps_partition_key(right)
+            }
+            Condition::Not(inner) | Condition::Grouped(inner) => self.wraps_partition_key(inner),
+            _ => false,
+        }
+    }
+
+    /// 加入实体的隐式过滤条件：主实体的条件加入每个 OR 分支的基础Filter，目标实体的条件加入对应的关联Filter
+    ///
+    /// 关联链中间实体的条件在 JOIN 时加入对应一跳的 ON 条件 (见 `with_hop_default_filters`)；
+    /// 子查询编译时以其实体为主实体再次经过这里，因此子查询实体的条件同样生效
+    fn with_default_filters(&self, mut query: AstQuery, entity: &str) -> AstQuery {
+        if self.default_filters.is_empty() {
+            return query;
+        }
+
+        let add = |filters: &mut Vec<FieldFilter>, entity: &str| {
+            for filter in self.default_filters.get(entity).into_iter().flatten() {
+                if !filters.contains(filter) {
+                    filters.push(filter.clone());
+                }
+            }
+        };
+        let branches = std::iter::once(&mut query.base_filters).zip(std::iter::once(&mut query.cross_filters))
+            .chain(query.or_branches.iter_mut().map(|branch| (&mut branch.base_filters, &mut branch.cross_filters)));
+        for (base_filters, cross_filters) in branches {
+            add(base_filters, entity);
+            for cross_filter in cross_filters {
+                add(&mut cross_filter.filters, &cross_filter.target_entity.0);
+            }
+        }
+        query
+    }
+
+    /// 启用 `strict_entities` 时，拒绝主实体或关联Filter需要 JOIN 的实体没有表映射的查询
+    fn check_entities(&self, query: &AstQuery, entity: &str) -> Result<(), CompileError> {
+        if !self.strict_entities {
+            return Ok(());
+        }
+        if !self.table_mapper.is_mapped(entity) {
+            return Err(CompileError::new(format!("实体 `{}` 没有配置表映射", entity)));
+        }
+        let cross_filters = query.branches().flat_map(|branch| branch.cross_filters.iter());
+        for cross_filter in cross_filters {
+            if let Some(unmapped) = cross_filter.join_path().find(|e| !self.table_mapper.is_mapped(&e.0)) {
+                return Err(CompileError::new(format!(
+                    "关联Filter <{}> 中的实体 `{}` 没有配置表映射",
+                    std::iter::once(&cross_filter.source_entity).chain(cross_filter.join_path())
+                        .map(|e| e.0.as_str()).collect::<Vec<_>>().join("-"),
+                    unmapped.0
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// 配置了 JOIN 数量上限时，拒绝 JOIN 数量超过上限的查询，多跳关联链按跳数计算
+    fn check_join_limit(&self, query: &AstQuery) -> Result<(), CompileError> {
+        let join_count: usize = query.branches()
+            .flat_map(|branch| &branch.cross_filters)
+            .map(|cross_filter| cross_filter.join_path().count())
+            .sum();
+        match self.max_joins {
+            Some(limit) if join_count > limit => Err(CompileError::new(format!(
+                "查询包含 {} 个 JOIN，超过 JOIN 数量上限 {}",
+                join_count, limit
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// 原始连接的目标表不加别名，同一张表在一条 SELECT 中出现两次会产生重复的表名，
+    /// 因此拒绝多个关联Filter经过指向同一张表的原始连接，或原始连接的目标就是主表的查询
+    fn check_raw_join_reuse(&self, query: &AstQuery, base_table: &str) -> Result<(), CompileError> {
+        let mut joined_tables = vec![base_table.to_string()];
+        for cross_filter in query.branches().flat_map(|branch| &branch.cross_filters) {
+            let mut previous = &cross_filter.source_entity;
+            for next in cross_filter.join_path() {
+                if self.raw_joins.contains_key(&(previous.0.clone(), next.0.clone())) {
+                    let table_name = self.table_mapper.get_table_name(&next.0);
+                    if joined_tables.contains(&table_name) {
+                        return Err(CompileError::new(format!(
+                            "原始连接 {}-{} 的目标表 `{}` 在查询中已经出现，原始连接不加别名，无法重复 JOIN",
+                            previous.0, next.0, table_name
+                        )));
+                    }
+                    joined_tables.push(table_name);
+                }
+                previous = next;
+            }
+        }
+        Ok(())
+    }
+
+    /// 获取关联链中相邻两个实体的连接键，显式配置优先于推导策略
+    fn join_key(&self, source: &str, target: &str) -> Option<JoinKey> {
+        self.join_keys.get(&(source.to_string(), target.to_string()))
+            .cloned()
+            .or_else(|| self.join_key_strategy.join_key(source, target))
+    }
+
+    /// 获取关联链中相邻两个实体的连接列，既未配置也无法推导时为 `(id, id)`
+    fn join_columns(&self, source: &str, target: &str) -> (String, String) {
+        match self.join_key(source, target) {
+            Some(key) => (key.source_column, key.target_column),
+            None => ("id".to_string(), "id".to_string()),
+        }
+    }
+
+    /// 为未配置连接键、退化为 `id = id` 的关联生成警告，包括子查询中的关联
+    fn join_key_diagnostics(&self, query: &AstQuery) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        for cross_filter in query.branches().flat_map(|branch| &branch.cross_filters) {
+            let mut previous = &cross_filter.source_entity;
+            for next in cross_filter.join_path() {
+                let raw_join = self.raw_joins.contains_key(&(previous.0.clone(), next.0.clone()));
+                if !raw_join && self.join_key(&previous.0, &next.0).is_none() {
+                    let diagnostic = Diagnostic::warning(format!(
+                        "关联 {}-{} 未配置连接键，默认使用 id = id",
+                        previous.0, next.0
+                    ));
+                    if !diagnostics.contains(&diagnostic) {
+                        diagnostics.push(diagnostic);
+                    }
+                }
+                previous = next;
+            }
+        }
+
+        for filter in query.field_filters() {
+            for (_, subquery) in filter.condition.subqueries() {
+                for diagnostic in self.join_key_diagnostics(subquery) {
+                    if !diagnostics.contains(&diagnostic) {
+                        diagnostics.push(diagnostic);
+                    }
+                }
+            }
         }
+
+        diagnostics
+    }
+
+    /// 构建 SELECT 语句，内联渲染与参数化渲染共用，应用的优化会通知优化回调
+    fn build_select(&self, query: AstQuery, entity: &str) -> Result<(SelectStatement, Vec<Optimization>), CompileError> {
+        let (select, optimizations) = self.build_select_quiet(query, entity)?;
+        self.notify_optimizations(&optimizations);
+        Ok((select, optimizations))
+    }
+
+    /// 构建 SELECT 语句但不通知优化回调，供预览以及嵌套在外层查询中的子查询使用
+    fn build_select_quiet(&self, query: AstQuery, entity: &str) -> Result<(SelectStatement, Vec<Optimization>), CompileError> {
+        self.check_entities(&query, entity)?;
+        self.check_join_limit(&query)?;
+        self.check_complexity(&query, entity)?;
+        let query = self.prepare_query(query, entity);
+        let mut optimizations = Vec::new();
         
-        fn set_table_mapping(&mut self, _mapping: HashMap<String, String>) {
+        // 获取实际的表名
+        let table_name = self.table_mapper.get_table_name(entity);
+        self.check_raw_join_reuse(&query, &table_name)?;
+        
+        // 从基本 SELECT 查询开始
+        let mut select = SelectStatement::new();
+        match query.sample_percent {
+            Some(percent) => {
+                let sample_clause = self.sample_clause(percent)?;
+                select.from(self.table_ref(SampledTableName { table: table_name.clone(), sample_clause }));
+            }
+            None => {
+                select.from(self.table_ref(TableName(table_name.clone())));
+            }
+        }
+        select.column(Asterisk);
+
+        let has_joins = query.branches().any(|branch| !branch.cross_filters.is_empty());
+        let mut join_index = 0;
+        self.apply_sort(&mut select, &query.sort, &table_name, has_joins);
+
+        if !query.or_branches.is_empty() {
+            // 段落级 OR：各分支的条件以 OR 连接。关联只在部分分支中要求匹配，
+            // 因此全部使用 LEFT JOIN，由各分支的条件决定是否需要关联行
+            let mut branch_conditions = Vec::new();
+            for branch in query.branches() {
+                let mut conditions = Vec::new();
+                if !branch.base_filters.is_empty() || !branch.base_or_groups.is_empty() {
+                    let (condition, mut filter_opts) = self.compile_base_filters(branch, entity, has_joins)?;
+                    optimizations.append(&mut filter_opts);
+                    conditions.push(condition);
+                }
+                for cross_filter in &branch.cross_filters {
+                    let (condition, mut cross_opts) = self.join_cross_filter(
+                        &mut select, cross_filter, &table_name, &mut join_index, JoinType::LeftJoin,
+                    )?;
+                    optimizations.append(&mut cross_opts);
+                    conditions.push(condition);
+                }
+                branch_conditions.push(self.combine_conditions_with_and(conditions));
+            }
+            if let Some(combined) = branch_conditions.into_iter().reduce(|acc, expr| acc.or(expr)) {
+                select.and_where(combined);
+            }
+            return Ok((select, optimizations));
+        }
+
+        // 处理基础Filter
+        if !query.base_filters.is_empty() || !query.base_or_groups.is_empty() {
+            let (conditions, mut filter_opts) = self.compile_base_filters(&query, entity, has_joins)?;
+            optimizations.append(&mut filter_opts);
+            select.and_where(conditions);
+        }
+
+        // 处理关联Filter (JOINs)
+        for cross_filter in &query.cross_filters {
+            let mut cross_opts = self.apply_cross_filter(&mut select, cross_filter, &table_name, &mut join_index)?;
+            optimizations.append(&mut cross_opts);
+        }
+
+        Ok((select, optimizations))
+    }
+
+    /// 编译不依赖 JOIN 的完整过滤条件，供 UPDATE/DELETE 使用
+    ///
+    /// 关联Filter编译为相关的 EXISTS 子查询，段落级 OR 的各分支以 OR 连接。
+    /// 条件恒成立 (没有任何条件，或某个 OR 分支为空) 时返回错误，避免修改整张表
+    fn build_predicate(&self, query: AstQuery, entity: &str) -> Result<(SimpleExpr, Vec<Optimization>), CompileError> {
+        if query.sample_percent.is_some() {
+            return Err(CompileError::new("UPDATE/DELETE 不支持采样子句".to_string()));
+        }
+        self.check_entities(&query, entity)?;
+        self.check_join_limit(&query)?;
+        self.check_complexity(&query, entity)?;
+        let query = self.prepare_query(query, entity);
+        let mut optimizations = Vec::new();
+
+        let table_name = self.table_mapper.get_table_name(entity);
+        let has_joins = query.branches().any(|branch| !branch.cross_filters.is_empty());
+        let mut join_index = 0;
+
+        let mut branch_conditions = Vec::new();
+        for branch in query.branches() {
+            let mut conditions = Vec::new();
+            if !branch.base_filters.is_empty() || !branch.base_or_groups.is_empty() {
+                let (condition, mut filter_opts) = self.compile_base_filters(branch, entity, has_joins)?;
+                optimizations.append(&mut filter_opts);
+                conditions.push(condition);
+            }
+            for cross_filter in &branch.cross_filters {
+                let (condition, mut cross_opts) = self.exists_cross_filter(cross_filter, &table_name, &mut join_index)?;
+                optimizations.append(&mut cross_opts);
+                conditions.push(condition);
+            }
+            if !conditions.is_empty() {
+                branch_conditions.push(self.combine_conditions_with_and(conditions));
+            }
+        }
+
+        // 某个分支没有条件时整个 OR 恒成立
+        let every_branch_filtered = branch_conditions.len() == query.branches().count();
+        match branch_conditions.into_iter().reduce(|acc, expr| acc.or(expr)) {
+            Some(condition) if every_branch_filtered => Ok((condition, optimizations)),
+            _ => Err(CompileError::new("UPDATE/DELETE 没有过滤条件，拒绝修改整张表".to_string())),
+        }
+    }
+
+    /// 将一个关联Filter编译为相关的 `EXISTS (SELECT 1 FROM ... WHERE ...)` 子查询
+    ///
+    /// 关联链的第一跳作为子查询的 FROM 并与 `base_table` 相关联，其余各跳在子查询内 JOIN
+    fn exists_cross_filter(
+        &self,
+        cross_filter: &CrossFilter,
+        base_table: &str,
+        join_index: &mut usize,
+    ) -> Result<(SimpleExpr, Vec<Optimization>), CompileError> {
+        let mut subquery = SelectStatement::new();
+        subquery.expr(Expr::val(1));
+
+        let mut optimizations = Vec::new();
+        let mut previous_alias = base_table.to_string();
+        let mut previous_entity = &cross_filter.source_entity;
+        for (hop, join_entity) in cross_filter.join_path().enumerate() {
+            *join_index += 1;
+            let alias = format!("joined_table_{}", join_index);
+
+            let join_table_name = self.table_mapper.get_table_name(&join_entity.0);
+            let join_table = self.table_ref(TableName(join_table_name.clone()));
+            let (alias, on) = self.join_hop(&previous_entity.0, &join_entity.0, &previous_alias, alias);
+            let on = self.with_hop_default_filters(on, cross_filter, hop, alias.as_deref().unwrap_or(&join_table_name), &mut optimizations)?;
+            match (hop, &alias) {
+                (0, Some(alias)) => subquery.from_as(join_table, TableName(alias.clone())).and_where(on),
+                (0, None) => subquery.from(join_table).and_where(on),
+                (_, Some(alias)) => subquery.join_as(JoinType::InnerJoin, join_table, TableName(alias.clone()), on),
+                (_, None) => subquery.join(JoinType::InnerJoin, join_table, on),
+            };
+            previous_alias = alias.unwrap_or(join_table_name);
+            previous_entity = join_entity;
+        }
+
+        let (condition, mut cross_opts) = self.compile_cross_filter(cross_filter, &previous_alias)?;
+        optimizations.append(&mut cross_opts);
+        subquery.and_where(condition);
+        Ok((Expr::exists(subquery), optimizations))
+    }
+
+    /// 将一个关联Filter的 JOIN 链与过滤条件追加到 `select` 上
+    ///
+    /// `join_index` 在多个关联Filter之间递增，保证 `joined_table_N` 别名唯一
+    fn apply_cross_filter(
+        &self,
+        select: &mut SelectStatement,
+        cross_filter: &CrossFilter,
+        base_table: &str,
+        join_index: &mut usize,
+    ) -> Result<Vec<Optimization>, CompileError> {
+        let (join_conditions, optimizations) =
+            self.join_cross_filter(select, cross_filter, base_table, join_index, self.join_type)?;
+        select.and_where(join_conditions);
+
+        Ok(optimizations)
+    }
+
+    /// 将一个关联Filter的 JOIN 链追加到 `select` 上，返回作用于链末端目标实体的过滤条件
+    fn join_cross_filter(
+        &self,
+        select: &mut SelectStatement,
+        cross_filter: &CrossFilter,
+        base_table: &str,
+        join_index: &mut usize,
+        join_type: JoinType,
+    ) -> Result<(SimpleExpr, Vec<Optimization>), CompileError> {
+        // 沿关联链依次 JOIN，每一跳都关联到上一跳的别名
+        let mut optimizations = Vec::new();
+        let mut previous_alias = base_table.to_string();
+        let mut previous_entity = &cross_filter.source_entity;
+        for (hop, join_entity) in cross_filter.join_path().enumerate() {
+            *join_index += 1;
+            let alias = format!("joined_table_{}", join_index);
+
+            // 获取关联表的实际名称
+            let join_table_name = self.table_mapper.get_table_name(&join_entity.0);
+            let join_table = self.table_ref(TableName(join_table_name.clone()));
+            let (alias, on) = self.join_hop(&previous_entity.0, &join_entity.0, &previous_alias, alias);
+            let on = self.with_hop_default_filters(on, cross_filter, hop, alias.as_deref().unwrap_or(&join_table_name), &mut optimizations)?;
+            match &alias {
+                Some(alias) => select.join_as(join_type, join_table, TableName(alias.clone()), on),
+                None => select.join(join_type, join_table, on),
+            };
+            previous_alias = alias.unwrap_or(join_table_name);
+            previous_entity = join_entity;
+        }
+
+        // 过滤条件作用于链末端的目标实体
+        let (condition, mut cross_opts) = self.compile_cross_filter(cross_filter, &previous_alias)?;
+        optimizations.append(&mut cross_opts);
+        Ok((condition, optimizations))
+    }
+
+    /// 关联链的中间实体配置了隐式过滤条件时，将其以该跳的别名限定后加入这一跳的 ON 条件
+    ///
+    /// 链末端目标实体的隐式条件已由 [`SqlCompiler::with_default_filters`] 加入关联Filter，这里只处理中间实体
+    fn with_hop_default_filters(
+        &self,
+        on: SimpleExpr,
+        cross_filter: &CrossFilter,
+        hop: usize,
+        alias: &str,
+        optimizations: &mut Vec<Optimization>,
+    ) -> Result<SimpleExpr, CompileError> {
+        let Some(entity) = cross_filter.intermediate_entities.get(hop) else {
+            return Ok(on);
+        };
+        let mut conditions = vec![on];
+        for filter in self.default_filters.get(&entity.0).into_iter().flatten() {
+            let (condition, mut opts) = self.compile_condition(&format!("{}.{}", alias, filter.field.0), &filter.condition)?;
+            optimizations.append(&mut opts);
+            conditions.push(condition);
+        }
+        Ok(self.combine_conditions_with_and(conditions))
+    }
+
+    /// 关联链中一跳的目标表别名与 ON 条件
+    ///
+    /// 默认以 `alias` 为别名并按连接键关联到 `previous_alias`；注册了原始连接时原样使用其 ON 子句，
+    /// 目标表不加别名 (返回 `None`)
+    fn join_hop(&self, previous_entity: &str, join_entity: &str, previous_alias: &str, alias: String) -> (Option<String>, SimpleExpr) {
+        if let Some(on) = self.raw_joins.get(&(previous_entity.to_string(), join_entity.to_string())) {
+            return (None, Expr::cust(on.clone()));
+        }
+        let (source_column, target_column) = self.join_columns(previous_entity, join_entity);
+        let on = Expr::col((TableName(previous_alias.to_string()), ColumnName(source_column)))
+            .equals((TableName(alias.clone()), ColumnName(target_column)));
+        (Some(alias), on)
+    }
+
+    /// 主实体的列是否加表名前缀，`has_joins` 表示查询是否包含关联Filter
+    fn qualifies(&self, has_joins: bool) -> bool {
+        match self.qualify_columns {
+            QualifyMode::Always => true,
+            QualifyMode::OnlyWithJoins => has_joins,
+            QualifyMode::Never => false,
+        }
+    }
+
+    /// 将 `UNION` / `UNION ALL` 连接的批次查询包装为子查询后统一排序，没有排序键时原样返回
+    fn order_union(&self, union_sql: &str, sort: &[SortKey]) -> Result<String, CompileError> {
+        if sort.is_empty() {
+            return Ok(union_sql.to_string());
+        }
+
+        const ALIAS: &str = "batched";
+        let render = |expr: SimpleExpr| self.render_inline(|query_builder, sql| query_builder.prepare_simple_expr(&expr, sql));
+        let keys = sort.iter()
+            .map(|key| {
+                let expr = self.sort_expr_to_expr(&key.expr, Some(ALIAS));
+                Ok(format!("{} {}", render(expr)?, if key.descending { "DESC" } else { "ASC" }))
+            })
+            .collect::<Result<Vec<String>, CompileError>>()?;
+        Ok(format!(
+            "SELECT * FROM ({}) AS {} ORDER BY {}",
+            union_sql,
+            render(Expr::col(Alias::new(ALIAS)).into())?,
+            keys.join(", ")
+        ))
+    }
+
+    /// 按排序键追加 ORDER BY，排序表达式中的字段属于主实体，与基础Filter的列同样限定
+    fn apply_sort(&self, select: &mut SelectStatement, sort: &[SortKey], table_name: &str, has_joins: bool) {
+        let table_prefix = self.qualifies(has_joins).then_some(table_name);
+        for key in sort {
+            let order = if key.descending { Order::Desc } else { Order::Asc };
+            select.order_by_expr(self.sort_expr_to_expr(&key.expr, table_prefix), order);
+        }
+    }
+
+    /// 将排序表达式转换为 sea-query 表达式
+    fn sort_expr_to_expr(&self, expr: &SortExpr, table_prefix: Option<&str>) -> SimpleExpr {
+        match expr {
+            SortExpr::Field(field) => {
+                let field = match table_prefix {
+                    Some(table_name) => format!("{}.{}", table_name, field.0),
+                    None => field.0.clone(),
+                };
+                self.field_to_col_expr(&field).into()
+            }
+            SortExpr::Number(n) => Expr::val(*n).into(),
+            SortExpr::Binary { op, left, right } => {
+                let (left, right) = (self.sort_expr_to_expr(left, table_prefix), self.sort_expr_to_expr(right, table_prefix));
+                match op {
+                    ArithOp::Add => left.add(right),
+                    ArithOp::Sub => left.sub(right),
+                    ArithOp::Mul => left.mul(right),
+                    ArithOp::Div => left.div(right),
+                }
+            }
+        }
+    }
+
+    /// 编译主实体上的基础Filter与 OR 分组，二者以 AND 连接
+    ///
+    /// `has_joins` 表示整个查询是否包含关联Filter，用于 `QualifyMode::OnlyWithJoins`
+    fn compile_base_filters(&self, query: &AstQuery, entity: &str, has_joins: bool) -> Result<(SimpleExpr, Vec<Optimization>), CompileError> {
+        let mut optimizations = Vec::new();
+        let mut conditions = Vec::new();
+
+        let table_name = self.table_mapper.get_table_name(entity);
+        let table_prefix = self.qualifies(has_joins).then_some(table_name.as_str());
+
+        if !query.base_filters.is_empty() {
+            let (condition, mut opts) = self.compile_field_filters(&query.base_filters, table_prefix)?;
+            optimizations.append(&mut opts);
+            conditions.push(condition);
+        }
+
+        for group in &query.base_or_groups {
+            let mut alternatives = Vec::new();
+            for filter in group {
+                let (condition, mut opts) = self.compile_field_filters(std::slice::from_ref(filter), table_prefix)?;
+                optimizations.append(&mut opts);
+                alternatives.push(condition);
+            }
+            // 空分组不匹配任何行
+            let combined = alternatives.into_iter()
+                .reduce(|acc, expr| acc.or(expr))
+                .unwrap_or_else(|| Expr::val(false).into());
+            conditions.push(combined);
+        }
+
+        Ok((self.combine_conditions_with_and(conditions), optimizations))
+    }
+
+    /// 编译字段Filter并进行优化，`table_prefix` 为 `None` 时列名不加表名前缀
+    fn compile_field_filters(&self, filters: &[FieldFilter], table_prefix: Option<&str>) -> Result<(SimpleExpr, Vec<Optimization>), CompileError> {
+        let mut optimizations = Vec::new();
+        let mut conditions = Vec::new();
+
+        for filter in filters {
+            let qualified_field = match table_prefix {
+                Some(table_name) => format!("{}.{}", table_name, filter.field.0),
+                None => filter.field.0.clone(),
+            };
+            let (condition, mut opts) = self.compile_condition(&qualified_field, &filter.condition)?;
+            optimizations.append(&mut opts);
+            conditions.push(condition);
         }
+
+        // 用 AND 组合所有条件
+        let combined = self.combine_conditions_with_and(conditions);
         
-        fn load_mapping_from_config(&mut self, _config: &TableMappingConfig) -> Result<(), ConfigError> {
-            Ok(())
+        Ok((combined, optimizations))
+    }
+
+    /// 编译关联Filter并进行优化
+    fn compile_cross_filter(&self, cross_filter: &CrossFilter, alias: &str) -> Result<(SimpleExpr, Vec<Optimization>), CompileError> {
+        let mut optimizations = Vec::new();
+        let mut conditions = Vec::new();
+
+        for filter in &cross_filter.filters {
+            // 字段会以连接表别名限定，已经带有限定前缀的字段会生成 `joined_table_1.a.b` 这样的错误列引用
+            if filter.field.0.contains('.') {
+                return Err(CompileError::new(format!(
+                    "关联Filter <{}> 中的字段 '{}' 不能包含 '.'，字段会自动以关联表别名限定",
+                    cross_filter.target_entity.0, filter.field.0
+                )));
+            }
+
+            // 为字段引用使用连接表的别名
+            let qualified_field = format!("{}.{}", alias, filter.field.0);
+            let (condition, mut opts) = self.compile_condition(&qualified_field, &filter.condition)?;
+            optimizations.append(&mut opts);
+            conditions.push(condition);
         }
+
+        let combined = self.combine_conditions_with_and(conditions);
+        Ok((combined, optimizations))
+    }
+
+    /// 编译单个条件并进行优化
+    fn compile_condition(&self, field: &str, condition: &Condition) -> Result<(SimpleExpr, Vec<Optimization>), CompileError> {
+        let mut optimizations = Vec::new();
+        let optimizer_config = self.optimizer.optimization_config();
+        
+        let expr = match condition {
+            Condition::Comparison { op, value } => {
+                self.compile_comparison(field, op, value)?
+            }
+            Condition::And(left, right) => {
+                // 检查 AND NOT 优化为 NOT IN 以及范围条件折叠为 BETWEEN 的机会
+                if let Some((not_in_expr, opt)) = self.try_optimize_and_not_to_not_in(field, condition, optimizer_config)? {
+                    optimizations.push(opt);
+                    not_in_expr
+                } else if let Some((between_expr, opt)) = self.try_fold_range_to_between(field, left, right)? {
+                    optimizations.push(opt);
+                    between_expr
+                } else {
+                    let (left_expr, mut left_opts) = self.compile_condition(field, left)?;
+                    let (right_expr, mut right_opts) = self.compile_condition(field, right)?;
+                    optimizations.append(&mut left_opts);
+                    optimizations.append(&mut right_opts);
+                    left_expr.and(right_expr)
+                }
+            }
+            Condition::Or(left, right) => {
+                // 检查 OR 优化机会
+                if let Some((in_expr, opt)) = self.try_optimize_or_to_in(field, condition, optimizer_config)? {
+                    optimizations.push(opt);
+                    in_expr
+                } else {
+                    let (left_expr, mut left_opts) = self.compile_condition(field, left)?;
+                    let (right_expr, mut right_opts) = self.compile_condition(field, right)?;
+                    optimizations.append(&mut left_opts);
+                    optimizations.append(&mut right_opts);
+                    left_expr.or(right_expr)
+                }
+            }
+            Condition::Not(inner) => {
+                let (inner_expr, mut inner_opts) = self.compile_condition(field, inner)?;
+                optimizations.append(&mut inner_opts);
+                inner_expr.not()
+            }
+            Condition::Grouped(inner) => {
+                self.compile_condition(field, inner)?.0
+            }
+            Condition::In(values) if values.contains(&Literal::Null) => {
+                // `x IN (.., NULL)` 对 NULL 行的结果为 NULL 而不是真，改写为 `x IN (..) OR x IS NULL`
+                let non_null: Vec<Literal> = values.iter().filter(|v| **v != Literal::Null).cloned().collect();
+                let is_null = self.field_to_col_expr(field).is_null();
+                let expr = if non_null.is_empty() {
+                    is_null
+                } else {
+                    let (in_expr, mut in_opts) = self.compile_condition(field, &Condition::In(non_null))?;
+                    optimizations.append(&mut in_opts);
+                    in_expr.or(is_null)
+                };
+                optimizations.push(Optimization::InNullToIsNull { field: field.to_string() });
+                expr
+            }
+            Condition::In(values) => {
+                Self::check_in_value_types(values)?;
+                let (column, in_values) = self.in_operands(field, &values.iter().collect::<Vec<_>>())?;
+                
+                // 数组参数不受 IN 列表长度限制，优先于 UNION 拆分；数组中的值无法用 LOWER() 包裹
+                if let Some(array) = self.in_values_as_array(values).filter(|_| !self.ignores_case(values)) {
+                    column.eq(PgFunc::any(Expr::val(array)))
+                } else if in_values.len() > optimizer_config.max_in_values {
+                    // 检查是否需要将大型 IN 子句拆分为 UNION
+                    let (expr, opt) = self.split_large_in_to_union(field, column, &in_values, optimizer_config)?;
+                    optimizations.push(opt);
+                    expr
+                } else {
+                    column.is_in(in_values)
+                }
+            }
+            Condition::InSubquery { entity, query } => {
+                // 子查询只选出关联实体的 id 列
+                let (mut subquery, mut sub_opts) = self.build_select_quiet((**query).clone(), &entity.0)?;
+                let sub_table = self.table_mapper.get_table_name(&entity.0);
+                subquery.clear_selects()
+                    .column((TableName(sub_table), ColumnName("id".to_string())));
+                optimizations.append(&mut sub_opts);
+                self.field_to_col_expr(field).in_subquery(subquery)
+            }
+            Condition::InTuple { fields, rows } => {
+                // 字段名形如 "table.(a, b)"，用其表前缀分别限定每个元组字段
+                let columns: Vec<SimpleExpr> = fields.iter()
+                    .map(|column| self.field_to_col_expr(&Self::sibling_field(field, &column.0)).into())
+                    .collect();
+                let tuples = rows.iter()
+                    .map(|row| row.iter().map(|v| self.literal_to_expr(field, v)).collect::<Result<Vec<_>, _>>().map(SimpleExpr::Tuple))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                if tuples.is_empty() {
+                    // 空元组列表不匹配任何行
+                    Expr::val(false).into()
+                } else {
+                    Expr::tuple(columns).binary(BinOper::In, SimpleExpr::Tuple(tuples))
+                }
+            }
+            Condition::AnyOf { op, values } | Condition::AllOf { op, values } => {
+                let is_any = matches!(condition, Condition::AnyOf { .. });
+                self.compile_quantified(field, op, values, is_any)?
+            }
+            Condition::Between { low, high, negated } => {
+                let (low, high) = (self.literal_to_expr(field, low)?, self.literal_to_expr(field, high)?);
+                let column = self.field_to_col_expr(field);
+                if *negated {
+                    column.not_between(low, high)
+                } else {
+                    column.between(low, high)
+                }
+            }
+            Condition::Like { pattern, negated } => {
+                // 模式中出现转义的通配符时才需要 ESCAPE 子句
+                let mut like = LikeExpr::new(pattern.as_str());
+                if pattern.contains('\\') {
+                    like = like.escape('\\');
+                }
+                let column = self.field_to_col_expr(field);
+                if *negated {
+                    column.not_like(like)
+                } else {
+                    column.like(like)
+                }
+            }
+            Condition::IsNull => {
+                self.field_to_col_expr(field).is_null()
+            }
+            Condition::IsNotNull => {
+                self.field_to_col_expr(field).is_not_null()
+            }
+            Condition::IsEmpty => {
+                let column = self.field_to_col_expr(field);
+                column.clone().eq("").or(column.is_null())
+            }
+            Condition::IsNotEmpty => {
+                let column = self.field_to_col_expr(field);
+                column.clone().ne("").and(column.is_not_null())
+            }
+        };
+
+        Ok((expr, optimizations))
+    }
+
+    /// 启用 `use_any_for_in` 且方言为 PostgreSQL 时，将 IN 值列表打包为数组值
+    ///
+    /// 空列表、元素类型不一致或包含日期等需编译为 SQL 表达式的值时返回 `None`，
+    /// 由调用方回退到普通 IN
+    fn in_values_as_array(&self, values: &[Literal]) -> Option<Value> {
+        if !self.optimizer.optimization_config().use_any_for_in || self.dialect != SqlDialect::PostgreSQL {
+            return None;
+        }
+        Self::literals_as_array(values)
+    }
+
+    /// 将同为字符串或同为数字的值列表打包为数组值，其他情况返回 `None`
+    fn literals_as_array(values: &[Literal]) -> Option<Value> {
+        let array_type = match values.first()? {
+            Literal::String(_) => ArrayType::String,
+            Literal::Number(_) => ArrayType::BigInt,
+            _ => return None,
+        };
+        let items = values.iter()
+            .map(|v| match (v, &array_type) {
+                (Literal::String(s), ArrayType::String) => Some(Value::String(Some(Box::new(s.clone())))),
+                (Literal::Number(n), ArrayType::BigInt) => Some(Value::BigInt(Some(*n))),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(Value::Array(array_type, Some(Box::new(items))))
+    }
+
+    /// 将大型 IN 子句拆分为 UNION 查询
+    ///
+    /// `max_in_values` 为 0 时无法拆分，返回错误
+    fn split_large_in_to_union(&self, field: &str, column: Expr, values: &[SimpleExpr], config: &OptimizationConfig) -> Result<(SimpleExpr, Optimization), CompileError> {
+        let chunk_size = config.max_in_values;
+        if chunk_size == 0 {
+            return Err(CompileError::new("max_in_values 必须大于 0，无法拆分 IN 列表".to_string()));
+        }
+        let chunks: Vec<&[SimpleExpr]> = values.chunks(chunk_size).collect();
+        let union_count = chunks.len();
+        
+        // 为每个块创建单独的 IN 表达式
+        let mut conditions = Vec::new();
+        for chunk in chunks {
+            let in_expr = column.clone().is_in(chunk.to_vec());
+            conditions.push(in_expr);
+        }
+        
+        // 用 OR 组合（在顶层有效地创建 UNION）
+        let combined = conditions.into_iter().reduce(|acc, expr| acc.or(expr)).unwrap();
+        
+        let optimization = Optimization::InToUnion {
+            field: field.to_string(),
+            total_values: values.len(),
+            union_count,
+        };
+        
+        Ok((combined, optimization))
+    }
+
+    /// 尝试将 OR 条件优化为 IN 子句
+    fn try_optimize_or_to_in(&self, field: &str, condition: &Condition, config: &OptimizationConfig) -> Result<Option<(SimpleExpr, Optimization)>, CompileError> {
+        let equality_values = self.extract_equality_values_from_or(field, condition);
+
+        // 类型不一致的值无法合并为同一个 IN 列表，保留逐个比较
+        if equality_values.windows(2).any(|pair| !Self::literal_types_compatible(pair[0], pair[1])) {
+            return Ok(None);
+        }
+        
+        if equality_values.len() >= config.max_or_conditions_for_in {
+            let (column, in_values) = self.in_operands(field, &equality_values)?;
+            let in_expr = column.is_in(in_values);
+            let optimization = Optimization::OrToIn {
+                field: field.to_string(),
+                value_count: equality_values.len(),
+            };
+            
+            return Ok(Some((in_expr, optimization)));
+        }
+        
+        Ok(None)
+    }
+
+    /// 尝试将 `NOT a AND NOT b AND ...` 条件优化为 NOT IN 子句，与 OR 转 IN 对称
+    ///
+    /// 叶子可以是 `NOT =a`，也可以是 NOT 下推后的 `!=a`
+    fn try_optimize_and_not_to_not_in(&self, field: &str, condition: &Condition, config: &OptimizationConfig) -> Result<Option<(SimpleExpr, Optimization)>, CompileError> {
+        let mut excluded_values = Vec::new();
+        if !Self::collect_not_equal_values(condition, &mut excluded_values) {
+            return Ok(None);
+        }
+
+        // 与 OR 转 IN 相同，类型不一致的值保留逐个比较
+        if excluded_values.windows(2).any(|pair| !Self::literal_types_compatible(pair[0], pair[1])) {
+            return Ok(None);
+        }
+
+        if excluded_values.len() >= config.max_or_conditions_for_in {
+            let (column, values) = self.in_operands(field, &excluded_values)?;
+            let not_in_expr = column.is_not_in(values);
+            let optimization = Optimization::AndNotToNotIn {
+                field: field.to_string(),
+                value_count: excluded_values.len(),
+            };
+
+            return Ok(Some((not_in_expr, optimization)));
+        }
+
+        Ok(None)
+    }
+
+    /// 递归收集 AND 条件中的不等值，遇到其他形式的叶子时返回 `false`
+    fn collect_not_equal_values<'a>(condition: &'a Condition, values: &mut Vec<&'a Literal>) -> bool {
+        match condition {
+            Condition::Comparison { op: CompOp::NotEq, value } => {
+                values.push(value);
+                true
+            }
+            Condition::Not(inner) => match inner.as_ref() {
+                Condition::Comparison { op: CompOp::Eq, value } => {
+                    values.push(value);
+                    true
+                }
+                _ => false,
+            },
+            Condition::And(left, right) => {
+                Self::collect_not_equal_values(left, values) && Self::collect_not_equal_values(right, values)
+            }
+            Condition::Grouped(inner) => Self::collect_not_equal_values(inner, values),
+            _ => false,
+        }
+    }
+
+    /// 尝试将同一字段上的 `>=a AND <=b` 范围条件折叠为 BETWEEN
+    ///
+    /// 只折叠两端都是闭区间的范围；开区间 (`>`/`<`) 无法在不知道列类型的情况下
+    /// 转换为闭区间 (`>5` 对浮点列不等于 `>=6`)，保持原样
+    fn try_fold_range_to_between(&self, field: &str, left: &Condition, right: &Condition) -> Result<Option<(SimpleExpr, Optimization)>, CompileError> {
+        let (
+            Condition::Comparison { op: left_op, value: left_value },
+            Condition::Comparison { op: right_op, value: right_value },
+        ) = (left, right) else {
+            return Ok(None);
+        };
+
+        let (low, high) = match (left_op, right_op) {
+            (CompOp::Gte, CompOp::Lte) => (left_value, right_value),
+            (CompOp::Lte, CompOp::Gte) => (right_value, left_value),
+            _ => return Ok(None),
+        };
+
+        let expr = self.field_to_col_expr(field)
+            .between(self.literal_to_expr(field, low)?, self.literal_to_expr(field, high)?);
+        let optimization = Optimization::ConditionSimplification {
+            original: format!("{} {} {} AND {} {} {}", field, left_op, left_value, field, right_op, right_value),
+            simplified: format!("{} BETWEEN {} AND {}", field, low, high),
+        };
+
+        Ok(Some((expr, optimization)))
+    }
+
+    /// 检查 IN 列表中的值类型是否一致，返回指出第一个不兼容值的错误
+    fn check_in_value_types(values: &[Literal]) -> Result<(), CompileError> {
+        let Some(first) = values.first() else {
+            return Ok(());
+        };
+
+        match values.iter().find(|v| !Self::literal_types_compatible(first, v)) {
+            Some(offending) => Err(CompileError::new(format!(
+                "IN 列表中的值类型不一致: {} 与第一个值 {} 的类型不兼容",
+                offending, first
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// 判断两个字面量能否出现在同一个 IN 列表中
+    ///
+    /// 数字只与数字兼容；字符串、日期关键字和 current_user 都按文本处理，彼此兼容
+    fn literal_types_compatible(a: &Literal, b: &Literal) -> bool {
+        matches!(a, Literal::Number(_)) == matches!(b, Literal::Number(_))
+    }
+
+    /// 从同一字段的 OR 条件中提取相等值
+    fn extract_equality_values_from_or<'a>(&self, _target_field: &str, condition: &'a Condition) -> Vec<&'a Literal> {
+        let mut values = Vec::new();
+        self.collect_equality_values(condition, &mut values);
+        values
+    }
+
+    /// 递归收集 OR 条件中的相等值
+    fn collect_equality_values<'a>(&self, condition: &'a Condition, values: &mut Vec<&'a Literal>) {
+        match condition {
+            Condition::Comparison { op: CompOp::Eq, value } => {
+                values.push(value);
+            }
+            Condition::Or(left, right) => {
+                self.collect_equality_values(left, values);
+                self.collect_equality_values(right, values);
+            }
+            Condition::Grouped(inner) => {
+                self.collect_equality_values(inner, values);
+            }
+            _ => {} // 其他条件类型会破坏相等模式
+        }
+    }
+
+    /// 用 AND 组合多个条件
+    fn combine_conditions_with_and(&self, conditions: Vec<SimpleExpr>) -> SimpleExpr {
+        if conditions.is_empty() {
+            return Expr::val(true).into();
+        }
+        
+        conditions.into_iter().reduce(|acc, expr| acc.and(expr)).unwrap()
+    }
+
+    /// 编译比较操作
+    fn compile_comparison(&self, field: &str, op: &CompOp, value: &Literal) -> Result<SimpleExpr, CompileError> {
+        let col = self.field_to_col_expr(field);
+        let val = self.literal_to_expr(field, value)?;
+
+        // 忽略大小写时两侧都用 LOWER() 包裹，只影响字符串的相等比较
+        if self.case_insensitive_strings
+            && matches!(value, Literal::String(_))
+            && matches!(op, CompOp::Eq | CompOp::NotEq)
+        {
+            let lower_col = Expr::expr(Func::lower(col));
+            let lower_val = Func::lower(val);
+            return Ok(match op {
+                CompOp::Eq => lower_col.eq(lower_val),
+                _ => lower_col.ne(lower_val),
+            });
+        }
+
+        let expr = match op {
+            CompOp::Eq => col.eq(val),
+            CompOp::NotEq => col.ne(val),
+            CompOp::Gt => col.gt(val),
+            CompOp::Lt => col.lt(val),
+            CompOp::Gte => col.gte(val),
+            CompOp::Lte => col.lte(val),
+            CompOp::NullSafeEq => match self.dialect {
+                SqlDialect::MySQL => col.binary(BinOper::Custom("<=>"), val),
+                SqlDialect::SQLite => col.binary(BinOper::Is, val),
+                SqlDialect::PostgreSQL => col.binary(BinOper::Custom("IS NOT DISTINCT FROM"), val),
+                // Oracle 没有 IS NOT DISTINCT FROM，MsSQL 要到 2022 版本才支持
+                SqlDialect::MsSQL | SqlDialect::Oracle => {
+                    return Err(CompileError::new(format!(
+                        "{:?} 方言不支持 NULL 安全比较 <=>",
+                        self.dialect
+                    )));
+                }
+            },
+        };
+
+        Ok(expr)
+    }
+
+    /// 将 `op ANY (...)` / `op ALL (...)` 编译为 PostgreSQL 的 `col op ANY(ARRAY[...])`
+    ///
+    /// 值列表作为一个数组值，参数化编译时只绑定一个数组参数。数组中的值无法用 `LOWER()` 包裹，
+    /// 因此忽略大小写时拒绝字符串的相等比较，而不是静默地区分大小写
+    fn compile_quantified(&self, field: &str, op: &CompOp, values: &[Literal], is_any: bool) -> Result<SimpleExpr, CompileError> {
+        let quantifier = if is_any { "ANY" } else { "ALL" };
+        if self.dialect != SqlDialect::PostgreSQL {
+            return Err(CompileError::new(format!(
+                "{} 比较仅支持 PostgreSQL 方言，当前方言为 {:?}",
+                quantifier, self.dialect
+            )));
+        }
+        if matches!(op, CompOp::Eq | CompOp::NotEq) && self.ignores_case(values) {
+            return Err(CompileError::new(format!(
+                "忽略大小写时 {} 不支持字符串的相等比较，请改用 IN",
+                quantifier
+            )));
+        }
+        Self::check_in_value_types(values)?;
+
+        let Some(array) = Self::literals_as_array(values) else {
+            return Err(CompileError::new(format!(
+                "{} 列表只支持字符串或数字: {}",
+                quantifier,
+                values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ")
+            )));
+        };
+        let array = Expr::val(array);
+        let quantified: SimpleExpr = if is_any { PgFunc::any(array).into() } else { PgFunc::all(array).into() };
+
+        let oper = match op {
+            CompOp::Eq => BinOper::Equal,
+            CompOp::NotEq => BinOper::NotEqual,
+            CompOp::Gt => BinOper::GreaterThan,
+            CompOp::Lt => BinOper::SmallerThan,
+            CompOp::Gte => BinOper::GreaterThanOrEqual,
+            CompOp::Lte => BinOper::SmallerThanOrEqual,
+            CompOp::NullSafeEq => {
+                return Err(CompileError::new(format!("<=> 不能与 {} 组合使用", quantifier)));
+            }
+        };
+
+        Ok(self.field_to_col_expr(field).binary(oper, quantified))
+    }
+
+    /// 值列表中的字符串是否需要忽略大小写比较
+    fn ignores_case<'a>(&self, values: impl IntoIterator<Item = &'a Literal>) -> bool {
+        self.case_insensitive_strings && values.into_iter().any(|v| matches!(v, Literal::String(_)))
+    }
+
+    /// 编译 `[NOT] IN` 的列和值列表，忽略大小写且包含字符串时列和字符串值都用 `LOWER()` 包裹
+    fn in_operands(&self, field: &str, values: &[&Literal]) -> Result<(Expr, Vec<SimpleExpr>), CompileError> {
+        let lower = self.ignores_case(values.iter().copied());
+        let in_values = values.iter()
+            .map(|v| {
+                let expr = self.literal_to_expr(field, v)?;
+                Ok(if lower && matches!(v, Literal::String(_)) { Func::lower(expr).into() } else { expr })
+            })
+            .collect::<Result<Vec<_>, CompileError>>()?;
+        let column = self.field_to_col_expr(field);
+        Ok((if lower { Expr::expr(Func::lower(column)) } else { column }, in_values))
+    }
+
+    /// 用 `field` ("table.column" 或 "column") 的表前缀限定同一实体的另一列
+    fn sibling_field(field: &str, column: &str) -> String {
+        match field.split_once('.') {
+            Some((table, _)) => format!("{}.{}", table, column),
+            None => column.to_string(),
+        }
+    }
+
+    /// 将 AST 字面量转换为 sea-query 表达式，`field` 为比较左侧的字段
+    ///
+    /// 日期关键字及其偏移编译为 SQL 日期表达式，列引用以与 `field` 相同的表前缀限定，其余字面量作为绑定值
+    fn literal_to_expr(&self, field: &str, literal: &Literal) -> Result<SimpleExpr, CompileError> {
+        match literal {
+            Literal::Column(column) => Ok(self.field_to_col_expr(&Self::sibling_field(field, &column.0)).into()),
+            Literal::Date(d) => match date_keyword_offset(d) {
+                Some(offset) => Ok(self.current_date_expr(offset)),
+                None => Ok(Expr::val(d.clone()).into()),
+            },
+            Literal::RelativeDate { base, offset_days } => {
+                let base_offset = date_keyword_offset(base).ok_or_else(|| {
+                    CompileError::new(format!("未知的日期关键字: {}", base))
+                })?;
+                let offset = base_offset.checked_add(*offset_days).ok_or_else(|| {
+                    CompileError::new(format!("日期偏移量溢出: {}", literal))
+                })?;
+                Ok(self.current_date_expr(offset))
+            }
+            Literal::String(s) => Ok(Expr::val(s.clone()).into()),
+            Literal::Number(n) => Ok(Expr::val(*n).into()),
+            Literal::Null => Ok(SimpleExpr::Keyword(Keyword::Null)),
+            // 查询以服务账号执行时，由调用方提供实际的最终用户 id
+            Literal::CurrentUser => match &self.current_user_id {
+                Some(user_id) => Ok(Expr::val(user_id.clone()).into()),
+                None => Ok(SimpleExpr::Keyword(Keyword::Custom(Alias::new("CURRENT_USER").into_iden()))),
+            },
+        }
+    }
+
+    /// 生成相对当前日期偏移 `offset_days` 天的 SQL 日期表达式
+    fn current_date_expr(&self, offset_days: i64) -> SimpleExpr {
+        if offset_days == 0 {
+            return Expr::cust("CURRENT_DATE");
+        }
+
+        let sign = if offset_days < 0 { '-' } else { '+' };
+        let days = offset_days.unsigned_abs();
+        let unit = if days == 1 { "day" } else { "days" };
+        match self.dialect {
+            SqlDialect::MySQL => Expr::cust(format!("CURRENT_DATE {} INTERVAL {} DAY", sign, days)),
+            // SQLite 没有 INTERVAL，使用 date() 的修饰符
+            SqlDialect::SQLite => Expr::cust(format!("date('now', '{}{} {}')", sign, days, unit)),
+            _ => Expr::cust(format!("CURRENT_DATE {} INTERVAL '{} {}'", sign, days, unit)),
+        }
+    }
+}
+
+/// `YYYY-MM-DD` 格式日期的次日，格式不符或日期不存在时返回 `None`
+fn next_day(date: &str) -> Option<String> {
+    let bytes = date.as_bytes();
+    let well_formed = bytes.len() == 10
+        && bytes.iter().enumerate().all(|(i, b)| if i == 4 || i == 7 { *b == b'-' } else { b.is_ascii_digit() });
+    if !well_formed {
+        return None;
+    }
+    let (year, month, day): (i32, u32, u32) = (date[0..4].parse().ok()?, date[5..7].parse().ok()?, date[8..10].parse().ok()?);
+    let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return None,
+    };
+    let (year, month, day) = match day {
+        0 => return None,
+        d if d < days_in_month => (year, month, d + 1),
+        d if d == days_in_month && month < 12 => (year, month + 1, 1),
+        d if d == days_in_month => (year + 1, 1, 1),
+        _ => return None,
+    };
+    Some(format!("{:04}-{:02}-{:02}", year, month, day))
+}
+
+/// 日期关键字相对当前日期的天数偏移，非日期关键字返回 `None`
+pub(crate) fn date_keyword_offset(keyword: &str) -> Option<i64> {
+    match keyword {
+        "today" => Some(0),
+        "yesterday" => Some(-1),
+        "tomorrow" => Some(1),
+        _ => None,
+    }
+}
+
+/// SqlCompiler 的工厂实现
+pub struct SqlCompilerFactory;
+
+impl CompilerFactory for SqlCompilerFactory {
+    type Compiler = SqlCompiler;
+    
+    fn create_default() -> Self::Compiler {
+        SqlCompiler::new()
+    }
+    
+    fn create_with_config(config: CompilerConfig) -> Result<Self::Compiler, CompileError> {
+        Ok(SqlCompiler::from_config(config))
+    }
+}
+
+/// 编译器注册表，用于管理不同的编译器实现
+pub struct CompilerRegistry {
+    compilers: HashMap<String, Box<dyn Fn() -> Box<dyn QueryCompiler>>>,
+}
+
+impl CompilerRegistry {
+    pub fn new() -> Self {
+        let mut registry = Self {
+            compilers: HashMap::new(),
+        };
+        
+        // 注册默认的 SqlCompiler
+        registry.register("sql", || Box::new(SqlCompiler::new()));
+        registry.register("default", || Box::new(SqlCompiler::new()));
+        registry.register("datafusion", || Box::new(DataFusionCompiler::new()));
+        
+        registry
+    }
+    
+    /// 注册新的编译器
+    pub fn register<F>(&mut self, name: &str, factory: F)
+    where
+        F: Fn() -> Box<dyn QueryCompiler> + 'static,
+    {
+        self.compilers.insert(name.to_string(), Box::new(factory));
+    }
+    
+    /// 创建指定类型的编译器
+    pub fn create(&self, name: &str) -> Option<Box<dyn QueryCompiler>> {
+        self.compilers.get(name).map(|factory| factory())
+    }
+    
+    /// 获取所有已注册的编译器名称
+    pub fn available_compilers(&self) -> Vec<String> {
+        self.compilers.keys().cloned().collect()
+    }
+}
+
+impl Default for CompilerRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::*;
+
+    fn create_test_compiler() -> SqlCompiler {
+        let mut compiler = SqlCompiler::new();
+        let mut mapping = HashMap::new();
+        mapping.insert("Test".to_string(), "tests".to_string());
+        mapping.insert("Run".to_string(), "test_runs".to_string());
+        compiler.table_mapper_mut().set_table_mapping(mapping);
+        compiler
+    }
+
+    struct CustomCompiler {
+        name: String,
+        dialect: SqlDialect,
+        config: OptimizationConfig,
+    }
+
+    impl CustomCompiler {
+        fn new(name: String, dialect: SqlDialect) -> Self {
+            Self { 
+                name, 
+                dialect,
+                config: OptimizationConfig::default(),
+            }
+        }
+    }
+
+    impl QueryCompiler for CustomCompiler {
+        fn compile(&self, _query: AstQuery, _entity: &str) -> Result<CompileResult, CompileError> {
+            Ok(CompileResult {
+                sql: format!("-- Generated by {} for {:?}\nSELECT * FROM custom_table;", self.name, self.dialect),
+                optimizations: vec![],
+                diagnostics: vec![],
+            })
+        }
+        
+        fn name(&self) -> &'static str {
+            "CustomCompiler"
+        }
+        
+        fn supported_dialect(&self) -> SqlDialect {
+            self.dialect
+        }
+    }
+
+    impl QueryOptimizer for CustomCompiler {
+        fn optimize(&self, _query: &mut AstQuery) -> Vec<Optimization> {
+            vec![Optimization::ConditionSimplification {
+                original: "custom_original".to_string(),
+                simplified: "custom_simplified".to_string(),
+            }]
+        }
+        
+        fn optimization_config(&self) -> &OptimizationConfig {
+            &self.config
+        }
+        
+        fn set_optimization_config(&mut self, _config: OptimizationConfig) {
+        }
+    }
+
+    impl BatchQueryCompiler for CustomCompiler {
+        fn compile_batch(&self, query: AstQuery, entity: &str, _config: &BatchConfig) -> Result<BatchQueryResult, CompileError> {
+            let result = self.compile(query, entity)?;
+            Ok(BatchQueryResult {
+                queries: vec![result.sql],
+                optimizations: result.optimizations,
+                total_estimated_rows: Some(100),
+            })
+        }
+        
+        fn estimate_query_complexity(&self, _query: &AstQuery) -> QueryComplexity {
+            QueryComplexity {
+                estimated_rows: Some(100),
+                join_count: 0,
+                condition_count: 1,
+                complexity_score: 1.0,
+            }
+        }
+    }
+
+    impl TableMappingProvider for CustomCompiler {
+        fn get_table_name(&self, entity: &str) -> String {
+            format!("custom_{}", entity.to_lowercase())
+        }
+        
+        fn set_table_mapping(&mut self, _mapping: HashMap<String, String>) {
+        }
+        
+        fn load_mapping_from_config(&mut self, _config: &TableMappingConfig) -> Result<(), ConfigError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_trait_based_compilation() {
+        let compiler: Box<dyn QueryCompiler> = Box::new(SqlCompiler::new());
+        
+        let query = Query {
+            base_filters: vec![
+                FieldFilter {
+                    field: Identifier("status".to_string()),
+                    condition: Condition::Comparison {
+                        op: CompOp::Eq,
+                        value: Literal::String("Open".to_string()),
+                    },
+                    span: None,
+                }
+            ],
+            cross_filters: vec![],
+            ..Default::default()
+        };
+
+        let result = compiler.compile(query, "Test").unwrap();
+        assert_eq!(compiler.name(), "SeaQuerySqlCompiler");
+        assert_eq!(compiler.supported_dialect(), SqlDialect::PostgreSQL);
+        assert!(result.sql.contains("status"));
+    }
+
+    #[test]
+    fn test_custom_compiler() {
+        let compiler = CustomCompiler::new("TestCompiler".to_string(), SqlDialect::MySQL);
+        
+        let query = Query {
+            base_filters: vec![],
+            cross_filters: vec![],
+            ..Default::default()
+        };
+
+        let result = compiler.compile(query, "Test").unwrap();
+        assert!(result.sql.contains("custom_table"));
+        assert!(result.sql.contains("TestCompiler"));
+        assert!(result.sql.contains("MySQL"));
+        assert_eq!(compiler.name(), "CustomCompiler");
+        assert_eq!(compiler.supported_dialect(), SqlDialect::MySQL);
+    }
+
+    #[test]
+    fn test_compiler_interface() {
+        let compiler = SqlCompiler::new();
+        
+        let query = Query {
+            base_filters: vec![
+                FieldFilter {
+                    field: Identifier("priority".to_string()),
+                    condition: Condition::Comparison {
+                        op: CompOp::Eq,
+                        value: Literal::String("High".to_string()),
+                    },
+                    span: None,
+                }
+            ],
+            cross_filters: vec![],
+            ..Default::default()
+        };
+
+        // 测试编译
+        let result = compiler.compile(query.clone(), "Test").unwrap();
+        assert!(result.sql.contains("priority"));
+        
+        // 测试复杂度评估
+        let complexity = compiler.batch_processor().estimate_query_complexity(&query);
+        assert_eq!(complexity.join_count, 0);
+        assert_eq!(complexity.condition_count, 1);
+        assert!(complexity.complexity_score > 0.0);
+        assert_eq!(complexity.estimated_rows, None);
+    }
+
+    #[derive(Debug)]
+    struct StubTableStats;
+
+    impl TableStats for StubTableStats {
+        fn row_count(&self, table: &str) -> Option<u64> {
+            match table {
+                "tests" => Some(10_000),
+                "test_runs" => Some(50_000),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn test_estimated_rows_from_table_stats() {
+        let mut compiler = create_test_compiler();
+        compiler.batch_processor_mut().set_table_stats(Arc::new(StubTableStats));
+
+        let query = Query {
+            base_filters: vec![
+                FieldFilter {
+                    field: Identifier("status".to_string()),
+                    condition: Condition::Comparison {
+                        op: CompOp::Eq,
+                        value: Literal::String("Open".to_string()),
+                    },
+                    span: None,
+                },
+                FieldFilter {
+                    field: Identifier("priority".to_string()),
+                    condition: Condition::Comparison {
+                        op: CompOp::Gt,
+                        value: Literal::Number(2),
+                    },
+                    span: None,
+                },
+            ],
+            ..Default::default()
+        };
+
+        // 10000 行 * 等值 0.1 * 范围 0.3
+        let complexity = compiler.estimate_query_complexity(&query, "Test");
+        assert_eq!(complexity.estimated_rows, Some(300));
+
+        // 没有统计信息的表无法估算
+        let complexity = compiler.estimate_query_complexity(&query, "Step");
+        assert_eq!(complexity.estimated_rows, None);
+
+        // 关联Filter的选择率作用于其目标实体的行数：10000 * 0.1 * (50000 * 0.1 / 10000)
+        let query: Query = r#"Filter: status["Open"]; CrossFilter: <Test-Run> result["FAIL"]"#.parse().unwrap();
+        let complexity = compiler.estimate_query_complexity(&query, "Test");
+        assert_eq!(complexity.estimated_rows, Some(500));
+
+        // 目标实体缺少统计信息时无法估算
+        let query: Query = r#"CrossFilter: <Test-Step> name["setup"]"#.parse().unwrap();
+        assert_eq!(compiler.estimate_query_complexity(&query, "Test").estimated_rows, None);
+    }
+
+    #[test]
+    fn test_compiler_registry() {
+        let mut registry = CompilerRegistry::new();
+        
+        // 注册自定义编译器
+        registry.register("custom", || {
+            Box::new(CustomCompiler::new("RegisteredCustom".to_string(), SqlDialect::SQLite))
+        });
+        
+        // 测试默认编译器
+        let default_compiler = registry.create("default").unwrap();
+        assert_eq!(default_compiler.name(), "SeaQuerySqlCompiler");
+        
+        // 测试自定义编译器
+        let custom_compiler = registry.create("custom").unwrap();
+        assert_eq!(custom_compiler.name(), "CustomCompiler");
+        
+        // 测试可用编译器列表
+        let available = registry.available_compilers();
+        assert!(available.contains(&"default".to_string()));
+        assert!(available.contains(&"custom".to_string()));
+        assert!(available.contains(&"sql".to_string()));
+        assert!(available.contains(&"datafusion".to_string()));
+    }
+
+    #[test]
+    fn test_compiler_factory() {
+        // 测试默认工厂
+        let compiler = SqlCompilerFactory::create_default();
+        assert_eq!(compiler.name(), "SeaQuerySqlCompiler");
+        
+        // 测试配置工厂
+        let config = CompilerConfig {
+            optimization_config: OptimizationConfig {
+                max_or_conditions_for_in: 10,
+                max_in_values: 2000,
+                ..Default::default()
+            },
+            batch_config: BatchConfig::default(),
+            cache_config: CacheConfig::default(),
+            table_mapping: {
+                let mut map = HashMap::new();
+                map.insert("Entity".to_string(), "entity_table".to_string());
+                map
+            },
+            naming_strategy: NamingStrategy::SnakeCasePlural,
+            column_naming: ColumnNaming::Preserve,
+            dialect: SqlDialect::PostgreSQL,
+            case_insensitive_strings: false,
+            join_type: JoinType::InnerJoin,
+            join_keys: HashMap::new(),
+            join_key_strategy: JoinKeyStrategy::Explicit,
+            current_user_id: None,
+            max_complexity_score: None,
+            max_joins: None,
+            qualify_columns: QualifyMode::Always,
+            param_style: ParamStyle::Positional,
+            schema: None,
+            default_filters: HashMap::new(),
+            string_quote_style: StringQuoteStyle::Dialect,
+            mine_recently_fields: HashMap::new(),
+            strict_entities: false,
+            replica_hint: None,
+            partition_keys: HashMap::new(),
+            partition_pruning: false,
+        };
+        
+        let compiler = SqlCompilerFactory::create_with_config(config.clone()).unwrap();
+        assert_eq!(compiler.optimizer().optimization_config().max_or_conditions_for_in, 10);
+        assert_eq!(compiler.optimizer().optimization_config().max_in_values, 2000);
+        assert_eq!(compiler.table_mapper().get_table_name("Entity"), "entity_table");
+        assert_eq!(compiler.table_mapper().get_table_name("TestRun"), "test_runs");
+    }
+
+    struct StubSchema;
+
+    impl SchemaProvider for StubSchema {
+        fn has_column(&self, entity: &str, field: &str) -> bool {
+            matches!((entity, field), ("Test", "status") | ("Run", "status"))
+        }
+    }
+
+    #[test]
+    fn test_validate_reports_unknown_fields() {
+        use crate::lexer::Lexer;
+        use crate::parser::Parser;
+
+        let input = r#"Filter: status["Open"]; foo[>2]; CrossFilter: <Test-Run> status["PASS"]"#;
+        let tokens: Vec<_> = Lexer::new(input).collect();
+        let query = Parser::new(&tokens).parse().unwrap();
+
+        let compiler = create_test_compiler();
+        let errors = compiler.validate(&query, "Test", &StubSchema);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].entity, "Test");
+        assert_eq!(errors[0].field, "foo");
+        let span = errors[0].span.unwrap();
+        assert_eq!(&input[span.start..span.end], "foo[>2]");
+    }
+
+    struct TypedSchema;
+
+    impl SchemaProvider for TypedSchema {
+        fn has_column(&self, entity: &str, field: &str) -> bool {
+            self.column_type(entity, field).is_some()
+        }
+
+        fn column_type(&self, entity: &str, field: &str) -> Option<ColumnType> {
+            match (entity, field) {
+                ("Test", "title") => Some(ColumnType::Text),
+                ("Test", "priority") => Some(ColumnType::Integer),
+                ("Test", "flaky") => Some(ColumnType::Boolean),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_operator_against_column_type() {
+        let compiler = create_test_compiler();
+        let validate = |input: &str| {
+            let query: Query = input.parse().unwrap();
+            compiler.validate(&query, "Test", &TypedSchema)
+        };
+
+        assert!(validate(r#"Filter: title[LIKE "login%"]; priority[BETWEEN 1 AND 3]; flaky[=1]"#).is_empty());
+
+        let errors = validate(r#"Filter: priority[NOT LIKE "1%" OR LIKE "2%"]"#);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "priority");
+        assert_eq!(errors[0].message, "实体 Test 的字段 priority 类型为 Integer，不支持 LIKE 运算");
+
+        let errors = validate(r#"Filter: flaky[>0]; title[BETWEEN "a" AND "m"]"#);
+        let fields: Vec<&str> = errors.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(fields, ["flaky", "title"]);
+    }
+
+    #[test]
+    fn test_compile_result_to_json() {
+        let result = CompileResult {
+            sql: "SELECT 1".to_string(),
+            optimizations: vec![Optimization::OrToIn {
+                field: "tests.status".to_string(),
+                value_count: 5,
+            }],
+            diagnostics: vec![],
+        };
+
+        let json = result.to_json();
+        assert_eq!(json["sql"], "SELECT 1");
+        assert_eq!(json["optimizations"][0]["type"], "OrToIn");
+        assert_eq!(json["optimizations"][0]["field"], "tests.status");
+        assert_eq!(json["optimizations"][0]["value_count"], 5);
+    }
+
+    #[test]
+    fn test_batch_total_estimated_rows_counts_actual_values() {
+        let compiler = create_test_compiler();
+        let query = Query {
+            base_filters: vec![FieldFilter {
+                field: Identifier("id".to_string()),
+                condition: Condition::In((0..1200).map(Literal::Number).collect()),
+                span: None,
+            }],
+            ..Default::default()
+        };
+
+        let result = compiler.compile_batch_query(query, "Test").unwrap();
+        assert_eq!(result.queries.len(), 3);
+        assert_eq!(result.total_estimated_rows, Some(1200));
+        // 每个批次只包含自己的值
+        assert!(result.queries[0].contains("499") && !result.queries[0].contains("500"));
+        assert!(result.queries[2].contains("1199") && !result.queries[2].contains("999"));
+    }
+
+    #[test]
+    fn test_batch_skips_large_in_under_not_or_or() {
+        let compiler = create_test_compiler();
+        let large_in = || Condition::In((0..1200).map(Literal::Number).collect());
+        let greater_than_five = Condition::Comparison { op: CompOp::Gt, value: Literal::Number(5) };
+        let conditions = [
+            Condition::Not(Box::new(large_in())),
+            Condition::Or(Box::new(large_in()), Box::new(greater_than_five)),
+        ];
+
+        for condition in conditions {
+            let query = Query {
+                base_filters: vec![FieldFilter { field: Identifier("id".to_string()), condition, span: None }],
+                ..Default::default()
+            };
+            let result = compiler.compile_batch_query(query, "Test").unwrap();
+            assert_eq!(result.queries.len(), 1);
+            assert!(result.queries[0].contains("1199"), "SQL: {}", result.queries[0]);
+            assert_ne!(result.total_estimated_rows, Some(1200));
+        }
+
+        // AND 连接的 IN 仍然拆分
+        let query = Query {
+            base_filters: vec![FieldFilter {
+                field: Identifier("id".to_string()),
+                condition: Condition::And(
+                    Box::new(large_in()),
+                    Box::new(Condition::Comparison { op: CompOp::Gt, value: Literal::Number(5) }),
+                ),
+                span: None,
+            }],
+            ..Default::default()
+        };
+        assert_eq!(compiler.compile_batch_query(query, "Test").unwrap().queries.len(), 3);
+    }
+
+    #[test]
+    fn test_batch_skips_large_in_inside_or_group() {
+        let compiler = create_test_compiler();
+        let query = Query {
+            base_or_groups: vec![vec![
+                FieldFilter {
+                    field: Identifier("status".to_string()),
+                    condition: Condition::Comparison { op: CompOp::Eq, value: Literal::String("Open".to_string()) },
+                    span: None,
+                },
+                FieldFilter {
+                    field: Identifier("id".to_string()),
+                    condition: Condition::In((0..1200).map(Literal::Number).collect()),
+                    span: None,
+                },
+            ]],
+            ..Default::default()
+        };
+
+        // 拆分后每个批次都会重复返回满足 status 条件的行，因此整体不做批量处理
+        let result = compiler.compile_batch_query(query, "Test").unwrap();
+        assert_eq!(result.queries.len(), 1);
+        assert!(result.queries[0].contains("OR") && result.queries[0].contains("1199"), "SQL: {}", result.queries[0]);
+
+        let mut query: Query = r#"Filter: status["Open"]"#.parse().unwrap();
+        query.base_filters[0].condition = Condition::In((0..1200).map(Literal::Number).collect());
+        query.or_branches.push(r#"Filter: status["Closed"]"#.parse().unwrap());
+        assert_eq!(compiler.compile_batch_query(query, "Test").unwrap().queries.len(), 1);
+    }
+
+    #[test]
+    fn test_batch_uses_configured_table_mapping() {
+        let compiler = create_test_compiler();
+        let small: Query = r#"Filter: status["Open"]"#.parse().unwrap();
+        let large = Query {
+            base_filters: vec![FieldFilter {
+                field: Identifier("id".to_string()),
+                condition: Condition::In((0..600).map(Literal::Number).collect()),
+                span: None,
+            }],
+            ..Default::default()
+        };
+
+        for query in [small, large] {
+            let result = compiler.compile_batch_query(query, "Test").unwrap();
+            for sql in &result.queries {
+                assert!(sql.contains(r#"FROM "tests""#), "SQL: {}", sql);
+                assert!(!sql.contains(r#""test"."#), "SQL: {}", sql);
+            }
+        }
+    }
+
+    #[test]
+    fn test_batch_trait_uses_processor_dialect_and_mapping() {
+        let compiler = SqlCompiler::from_config(CompilerConfig {
+            dialect: SqlDialect::MySQL,
+            table_mapping: HashMap::from([(String::from("Test"), String::from("tests"))]),
+            ..Default::default()
+        });
+        let query: Query = "Filter: id[IN (1, 2, 3)]".parse().unwrap();
+        let config = BatchConfig { max_batch_size: 2, ..Default::default() };
+
+        let result = compiler.batch_processor().compile_batch(query.clone(), "Test", &config).unwrap();
+        assert_eq!(result.queries.len(), 2);
+        for sql in &result.queries {
+            assert!(sql.starts_with("SELECT * FROM `tests`"), "SQL: {}", sql);
+        }
+
+        let processor = DefaultBatchProcessor::new()
+            .with_dialect(SqlDialect::SQLite)
+            .with_table_mapper(DefaultTableMapper::new().with_naming_strategy(NamingStrategy::SnakeCasePlural));
+        let result = processor.compile_batch(query, "Test", &config).unwrap();
+        assert!(result.queries[0].starts_with(r#"SELECT * FROM "tests""#), "SQL: {}", result.queries[0]);
+    }
+
+    #[test]
+    fn test_in_list_compiles_to_any_array_parameter() {
+        let mut compiler = create_test_compiler();
+        let query = Query {
+            base_filters: vec![FieldFilter {
+                field: Identifier("id".to_string()),
+                condition: Condition::In((0..1000).map(Literal::Number).collect()),
+                span: None,
+            }],
+            ..Default::default()
+        };
+
+        // 默认每个值一个占位符
+        let result = compiler.compile_parameterized(query.clone(), "Test").unwrap();
+        assert_eq!(result.values.len(), 1000);
+        assert!(result.sql.contains("$1000"));
+
+        let mut config = compiler.optimizer().optimization_config().clone();
+        config.use_any_for_in = true;
+        compiler.optimizer_mut().set_optimization_config(config);
+
+        let result = compiler.compile_parameterized(query, "Test").unwrap();
+        assert!(result.sql.contains(r#""tests"."id" = ANY($1)"#), "SQL: {}", result.sql);
+        assert_eq!(result.values.len(), 1);
+        assert!(matches!(&result.values[0], Value::Array(ArrayType::BigInt, Some(items)) if items.len() == 1000));
+    }
+
+    #[test]
+    fn test_sample_clause_compiles_to_tablesample() {
+        let compiler = create_test_compiler();
+        let query = Query {
+            base_filters: vec![
+                FieldFilter {
+                    field: Identifier("status".to_string()),
+                    condition: Condition::Comparison {
+                        op: CompOp::Eq,
+                        value: Literal::String("Open".to_string()),
+                    },
+                    span: None,
+                }
+            ],
+            sample_percent: Some(10),
+            ..Default::default()
+        };
+
+        let result = compiler.compile(query, "Test").unwrap();
+        assert!(result.sql.contains(r#"FROM "tests" TABLESAMPLE BERNOULLI (10) WHERE"#));
+    }
+
+    #[test]
+    fn test_cross_filter_chain_compiles_to_joins() {
+        let mut compiler = SqlCompiler::new();
+        let mut mapping = HashMap::new();
+        mapping.insert("Test".to_string(), "tests".to_string());
+        mapping.insert("Run".to_string(), "test_runs".to_string());
+        mapping.insert("Step".to_string(), "test_steps".to_string());
+        compiler.table_mapper_mut().set_table_mapping(mapping);
+
+        let query = Query {
+            cross_filters: vec![CrossFilter {
+                source_entity: Identifier("Test".to_string()),
+                intermediate_entities: vec![Identifier("Run".to_string())],
+                target_entity: Identifier("Step".to_string()),
+                filters: vec![FieldFilter {
+                    field: Identifier("result".to_string()),
+                    condition: Condition::Comparison {
+                        op: CompOp::Eq,
+                        value: Literal::String("PASS".to_string()),
+                    },
+                    span: None,
+                }],
+            }],
+            ..Default::default()
+        };
+
+        let result = compiler.compile(query, "Test").unwrap();
+        assert!(result.sql.contains(
+            r#"INNER JOIN "test_runs" AS "joined_table_1" ON "tests"."id" = "joined_table_1"."id""#
+        ), "SQL: {}", result.sql);
+        assert!(result.sql.contains(
+            r#"INNER JOIN "test_steps" AS "joined_table_2" ON "joined_table_1"."id" = "joined_table_2"."id""#
+        ), "SQL: {}", result.sql);
+        assert!(result.sql.contains(r#""joined_table_2"."result" = 'PASS'"#), "SQL: {}", result.sql);
+    }
+
+    #[test]
+    fn test_compile_to_statement_matches_compile() {
+        let compiler = create_test_compiler();
+        let query: Query = r#"Filter: status["Open"]; CrossFilter: <Test-Run> result["PASS"]"#.parse().unwrap();
+
+        let compiled = compiler.compile(query.clone(), "Test").unwrap();
+        let mut statement = compiler.compile_to_statement(query, "Test").unwrap();
+        assert_eq!(statement.to_string(PostgresQueryBuilder), compiled.sql);
+
+        // 调用方追加自己的条件和分页
+        statement
+            .and_where(Expr::col((TableName("tests".to_string()), ColumnName("tenant_id".to_string()))).eq(7))
+            .limit(10);
+        let sql = statement.to_string(PostgresQueryBuilder);
+        assert!(sql.starts_with(&compiled.sql), "SQL: {}", sql);
+        assert!(sql.ends_with(r#" AND "tests"."tenant_id" = 7 LIMIT 10"#), "SQL: {}", sql);
+    }
+
+    #[test]
+    fn test_in_subquery_compiles() {
+        let query: Query = r#"Filter: project_id[IN (CrossFilter: <Project> status["Active"])]"#.parse().unwrap();
+        let result = SqlCompiler::new().compile(query, "Task").unwrap();
+        assert!(result.sql.contains(
+            r#""task"."project_id" IN (SELECT "project"."id" FROM "project" WHERE "project"."status" = 'Active')"#
+        ), "SQL: {}", result.sql);
+    }
+
+    #[test]
+    fn test_tuple_in_compiles() {
+        let compiler = create_test_compiler();
+        let tuple_query = |rows: Vec<Vec<Literal>>| Query {
+            base_filters: vec![FieldFilter {
+                field: Identifier("(a, b)".to_string()),
+                condition: Condition::InTuple {
+                    fields: vec![Identifier("a".to_string()), Identifier("b".to_string())],
+                    rows,
+                },
+                span: None,
+            }],
+            ..Default::default()
+        };
+
+        let result = compiler.compile(tuple_query(vec![
+            vec![Literal::Number(1), Literal::String("x".to_string())],
+            vec![Literal::Number(2), Literal::String("y".to_string())],
+        ]), "Test").unwrap();
+        assert!(result.sql.contains(r#"("tests"."a", "tests"."b") IN ((1, 'x'), (2, 'y'))"#), "SQL: {}", result.sql);
+
+        let result = compiler.compile(tuple_query(vec![
+            vec![Literal::Number(1), Literal::String("x".to_string())],
+        ]), "Test").unwrap();
+        assert!(result.sql.contains(r#"("tests"."a", "tests"."b") IN ((1, 'x'))"#), "SQL: {}", result.sql);
+
+        let result = compiler.compile(tuple_query(vec![]), "Test").unwrap();
+        assert!(result.sql.ends_with("WHERE FALSE"), "SQL: {}", result.sql);
+    }
+
+    fn optimize_str(input: &str) -> (Query, Vec<Optimization>) {
+        let mut query: Query = input.parse().unwrap();
+        let optimizations = DefaultQueryOptimizer::new().optimize(&mut query);
+        (query, optimizations)
+    }
+
+    #[test]
+    fn test_not_pushdown_de_morgan() {
+        let (query, optimizations) = optimize_str(r#"Filter: status[NOT ("Open" AND "Pending")]"#);
+        assert_eq!(query.base_filters[0].condition.to_string(), r#"(!="Open" OR !="Pending")"#);
+        assert_eq!(optimizations, vec![Optimization::ConditionSimplification {
+            original: r#"status[NOT ("Open" AND "Pending")]"#.to_string(),
+            simplified: r#"status[(!="Open" OR !="Pending")]"#.to_string(),
+        }]);
+
+        let (query, _) = optimize_str(r#"Filter: priority[NOT (>5)]"#);
+        assert_eq!(query.base_filters[0].condition, Condition::Comparison {
+            op: CompOp::Lte,
+            value: Literal::Number(5),
+        });
+
+        // 双重否定相互抵消，内部的 NOT 继续下推
+        let (query, _) = optimize_str(r#"Filter: priority[NOT NOT (>1 AND NOT <=3)]"#);
+        assert_eq!(query.base_filters[0].condition.to_string(), "(>1 AND >3)");
+
+        // 没有 NOT 时不记录优化
+        let (_, optimizations) = optimize_str(r#"Filter: status["Open"]"#);
+        assert!(optimizations.is_empty());
+    }
+
+    #[test]
+    fn test_and_merge_compiles_both_predicates() {
+        let tenant: Query = r#"Filter: tenant_id[42]"#.parse().unwrap();
+        let user: Query = r#"Filter: status["Open"]; tenant_id[42]"#.parse().unwrap();
+
+        let merged = user.and_merge(tenant);
+        assert_eq!(merged.base_filters.len(), 2);
+
+        let result = create_test_compiler().compile(merged, "Test").unwrap();
+        assert!(result.sql.contains(r#""tests"."status" = 'Open'"#), "SQL: {}", result.sql);
+        assert!(result.sql.contains(r#""tests"."tenant_id" = 42"#), "SQL: {}", result.sql);
+    }
+
+    #[test]
+    fn test_in_list_value_types() {
+        let compiler = create_test_compiler();
+        let in_query = |values: Vec<Literal>| Query {
+            base_filters: vec![FieldFilter {
+                field: Identifier("priority".to_string()),
+                condition: Condition::In(values),
+                span: None,
+            }],
+            ..Default::default()
+        };
+
+        let result = compiler.compile(in_query(vec![Literal::Number(1), Literal::Number(3)]), "Test").unwrap();
+        assert!(result.sql.contains(r#""tests"."priority" IN (1, 3)"#), "SQL: {}", result.sql);
+
+        let err = compiler.compile(in_query(vec![
+            Literal::Number(1),
+            Literal::String("two".to_string()),
+            Literal::Number(3),
+        ]), "Test").unwrap_err();
+        assert!(err.message.contains(r#""two""#), "{}", err.message);
+    }
+
+    #[test]
+    fn test_range_folds_to_between() {
+        let compiler = create_test_compiler();
+        let range_query = |left: (CompOp, i64), right: (CompOp, i64)| Query {
+            base_filters: vec![FieldFilter {
+                field: Identifier("priority".to_string()),
+                condition: Condition::And(
+                    Box::new(Condition::Comparison { op: left.0, value: Literal::Number(left.1) }),
+                    Box::new(Condition::Comparison { op: right.0, value: Literal::Number(right.1) }),
+                ),
+                span: None,
+            }],
+            ..Default::default()
+        };
+
+        let result = compiler.compile(range_query((CompOp::Gte, 1), (CompOp::Lte, 10)), "Test").unwrap();
+        assert!(result.sql.contains(r#""tests"."priority" BETWEEN 1 AND 10"#), "SQL: {}", result.sql);
+        assert_eq!(result.optimizations, vec![Optimization::ConditionSimplification {
+            original: "tests.priority >= 1 AND tests.priority <= 10".to_string(),
+            simplified: "tests.priority BETWEEN 1 AND 10".to_string(),
+        }]);
+
+        // 开区间不折叠，避免对非整数列改变语义
+        let result = compiler.compile(range_query((CompOp::Gt, 5), (CompOp::Lt, 10)), "Test").unwrap();
+        assert!(!result.sql.contains("BETWEEN"), "SQL: {}", result.sql);
+        assert!(result.sql.contains(r#""tests"."priority" > 5"#), "SQL: {}", result.sql);
+        assert!(result.sql.contains(r#""tests"."priority" < 10"#), "SQL: {}", result.sql);
+        assert!(result.optimizations.is_empty());
+
+        // 一端开区间同样保持原样
+        let result = compiler.compile(range_query((CompOp::Gte, 5), (CompOp::Lt, 10)), "Test").unwrap();
+        assert!(!result.sql.contains("BETWEEN"), "SQL: {}", result.sql);
+
+        // 同方向的比较不构成范围
+        let result = compiler.compile(range_query((CompOp::Gt, 1), (CompOp::Gt, 2)), "Test").unwrap();
+        assert!(!result.sql.contains("BETWEEN"), "SQL: {}", result.sql);
+        assert!(result.optimizations.is_empty());
+    }
+
+    #[test]
+    fn test_field_with_space_is_quoted() {
+        let compiler = create_test_compiler();
+        let query = Query {
+            base_filters: vec![FieldFilter {
+                field: Identifier("Due Date".to_string()),
+                condition: Condition::IsNull,
+                span: None,
+            }],
+            ..Default::default()
+        };
+
+        let result = compiler.compile(query, "Test").unwrap();
+        assert!(result.sql.contains(r#""tests"."Due Date" IS NULL"#), "SQL: {}", result.sql);
+    }
+
+    #[test]
+    fn test_cross_filter_join_type() {
+        let query = Query {
+            cross_filters: vec![CrossFilter {
+                source_entity: Identifier("Test".to_string()),
+                intermediate_entities: vec![],
+                target_entity: Identifier("Run".to_string()),
+                filters: vec![FieldFilter {
+                    field: Identifier("status".to_string()),
+                    condition: Condition::IsNotNull,
+                    span: None,
+                }],
+            }],
+            ..Default::default()
+        };
+
+        let result = SqlCompiler::new().compile(query.clone(), "Test").unwrap();
+        assert!(result.sql.contains(r#"INNER JOIN "run" AS "joined_table_1""#), "SQL: {}", result.sql);
+
+        let compiler = SqlCompiler::from_config(CompilerConfig {
+            join_type: JoinType::LeftJoin,
+            ..Default::default()
+        });
+        let result = compiler.compile(query, "Test").unwrap();
+        assert!(result.sql.contains(r#"LEFT JOIN "run" AS "joined_table_1""#), "SQL: {}", result.sql);
+        assert!(!result.sql.contains("INNER JOIN"));
+    }
+
+    #[test]
+    fn test_case_insensitive_string_comparison() {
+        let compiler = SqlCompiler::from_config(CompilerConfig {
+            case_insensitive_strings: true,
+            ..Default::default()
+        });
+        let query = Query {
+            base_filters: vec![
+                FieldFilter {
+                    field: Identifier("status".to_string()),
+                    condition: Condition::Comparison {
+                        op: CompOp::Eq,
+                        value: Literal::String("Open".to_string()),
+                    },
+                    span: None,
+                },
+                FieldFilter {
+                    field: Identifier("priority".to_string()),
+                    condition: Condition::Comparison {
+                        op: CompOp::Eq,
+                        value: Literal::Number(2),
+                    },
+                    span: None,
+                },
+            ],
+            ..Default::default()
+        };
+
+        let result = compiler.compile(query, "Test").unwrap();
+        assert!(result.sql.contains(r#"LOWER("test"."status") = LOWER('Open')"#), "SQL: {}", result.sql);
+        assert!(result.sql.contains(r#""test"."priority" = 2"#), "SQL: {}", result.sql);
+        assert!(!result.sql.contains(r#"LOWER("test"."priority")"#));
+    }
+
+    #[test]
+    fn test_case_insensitive_in_lists() {
+        let compiler = SqlCompiler::from_config(CompilerConfig {
+            case_insensitive_strings: true,
+            ..Default::default()
+        });
+        let compile = |input: &str| compiler.compile(input.parse().unwrap(), "Test").map(|result| result.sql);
+
+        let sql = compile(r#"Filter: status[IN ("Open", "closed")]"#).unwrap();
+        assert!(sql.ends_with(r#"WHERE LOWER("test"."status") IN (LOWER('Open'), LOWER('closed'))"#), "SQL: {}", sql);
+        let sql = compile(r#"Filter: status[NOT IN ("Open", "closed")]"#).unwrap();
+        assert!(sql.contains(r#"LOWER("test"."status") IN (LOWER('Open'), LOWER('closed'))"#) && sql.contains("NOT"), "SQL: {}", sql);
+
+        // OR / AND NOT 合并出的 IN 列表同样忽略大小写
+        let sql = compile(r#"Filter: status["A" OR "b" OR "C" OR "d" OR "E"]"#).unwrap();
+        assert!(sql.contains(r#"LOWER("test"."status") IN (LOWER('A'), LOWER('b')"#), "SQL: {}", sql);
+        let sql = compile(r#"Filter: status[NOT "A" AND NOT "b" AND NOT "C" AND NOT "d" AND NOT "E"]"#).unwrap();
+        assert!(sql.contains(r#"LOWER("test"."status") NOT IN (LOWER('A'), LOWER('b')"#), "SQL: {}", sql);
+
+        // 数字列表不受影响
+        let sql = compile("Filter: priority[IN (1, 2)]").unwrap();
+        assert!(sql.ends_with(r#"WHERE "test"."priority" IN (1, 2)"#), "SQL: {}", sql);
+
+        let err = compile(r#"Filter: status[= ANY ("Open", "closed")]"#).unwrap_err();
+        assert_eq!(err.message, "忽略大小写时 ANY 不支持字符串的相等比较，请改用 IN");
+        assert!(compile(r#"Filter: status[!= ALL ("Open", "closed")]"#).is_err());
+        assert!(compile("Filter: priority[= ANY (1, 2)]").is_ok());
+    }
+
+    #[test]
+    fn test_relative_date_compiles_to_interval() {
+        let query: Query = "Filter: created[>today-7]; due[<tomorrow]".parse().unwrap();
+
+        let result = SqlCompiler::new().compile(query.clone(), "Test").unwrap();
+        assert!(result.sql.contains(r#""test"."created" > (CURRENT_DATE - INTERVAL '7 days')"#), "SQL: {}", result.sql);
+        assert!(result.sql.contains(r#""test"."due" < (CURRENT_DATE + INTERVAL '1 day')"#), "SQL: {}", result.sql);
+
+        let mysql = SqlCompiler::from_config(CompilerConfig {
+            dialect: SqlDialect::MySQL,
+            ..Default::default()
+        });
+        let result = mysql.compile(query, "Test").unwrap();
+        assert!(result.sql.contains("`test`.`created` > (CURRENT_DATE - INTERVAL 7 DAY)"), "SQL: {}", result.sql);
+    }
+
+    #[test]
+    fn test_relative_date_per_dialect() {
+        let query: Query = "Filter: created[>yesterday]; due[<tomorrow]".parse().unwrap();
+        let compile = |dialect| {
+            SqlCompiler::from_config(CompilerConfig { dialect, ..Default::default() })
+                .compile(query.clone(), "Test")
+                .unwrap()
+                .sql
+        };
+
+        let sql = compile(SqlDialect::PostgreSQL);
+        assert!(sql.contains(r#""test"."created" > (CURRENT_DATE - INTERVAL '1 day')"#), "SQL: {}", sql);
+        assert!(sql.contains(r#""test"."due" < (CURRENT_DATE + INTERVAL '1 day')"#), "SQL: {}", sql);
+
+        let sql = compile(SqlDialect::MySQL);
+        assert!(sql.contains("`test`.`created` > (CURRENT_DATE - INTERVAL 1 DAY)"), "SQL: {}", sql);
+        assert!(sql.contains("`test`.`due` < (CURRENT_DATE + INTERVAL 1 DAY)"), "SQL: {}", sql);
+
+        let sql = compile(SqlDialect::SQLite);
+        assert!(sql.contains(r#""test"."created" > (date('now', '-1 day'))"#), "SQL: {}", sql);
+        assert!(sql.contains(r#""test"."due" < (date('now', '+1 day'))"#), "SQL: {}", sql);
+    }
+
+    #[test]
+    fn test_column_naming_modes() {
+        let query: Query = "Filter: dueDate[>5]".parse().unwrap();
+
+        let preserved = SqlCompiler::new().compile(query.clone(), "Test").unwrap();
+        assert!(preserved.sql.contains(r#""test"."dueDate" > 5"#), "SQL: {}", preserved.sql);
+
+        let snake = SqlCompiler::from_config(CompilerConfig {
+            column_naming: ColumnNaming::SnakeCase,
+            ..Default::default()
+        });
+        let result = snake.compile(query, "Test").unwrap();
+        assert!(result.sql.contains(r#""test"."due_date" > 5"#), "SQL: {}", result.sql);
+    }
+
+    #[test]
+    fn test_optimization_describe() {
+        let cases = vec![
+            (
+                Optimization::OrToIn { field: "status".to_string(), value_count: 5 },
+                "Converted 5 OR conditions on 'status' into an IN clause",
+            ),
+            (
+                Optimization::AndNotToNotIn { field: "status".to_string(), value_count: 5 },
+                "Converted 5 AND NOT conditions on 'status' into a NOT IN clause",
+            ),
+            (
+                Optimization::InToUnion { field: "id".to_string(), total_values: 2500, union_count: 3 },
+                "Split an IN list of 2500 values on 'id' into 3 smaller IN clauses",
+            ),
+            (
+                Optimization::ConditionSimplification {
+                    original: "priority[NOT =1]".to_string(),
+                    simplified: "priority[!=1]".to_string(),
+                },
+                "Simplified 'priority[NOT =1]' to 'priority[!=1]'",
+            ),
+            (
+                Optimization::RedundantConditionRemoval { removed_condition: "status[\"Open\"]".to_string() },
+                "Removed redundant condition 'status[\"Open\"]'",
+            ),
+        ];
+
+        for (optimization, expected) in cases {
+            assert_eq!(optimization.describe(), expected);
+        }
+    }
+
+    #[test]
+    fn test_compile_where_fragment() {
+        let compiler = create_test_compiler();
+
+        let query: Query = r#"Filter: status["Open"]; priority[>2]"#.parse().unwrap();
+        let fragment = compiler.compile_where(query, "Test").unwrap();
+        assert_eq!(fragment, r#""tests"."status" = 'Open' AND "tests"."priority" > 2"#);
+
+        assert_eq!(compiler.compile_where(Query::default(), "Test").unwrap(), "TRUE");
+
+        let cross: Query = r#"CrossFilter: <Test-Run> result["PASS"]"#.parse().unwrap();
+        assert!(compiler.compile_where(cross, "Test").is_err());
+    }
+
+    #[test]
+    fn test_current_user_resolution() {
+        let query: Query = "Filter: assignee[current_user]".parse().unwrap();
+
+        let result = SqlCompiler::new().compile(query.clone(), "Test").unwrap();
+        assert!(result.sql.contains(r#""test"."assignee" = CURRENT_USER"#), "SQL: {}", result.sql);
+
+        let compiler = SqlCompiler::from_config(CompilerConfig {
+            current_user_id: Some(Value::BigInt(Some(42))),
+            ..Default::default()
+        });
+        let result = compiler.compile(query.clone(), "Test").unwrap();
+        assert!(result.sql.contains(r#""test"."assignee" = 42"#), "SQL: {}", result.sql);
+
+        let parameterized = compiler.compile_parameterized(query, "Test").unwrap();
+        assert_eq!(parameterized.values, vec![Value::BigInt(Some(42))]);
+    }
+
+    #[test]
+    fn test_missing_join_key_diagnostic() {
+        let query: Query = r#"CrossFilter: <Foo-Bar> name["x"]"#.parse().unwrap();
+
+        let result = SqlCompiler::new().compile(query.clone(), "Foo").unwrap();
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(result.diagnostics[0].severity, DiagnosticSeverity::Warning);
+        assert!(result.diagnostics[0].message.contains("Foo-Bar"));
+
+        let mut join_keys = HashMap::new();
+        join_keys.insert(("Foo".to_string(), "Bar".to_string()), JoinKey::new("bar_id", "id"));
+        let compiler = SqlCompiler::from_config(CompilerConfig {
+            join_keys,
+            ..Default::default()
+        });
+        let result = compiler.compile(query, "Foo").unwrap();
+        assert!(result.diagnostics.is_empty());
+        assert!(result.sql.contains(r#""foo"."bar_id" = "joined_table_1"."id""#), "SQL: {}", result.sql);
+    }
+
+    #[test]
+    fn test_join_key_strategy_convention() {
+        let query: Query = r#"CrossFilter: <Test-Run> result["FAIL"]"#.parse().unwrap();
+        let config = || CompilerConfig {
+            table_mapping: TableMappingConfig::default().mappings,
+            join_key_strategy: JoinKeyStrategy::foreign_key(),
+            ..Default::default()
+        };
+
+        let result = SqlCompiler::from_config(config()).compile(query.clone(), "Test").unwrap();
+        assert!(result.sql.contains(r#""tests"."id" = "joined_table_1"."test_id""#), "SQL: {}", result.sql);
+        assert!(result.diagnostics.is_empty());
+
+        // 显式配置的连接键优先于约定
+        let mut join_keys = HashMap::new();
+        join_keys.insert(("Test".to_string(), "Run".to_string()), JoinKey::new("latest_run_id", "id"));
+        let compiler = SqlCompiler::from_config(CompilerConfig { join_keys, ..config() });
+        let result = compiler.compile(query, "Test").unwrap();
+        assert!(result.sql.contains(r#""tests"."latest_run_id" = "joined_table_1"."id""#), "SQL: {}", result.sql);
+    }
+
+    #[test]
+    fn test_cross_field_or_group() {
+        let compiler = create_test_compiler();
+        let query: Query = r#"Filter: type["Bug"]; (status["Open"] OR priority[>5])"#.parse().unwrap();
+
+        let result = compiler.compile(query, "Test").unwrap();
+        assert!(
+            result.sql.contains(r#""tests"."type" = 'Bug' AND ("tests"."status" = 'Open' OR "tests"."priority" > 5)"#),
+            "SQL: {}", result.sql
+        );
+    }
+
+    #[test]
+    fn test_batch_union_all_output() {
+        let compiler = SqlCompiler::from_config(CompilerConfig {
+            batch_config: BatchConfig {
+                output_mode: BatchOutputMode::UnionAll,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let query = Query {
+            base_filters: vec![FieldFilter {
+                field: Identifier("id".to_string()),
+                condition: Condition::In((0..1200).map(Literal::Number).collect()),
+                span: None,
+            }],
+            ..Default::default()
+        };
+
+        let result = compiler.compile_batch_query(query, "Test").unwrap();
+        assert_eq!(result.queries.len(), 1);
+        assert_eq!(result.queries[0].matches(" UNION ALL ").count(), 2);
+        assert_eq!(result.queries[0].matches(r#"SELECT * FROM "test""#).count(), 3);
+    }
+
+    #[test]
+    fn test_batch_union_keeps_rows_distinct() {
+        let compiler = SqlCompiler::from_config(CompilerConfig {
+            batch_config: BatchConfig {
+                max_batch_size: 2,
+                output_mode: BatchOutputMode::UnionAll,
+                ..Default::default()
+            },
+            replica_hint: Some(ReplicaHint::Comment("replica".to_string())),
+            ..Default::default()
+        });
+
+        // 单个 IN 列表的重复值只落入一个批次，提示只出现一次
+        let query: Query = "Filter: id[IN (1, 2, 1, 3)]".parse().unwrap();
+        let sql = compiler.compile_batch_query(query, "Test").unwrap().queries.remove(0);
+        assert_eq!(sql.matches(" UNION ALL ").count(), 1, "SQL: {}", sql);
+        assert_eq!(sql.matches("/* replica */").count(), 1, "SQL: {}", sql);
+        assert!(sql.starts_with("/* replica */ SELECT"), "SQL: {}", sql);
+        assert!(sql.contains(r#""test"."id" IN (1, 2) UNION ALL"#), "SQL: {}", sql);
+
+        // 两个字段上的 IN 各自拆分时批次会重叠，改用 UNION 去重
+        let query: Query = "Filter: id[IN (1, 2, 3)]; parent_id[IN (4, 5, 6)]".parse().unwrap();
+        let sql = compiler.compile_batch_query(query, "Test").unwrap().queries.remove(0);
+        assert!(!sql.contains("UNION ALL"), "SQL: {}", sql);
+        assert_eq!(sql.matches(" UNION ").count(), 3, "SQL: {}", sql);
+
+        // 关联Filter中的 IN 同样可能重叠
+        let query: Query = "CrossFilter: <Test-Run> id[IN (1, 2, 3)]".parse().unwrap();
+        let sql = compiler.compile_batch_query(query, "Test").unwrap().queries.remove(0);
+        assert!(!sql.contains("UNION ALL") && sql.contains(" UNION "), "SQL: {}", sql);
+
+        let query: Query = "Filter: id[IN (1, 2, 3)]; Sample: 10%".parse().unwrap();
+        let err = compiler.compile_batch_query(query, "Test").unwrap_err();
+        assert_eq!(err.message, "UNION ALL 批次输出不支持采样子句");
+    }
+
+    #[test]
+    fn test_batch_union_all_with_sort() {
+        let compiler = SqlCompiler::from_config(CompilerConfig {
+            batch_config: BatchConfig {
+                max_batch_size: 3,
+                output_mode: BatchOutputMode::UnionAll,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let query: Query = "Filter: id[IN (0, 1, 2, 3, 4)]; Sort: priority DESC".parse().unwrap();
+
+        let result = compiler.compile_batch_query(query, "Test").unwrap();
+        assert_eq!(result.queries.len(), 1);
+        let sql = &result.queries[0];
+        assert_eq!(sql.matches("ORDER BY").count(), 1, "SQL: {}", sql);
+        assert!(sql.starts_with(r#"SELECT * FROM (SELECT * FROM "test" WHERE "test"."id" IN (0, 1, 2) UNION ALL "#), "SQL: {}", sql);
+        assert!(sql.ends_with(r#") AS "batched" ORDER BY "batched"."priority" DESC"#), "SQL: {}", sql);
+
+        // 分别输出时每个批次都是独立的查询，各自保留排序
+        let compiler = SqlCompiler::from_config(CompilerConfig {
+            batch_config: BatchConfig { max_batch_size: 3, ..Default::default() },
+            ..Default::default()
+        });
+        let query: Query = "Filter: id[IN (0, 1, 2, 3, 4)]; Sort: priority DESC".parse().unwrap();
+        let result = compiler.compile_batch_query(query, "Test").unwrap();
+        assert_eq!(result.queries.len(), 2);
+        assert!(result.queries.iter().all(|sql| sql.ends_with(r#"ORDER BY "test"."priority" DESC"#)));
+    }
+
+    #[test]
+    fn test_cross_filter_preview() {
+        let compiler = create_test_compiler();
+        let query: Query = r#"CrossFilter: <Test-Run> result["PASS"]"#.parse().unwrap();
+
+        let fragment = compiler.compile_cross_filter_preview(&query.cross_filters[0], "Test").unwrap();
+        assert_eq!(
+            fragment,
+            r#"INNER JOIN "test_runs" AS "joined_table_1" ON "tests"."id" = "joined_table_1"."id" WHERE "joined_table_1"."result" = 'PASS'"#
+        );
+    }
+
+    #[test]
+    fn test_null_safe_equality_per_dialect() {
+        let query: Query = "Filter: owner[<=>5]".parse().unwrap();
+        let compile = |dialect| {
+            SqlCompiler::from_config(CompilerConfig { dialect, ..Default::default() })
+                .compile(query.clone(), "Test")
+                .unwrap()
+                .sql
+        };
+
+        assert!(compile(SqlDialect::PostgreSQL).contains(r#""test"."owner" IS NOT DISTINCT FROM 5"#));
+        assert!(compile(SqlDialect::MySQL).contains("`test`.`owner` <=> 5"));
+        assert!(compile(SqlDialect::SQLite).contains(r#""test"."owner" IS 5"#));
+
+        // `<=> null` 等价于 IS NULL；取反时保留 NOT
+        let null_query: Query = "Filter: owner[<=>null]".parse().unwrap();
+        assert_eq!(null_query.base_filters[0].condition, Condition::IsNull);
+        let (negated, optimizations) = optimize_str("Filter: owner[NOT <=>5]");
+        assert_eq!(negated.base_filters[0].condition.to_string(), "NOT <=>5");
+        assert!(optimizations.is_empty());
+    }
+
+    #[test]
+    fn test_max_complexity_score() {
+        let compiler = SqlCompiler::from_config(CompilerConfig {
+            max_complexity_score: Some(5.0),
+            ..Default::default()
+        });
+
+        // 1 个 JOIN (2 分) + 2 个条件 (2 分) = 4 分
+        let simple: Query = r#"Filter: status["Open"]; CrossFilter: <Test-Run> result["PASS"]"#.parse().unwrap();
+        assert!(compiler.compile(simple, "Test").is_ok());
+
+        // 3 个 JOIN (6 分) + 3 个条件 (3 分) = 9 分
+        let complex: Query = r#"CrossFilter: <Test-Run> a[1]; CrossFilter: <Test-Step> b[2]; CrossFilter: <Test-Bug> c[3]"#
+            .parse()
+            .unwrap();
+        let err = compiler.compile(complex, "Test").unwrap_err();
+        assert!(err.message.contains("超过上限"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_max_joins() {
+        let compiler = SqlCompiler::from_config(CompilerConfig {
+            max_joins: Some(2),
+            ..Default::default()
+        });
+        let with_joins = |count: usize| -> Query {
+            let segments: Vec<String> = (0..count)
+                .map(|i| format!(r#"CrossFilter: <Test-Run> result{}["PASS"]"#, i))
+                .collect();
+            format!(r#"Filter: status["Open"]; {}"#, segments.join("; ")).parse().unwrap()
+        };
+
+        assert!(compiler.compile(with_joins(1), "Test").is_ok());
+        assert!(compiler.compile(with_joins(2), "Test").is_ok());
+
+        let err = compiler.compile(with_joins(3), "Test").unwrap_err();
+        assert!(err.message.contains("JOIN 数量上限 2"), "{}", err.message);
+
+        // 多跳关联链的每一跳各算一个 JOIN
+        let multi_hop: Query = r#"CrossFilter: <Test-Run-Step> name["setup"]"#.parse().unwrap();
+        assert!(compiler.compile(multi_hop, "Test").is_ok());
+        let multi_hop: Query = r#"CrossFilter: <Test-Run-Step> name["setup"]; CrossFilter: <Test-Run> result["PASS"]"#.parse().unwrap();
+        let err = compiler.compile(multi_hop, "Test").unwrap_err();
+        assert!(err.message.contains("包含 3 个 JOIN"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_qualify_columns_modes() {
+        let single: Query = r#"Filter: status["Open"]"#.parse().unwrap();
+        let joined: Query = r#"Filter: status["Open"]; CrossFilter: <Test-Run> result["PASS"]"#.parse().unwrap();
+        let compile = |mode, query: &Query| {
+            SqlCompiler::from_config(CompilerConfig { qualify_columns: mode, ..Default::default() })
+                .compile(query.clone(), "Test")
+                .unwrap()
+                .sql
+        };
+
+        assert!(compile(QualifyMode::Always, &single).contains(r#"WHERE "test"."status" = 'Open'"#));
+        assert!(compile(QualifyMode::Always, &joined).contains(r#"WHERE "test"."status" = 'Open'"#));
+
+        assert!(compile(QualifyMode::OnlyWithJoins, &single).contains(r#"WHERE "status" = 'Open'"#));
+        assert!(compile(QualifyMode::OnlyWithJoins, &joined).contains(r#"WHERE "test"."status" = 'Open'"#));
+
+        assert!(compile(QualifyMode::Never, &single).contains(r#"WHERE "status" = 'Open'"#));
+        let never_joined = compile(QualifyMode::Never, &joined);
+        assert!(never_joined.contains(r#"WHERE "status" = 'Open'"#), "SQL: {}", never_joined);
+        assert!(never_joined.contains(r#""joined_table_1"."result" = 'PASS'"#), "SQL: {}", never_joined);
+    }
+
+    #[test]
+    fn test_column_to_column_comparison() {
+        let compiler = create_test_compiler();
+        let query: Query = "Filter: actual[>@estimated]; CrossFilter: <Test-Run> finished[>=@started]".parse().unwrap();
+
+        let result = compiler.compile(query, "Test").unwrap();
+        assert!(result.sql.contains(r#""tests"."actual" > "tests"."estimated""#), "SQL: {}", result.sql);
+        assert!(
+            result.sql.contains(r#""joined_table_1"."finished" >= "joined_table_1"."started""#),
+            "SQL: {}", result.sql
+        );
+    }
+
+    #[test]
+    fn test_compile_many_reports_per_query_results() {
+        let compiler = create_test_compiler();
+        let queries: Vec<(Query, &str)> = vec![
+            (r#"Filter: status["Open"]"#.parse().unwrap(), "Test"),
+            (r#"Filter: id[IN (1, "two")]"#.parse().unwrap(), "Test"),
+            (r#"Filter: result["PASS"]"#.parse().unwrap(), "Run"),
+        ];
+
+        let results = compiler.compile_many(&queries);
+        assert_eq!(results.len(), 3);
+        assert!(results[0].as_ref().unwrap().sql.contains(r#""tests"."status" = 'Open'"#));
+        assert!(results[1].as_ref().unwrap_err().message.contains("类型不一致"));
+        assert!(results[2].as_ref().unwrap().sql.contains(r#""test_runs"."result" = 'PASS'"#));
+    }
+
+    #[test]
+    fn test_compile_parameterized_named_params() {
+        let query = Query {
+            base_filters: vec![
+                FieldFilter {
+                    field: Identifier("status".to_string()),
+                    condition: Condition::Comparison { op: CompOp::Eq, value: Literal::String("Open".to_string()) },
+                    span: None,
+                },
+                FieldFilter {
+                    field: Identifier("priority".to_string()),
+                    condition: Condition::Comparison { op: CompOp::Gt, value: Literal::Number(3) },
+                    span: None,
+                },
+            ],
+            ..Default::default()
+        };
+
+        let compiler = SqlCompiler::from_config(CompilerConfig {
+            param_style: ParamStyle::Named(':'),
+            ..Default::default()
+        });
+        let result = compiler.compile_parameterized(query.clone(), "Test").unwrap();
+        assert_eq!(
+            result.sql,
+            r#"SELECT * FROM "test" WHERE "test"."status" = :p0 AND "test"."priority" > :p1"#
+        );
+        assert_eq!(result.named_values.len(), 2);
+        assert_eq!(result.named_values["p0"], Value::String(Some(Box::new("Open".to_string()))));
+        assert_eq!(result.named_values["p1"], Value::BigInt(Some(3)));
+        assert_eq!(result.values.len(), 2);
+
+        let compiler = SqlCompiler::from_config(CompilerConfig {
+            dialect: SqlDialect::MySQL,
+            param_style: ParamStyle::Named('@'),
+            ..Default::default()
+        });
+        let result = compiler.compile_parameterized(query, "Test").unwrap();
+        assert!(result.sql.contains("`test`.`status` = @p0"), "SQL: {}", result.sql);
+        assert!(result.sql.contains("`test`.`priority` > @p1"), "SQL: {}", result.sql);
+    }
+
+    #[test]
+    fn test_and_not_chain_to_not_in() {
+        let compiler = create_test_compiler();
+        let query: Query = r#"Filter: status[NOT "Open" AND NOT "Closed" AND NOT "Pending" AND NOT "Done" AND NOT "Archived"]"#
+            .parse()
+            .unwrap();
+
+        let result = compiler.compile(query, "Test").unwrap();
+        assert!(
+            result.sql.contains(r#""tests"."status" NOT IN ('Open', 'Closed', 'Pending', 'Done', 'Archived')"#),
+            "SQL: {}", result.sql
+        );
+        assert!(result.optimizations.contains(&Optimization::AndNotToNotIn {
+            field: "tests.status".to_string(),
+            value_count: 5,
+        }));
+
+        // 低于阈值时保留逐个比较
+        let query: Query = r#"Filter: status[NOT "Open" AND NOT "Closed"]"#.parse().unwrap();
+        let result = compiler.compile(query, "Test").unwrap();
+        assert!(!result.sql.contains("NOT IN"), "SQL: {}", result.sql);
+    }
+
+    #[test]
+    fn test_cross_filter_rejects_dotted_field() {
+        let query = Query {
+            cross_filters: vec![CrossFilter {
+                source_entity: Identifier("Test".to_string()),
+                intermediate_entities: vec![],
+                target_entity: Identifier("Run".to_string()),
+                filters: vec![FieldFilter {
+                    field: Identifier("runs.status".to_string()),
+                    condition: Condition::IsNotNull,
+                    span: None,
+                }],
+            }],
+            ..Default::default()
+        };
+
+        let err = create_test_compiler().compile(query, "Test").unwrap_err();
+        assert!(err.message.contains("'runs.status'"), "{}", err.message);
+        assert!(err.message.contains("不能包含 '.'"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_any_all_compile_to_postgres_arrays() {
+        let compiler = create_test_compiler();
+        let query: Query = r#"Filter: tags[= ANY ("a", "b")]; score[> ALL (1, 2)]"#.parse().unwrap();
+
+        let result = compiler.compile(query.clone(), "Test").unwrap();
+        assert!(result.sql.contains(r#""tests"."tags" = ANY(ARRAY ['a','b'])"#), "SQL: {}", result.sql);
+        assert!(result.sql.contains(r#""tests"."score" > ALL(ARRAY [1,2])"#), "SQL: {}", result.sql);
+
+        let mysql = SqlCompiler::from_config(CompilerConfig {
+            dialect: SqlDialect::MySQL,
+            ..Default::default()
+        });
+        let err = mysql.compile(query, "Test").unwrap_err();
+        assert!(err.message.contains("仅支持 PostgreSQL"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_schema_qualified_table_names() {
+        let compiler = SqlCompiler::from_config(CompilerConfig {
+            schema: Some("reporting".to_string()),
+            table_mapping: TableMappingConfig::default().mappings,
+            ..Default::default()
+        });
+        let query: Query = r#"Filter: status["Open"]; CrossFilter: <Test-Run> result["PASS"]"#.parse().unwrap();
+
+        let result = compiler.compile(query, "Test").unwrap();
+        assert!(result.sql.starts_with(r#"SELECT * FROM "reporting"."tests""#), "SQL: {}", result.sql);
+        assert!(
+            result.sql.contains(r#"INNER JOIN "reporting"."test_runs" AS "joined_table_1""#),
+            "SQL: {}", result.sql
+        );
+        assert!(!result.sql.contains(r#""reporting.tests""#), "SQL: {}", result.sql);
+    }
+
+    #[test]
+    fn test_segment_level_or_compiles_branches() {
+        let compiler = create_test_compiler();
+        let query: Query = r#"Filter: status["Open"]; priority[>5] OR CrossFilter: <Test-Run> result["FAIL"]"#
+            .parse()
+            .unwrap();
+
+        let result = compiler.compile(query, "Test").unwrap();
+        assert_eq!(
+            result.sql,
+            r#"SELECT * FROM "tests" LEFT JOIN "test_runs" AS "joined_table_1" ON "tests"."id" = "joined_table_1"."id" WHERE ("tests"."status" = 'Open' AND "tests"."priority" > 5) OR "joined_table_1"."result" = 'FAIL'"#
+        );
+
+        let query: Query = r#"Filter: status["Open"] OR Filter: status["Closed"]; priority[>5]"#.parse().unwrap();
+        assert_eq!(
+            compiler.compile_where(query, "Test").unwrap(),
+            r#""tests"."status" = 'Open' OR ("tests"."status" = 'Closed' AND "tests"."priority" > 5)"#
+        );
+
+        // 与租户范围Filter合并时按分配律展开，每个分支都带上租户条件
+        let query: Query = r#"Filter: status["Open"] OR Filter: status["Closed"]"#.parse().unwrap();
+        let tenant: Query = "Filter: tenant_id[42]".parse().unwrap();
+        assert_eq!(
+            compiler.compile_where(query.and_merge(tenant), "Test").unwrap(),
+            r#"("tests"."status" = 'Open' AND "tests"."tenant_id" = 42) OR ("tests"."status" = 'Closed' AND "tests"."tenant_id" = 42)"#
+        );
+    }
+
+    #[test]
+    fn test_cross_combinator_or_groups_predicates() {
+        let compiler = create_test_compiler();
+        let mut query: Query = r#"Filter: assignee[=current_user]; status["Open"]; CrossFilter: <Test-Run> result["FAIL"]; duration[>60]"#
+            .parse()
+            .unwrap();
+        query.cross_combinator = CrossCombinator::Or;
+
+        // 主实体的条件和关联Filter各自成组后以 OR 连接，关联可选因此使用 LEFT JOIN
+        let result = compiler.compile(query.clone(), "Test").unwrap();
+        assert_eq!(
+            result.sql,
+            r#"SELECT * FROM "tests" LEFT JOIN "test_runs" AS "joined_table_1" ON "tests"."id" = "joined_table_1"."id" WHERE ("tests"."assignee" = CURRENT_USER AND "tests"."status" = 'Open') OR ("joined_table_1"."result" = 'FAIL' AND "joined_table_1"."duration" > 60)"#
+        );
+
+        // 与租户范围Filter合并时先展开，租户条件作用于两组
+        let tenant: Query = "Filter: tenant_id[42]".parse().unwrap();
+        let merged = query.and_merge(tenant);
+        assert_eq!(merged.cross_combinator, CrossCombinator::And);
+        assert_eq!(merged.or_branches.len(), 1);
+        assert!(merged.or_branches[0].base_filters.iter().any(|f| f.field.0 == "tenant_id"));
+    }
+
+    #[test]
+    fn test_default_filters_scope_entities() {
+        let soft_delete: Query = "Filter: deleted_at[IS NULL]".parse().unwrap();
+        let mut default_filters = HashMap::new();
+        default_filters.insert("Test".to_string(), soft_delete.base_filters.clone());
+        default_filters.insert("Run".to_string(), soft_delete.base_filters);
+        let compiler = SqlCompiler::from_config(CompilerConfig {
+            table_mapping: TableMappingConfig::default().mappings,
+            default_filters,
+            ..Default::default()
+        });
+
+        let result = compiler.compile(Query::default(), "Test").unwrap();
+        assert_eq!(result.sql, r#"SELECT * FROM "tests" WHERE "tests"."deleted_at" IS NULL"#);
+
+        let query: Query = r#"Filter: status["Open"]; CrossFilter: <Test-Run> result["FAIL"]"#.parse().unwrap();
+        let result = compiler.compile(query, "Test").unwrap();
+        assert!(
+            result.sql.contains(r#"WHERE "tests"."status" = 'Open' AND "tests"."deleted_at" IS NULL"#),
+            "SQL: {}", result.sql
+        );
+        assert!(
+            result.sql.contains(r#""joined_table_1"."result" = 'FAIL' AND "joined_table_1"."deleted_at" IS NULL"#),
+            "SQL: {}", result.sql
+        );
+
+        // 没有配置隐式条件的实体不受影响
+        let result = compiler.compile(Query::default(), "Project").unwrap();
+        assert_eq!(result.sql, r#"SELECT * FROM "projects""#);
+    }
+
+    #[test]
+    fn test_default_filters_apply_to_every_joined_entity() {
+        let soft_delete: Query = "Filter: deleted_at[IS NULL]".parse().unwrap();
+        let default_filters = HashMap::from([
+            ("Run".to_string(), soft_delete.base_filters.clone()),
+            ("Project".to_string(), soft_delete.base_filters),
+        ]);
+        let compiler = SqlCompiler::from_config(CompilerConfig {
+            table_mapping: TableMappingConfig::default().mappings,
+            default_filters,
+            ..Default::default()
+        });
+
+        // 多跳关联链的中间实体在其 ON 条件中加入隐式条件
+        let query: Query = r#"CrossFilter: <Test-Run-Step> name["setup"]"#.parse().unwrap();
+        let result = compiler.compile(query.clone(), "Test").unwrap();
+        assert!(
+            result.sql.contains(r#""tests"."id" = "joined_table_1"."id" AND "joined_table_1"."deleted_at" IS NULL"#),
+            "SQL: {}", result.sql
+        );
+        assert!(!result.sql.contains(r#""joined_table_2"."deleted_at""#), "SQL: {}", result.sql);
+
+        let delete = compiler.compile_delete(query, "Test").unwrap();
+        assert!(delete.sql.contains(r#""joined_table_1"."deleted_at" IS NULL"#), "SQL: {}", delete.sql);
+
+        // 子查询的实体同样加入隐式条件
+        let query: Query = r#"Filter: project_id[IN (CrossFilter: <Project> status["Active"])]"#.parse().unwrap();
+        let result = compiler.compile(query, "Test").unwrap();
+        assert!(result.sql.contains(r#""projects"."deleted_at" IS NULL"#), "SQL: {}", result.sql);
+    }
+
+    #[test]
+    fn test_between_and_not_between() {
+        let mut compiler = create_test_compiler();
+
+        let query: Query = "Filter: age[BETWEEN 18 AND 65]".parse().unwrap();
+        let result = compiler.compile(query, "Test").unwrap();
+        assert!(result.sql.contains(r#""tests"."age" BETWEEN 18 AND 65"#), "SQL: {}", result.sql);
+
+        let query: Query = "Filter: age[NOT BETWEEN 18 AND 65]".parse().unwrap();
+        let result = compiler.compile(query, "Test").unwrap();
+        assert!(result.sql.contains(r#""tests"."age" NOT BETWEEN 18 AND 65"#), "SQL: {}", result.sql);
+        assert!(!result.sql.contains("NOT ("), "SQL: {}", result.sql);
+
+        // 外层 NOT 取反后同样编译为 NOT BETWEEN
+        let query: Query = "Filter: age[NOT (BETWEEN 18 AND 65)]".parse().unwrap();
+        let result = compiler.compile_optimized(query, "Test").unwrap();
+        assert!(result.sql.contains(r#""tests"."age" NOT BETWEEN 18 AND 65"#), "SQL: {}", result.sql);
+    }
+
+    #[test]
+    fn test_on_optimization_hook() {
+        use std::sync::Arc;
+
+        let mut compiler = create_test_compiler();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        compiler.set_on_optimization(move |opt| sink.lock().unwrap().push(opt.clone()));
+
+        let query: Query = r#"Filter: status["Open" OR "Pending" OR "Review" OR "Blocked" OR "Done"]"#.parse().unwrap();
+        let result = compiler.compile(query, "Test").unwrap();
+
+        assert_eq!(*seen.lock().unwrap(), vec![Optimization::OrToIn { field: "tests.status".to_string(), value_count: 5 }]);
+        assert_eq!(*seen.lock().unwrap(), result.optimizations);
+    }
+
+    #[test]
+    fn test_plan_does_not_notify_optimization_hook() {
+        let mut compiler = create_test_compiler();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        compiler.set_on_optimization(move |opt| sink.lock().unwrap().push(opt.clone()));
+
+        let query: Query = r#"Filter: status["Open" OR "Pending" OR "Review" OR "Blocked" OR "Done"]"#.parse().unwrap();
+        let planned = compiler.plan(query.clone(), "Test").unwrap();
+        assert_eq!(planned.len(), 1);
+        assert!(seen.lock().unwrap().is_empty());
+
+        // 实际编译时才通知
+        compiler.compile(query, "Test").unwrap();
+        assert_eq!(*seen.lock().unwrap(), planned);
     }
 
     #[test]
-    fn test_trait_based_compilation() {
-        let compiler: Box<dyn QueryCompiler> = Box::new(SqlCompiler::new());
-        
-        let query = Query {
-            base_filters: vec![
-                FieldFilter {
-                    field: Identifier("status".to_string()),
-                    condition: Condition::Comparison {
-                        op: CompOp::Eq,
-                        value: Literal::String("Open".to_string()),
-                    },
-                }
-            ],
-            cross_filters: vec![],
+    fn test_poisoned_optimization_hook_does_not_panic_compile() {
+        let mut compiler = create_test_compiler();
+        let calls = Arc::new(Mutex::new(0));
+        let sink = Arc::clone(&calls);
+        compiler.set_on_optimization(move |_| {
+            let count = {
+                let mut calls = sink.lock().unwrap();
+                *calls += 1;
+                *calls
+            };
+            if count == 1 {
+                panic!("hook failure");
+            }
+        });
+
+        let query: Query = r#"Filter: status["Open" OR "Pending" OR "Review" OR "Blocked" OR "Done"]"#.parse().unwrap();
+        let first = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| compiler.compile(query.clone(), "Test")));
+        assert!(first.is_err());
+
+        // 回调 panic 使互斥锁中毒后，之后的编译仍然成功并继续调用回调
+        assert!(compiler.compile(query, "Test").is_ok());
+        assert_eq!(*calls.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_lint_always_false_filters() {
+        let compiler = create_test_compiler();
+        let lint_kinds = |dsl: &str| -> Vec<LintKind> {
+            let query: Query = dsl.parse().unwrap();
+            compiler.lint(&query, "Test").into_iter().map(|lint| lint.kind).collect()
         };
 
+        assert_eq!(lint_kinds("Filter: priority[>10 AND <5]"), vec![LintKind::EmptyRange]);
+        assert_eq!(lint_kinds("Filter: priority[>5 AND <5]"), vec![LintKind::EmptyRange]);
+        assert_eq!(lint_kinds("Filter: priority[BETWEEN 10 AND 1]"), vec![LintKind::EmptyRange]);
+        assert_eq!(lint_kinds("Filter: priority[=3 AND >=4]"), vec![LintKind::EmptyRange]);
+        assert_eq!(lint_kinds("Filter: status[IN ()]"), vec![LintKind::EmptyInList]);
+        assert_eq!(lint_kinds(r#"Filter: owner[IS NULL AND "x"]"#), vec![LintKind::NullComparison]);
+        assert_eq!(lint_kinds("Filter: owner[IS NULL AND IS NOT NULL]"), vec![LintKind::NullComparison]);
+
+        // OR 的一侧永远不成立时同样报告，关联Filter归属于其目标实体
+        let query: Query = r#"Filter: status["Open"]; CrossFilter: <Test-Run> duration["fast" OR (>10 AND <5)]"#.parse().unwrap();
+        let lints = compiler.lint(&query, "Test");
+        assert_eq!(lints.len(), 1);
+        assert_eq!((lints[0].kind, lints[0].entity.as_str(), lints[0].field.as_str()), (LintKind::EmptyRange, "Run", "duration"));
+        assert!(lints[0].span.is_some());
+        assert!(lints[0].message.contains("duration"), "{}", lints[0].message);
+
+        // 可以满足的条件不报告
+        assert!(lint_kinds("Filter: priority[>=5 AND <=5]; owner[IS NULL OR \"x\"]; due[NOT (>10 AND <5)]").is_empty());
+    }
+
+    #[test]
+    fn test_compile_delete_and_update() {
+        let compiler = create_test_compiler();
+
+        let query: Query = r#"Filter: status["Closed"]"#.parse().unwrap();
+        let result = compiler.compile_delete(query.clone(), "Test").unwrap();
+        assert_eq!(result.sql, r#"DELETE FROM "tests" WHERE "tests"."status" = 'Closed'"#);
+
+        let set = vec![("status".to_string(), Value::from("Archived")), ("priority".to_string(), Value::from(0))];
+        let result = compiler.compile_update(query, "Test", set).unwrap();
+        assert_eq!(
+            result.sql,
+            r#"UPDATE "tests" SET "status" = 'Archived', "priority" = 0 WHERE "tests"."status" = 'Closed'"#
+        );
+
+        // 关联Filter编译为 EXISTS 子查询而不是 JOIN
+        let query: Query = r#"CrossFilter: <Test-Run> result["FAIL"]"#.parse().unwrap();
+        let result = compiler.compile_delete(query, "Test").unwrap();
+        assert_eq!(
+            result.sql,
+            r#"DELETE FROM "tests" WHERE EXISTS(SELECT 1 FROM "test_runs" AS "joined_table_1" WHERE "tests"."id" = "joined_table_1"."id" AND "joined_table_1"."result" = 'FAIL')"#
+        );
+
+        let err = compiler.compile_update(Query::default(), "Test", Vec::new()).unwrap_err();
+        assert!(err.message.contains("UPDATE"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_unfiltered_delete_and_update_are_rejected() {
+        let compiler = create_test_compiler();
+        let set = || vec![("status".to_string(), Value::from("Archived"))];
+
+        let err = compiler.compile_delete(Query::default(), "Test").unwrap_err();
+        assert_eq!(err.message, "UPDATE/DELETE 没有过滤条件，拒绝修改整张表");
+        assert!(compiler.compile_update(Query::default(), "Test", set()).is_err());
+
+        // 段落级 OR 的某个分支为空时条件恒成立
+        let mut query: Query = r#"Filter: status["Closed"]"#.parse().unwrap();
+        query.or_branches.push(Query::default());
+        assert!(compiler.compile_delete(query.clone(), "Test").is_err());
+        assert!(compiler.compile_update(query, "Test", set()).is_err());
+    }
+
+    #[test]
+    fn test_plan_matches_compile_optimizations() {
+        let compiler = create_test_compiler();
+        let query: Query = r#"Filter: status["A" OR "B" OR "C" OR "D" OR "E"]; priority[>=1 AND <=3]; CrossFilter: <Test-Run> result[NOT "x" AND NOT "y" AND NOT "z" AND NOT "w" AND NOT "v"]"#
+            .parse()
+            .unwrap();
+
+        let planned = compiler.plan(query.clone(), "Test").unwrap();
         let result = compiler.compile(query, "Test").unwrap();
-        assert_eq!(compiler.name(), "SeaQuerySqlCompiler");
-        assert_eq!(compiler.supported_dialect(), SqlDialect::PostgreSQL);
-        assert!(result.sql.contains("status"));
+        assert_eq!(planned.len(), 3);
+        assert_eq!(planned, result.optimizations);
     }
 
     #[test]
-    fn test_custom_compiler() {
-        let compiler = CustomCompiler::new("TestCompiler".to_string(), SqlDialect::MySQL);
-        
-        let query = Query {
-            base_filters: vec![],
-            cross_filters: vec![],
-        };
+    fn test_like_with_escaped_wildcard() {
+        let compiler = create_test_compiler();
 
+        let query: Query = r#"Filter: name[LIKE "bug%"]"#.parse().unwrap();
         let result = compiler.compile(query, "Test").unwrap();
-        assert!(result.sql.contains("custom_table"));
-        assert!(result.sql.contains("TestCompiler"));
-        assert!(result.sql.contains("MySQL"));
-        assert_eq!(compiler.name(), "CustomCompiler");
-        assert_eq!(compiler.supported_dialect(), SqlDialect::MySQL);
+        assert!(result.sql.ends_with(r#""tests"."name" LIKE 'bug%'"#), "SQL: {}", result.sql);
+
+        let query: Query = r#"Filter: discount[LIKE "50\%"]"#.parse().unwrap();
+        let result = compiler.compile(query, "Test").unwrap();
+        // PostgreSQL 中含反斜杠的字符串以 E'' 形式输出
+        assert!(result.sql.ends_with(r#""tests"."discount" LIKE E'50\\%' ESCAPE E'\\'"#), "SQL: {}", result.sql);
+
+        let mysql = SqlCompiler::from_config(CompilerConfig { dialect: SqlDialect::MySQL, ..Default::default() });
+        let query: Query = r#"Filter: code[NOT LIKE "a\_b"]"#.parse().unwrap();
+        let result = mysql.compile(query, "Test").unwrap();
+        assert!(result.sql.ends_with(r"`test`.`code` NOT LIKE 'a\\_b' ESCAPE '\\'"), "SQL: {}", result.sql);
     }
 
     #[test]
-    fn test_compiler_interface() {
-        let compiler = SqlCompiler::new();
-        
+    fn test_sort_by_computed_expression() {
+        let compiler = create_test_compiler();
+
+        let query: Query = r#"Filter: status["Open"]; Sort: priority * 2 DESC"#.parse().unwrap();
+        let result = compiler.compile(query, "Test").unwrap();
+        assert_eq!(
+            result.sql,
+            r#"SELECT * FROM "tests" WHERE "tests"."status" = 'Open' ORDER BY "tests"."priority" * 2 DESC"#
+        );
+
+        let query: Query = "Sort: (estimated - actual) / 2, name".parse().unwrap();
+        let result = compiler.compile(query, "Test").unwrap();
+        assert!(
+            result.sql.ends_with(r#"ORDER BY ("tests"."estimated" - "tests"."actual") / 2 ASC, "tests"."name" ASC"#),
+            "SQL: {}", result.sql
+        );
+    }
+
+    #[test]
+    fn test_pretty_sql_breaks_major_clauses() {
+        use crate::lexer::Lexer;
+        use crate::parser::Parser;
+
+        let input = r#"Filter: title["a AND b"]; id[BETWEEN 1 AND 9]; CrossFilter: <Test-Run> result["FAIL" OR "ERROR"]"#;
+        let tokens: Vec<_> = Lexer::new(input).collect();
+        let query = Parser::new(&tokens).parse().unwrap();
+
+        let compiler = create_test_compiler();
+        let result = compiler.compile(query, "Test").unwrap();
+        let pretty = result.pretty_sql();
+
+        assert!(!result.sql.contains('\n'));
+        assert!(pretty.starts_with("SELECT"), "SQL:\n{}", pretty);
+        for clause in ["\nFROM ", "\nINNER JOIN ", "\nWHERE ", "\n  AND "] {
+            assert!(pretty.contains(clause), "缺少 {:?}，SQL:\n{}", clause, pretty);
+        }
+        // 字符串中的关键字和 BETWEEN 的 AND 不换行
+        assert!(pretty.contains("'a AND b'"), "SQL:\n{}", pretty);
+        assert!(pretty.contains("BETWEEN 1 AND 9"), "SQL:\n{}", pretty);
+        // 换行只改变空白
+        let squash = |s: &str| s.split_whitespace().collect::<Vec<_>>().join(" ");
+        assert_eq!(squash(&pretty), squash(&result.sql));
+    }
+
+    #[test]
+    fn test_is_empty_expands_to_null_check() {
+        let compiler = create_test_compiler();
+
+        let query: Query = r#"Filter: status["Open"]; note[IS EMPTY]"#.parse().unwrap();
+        let result = compiler.compile(query, "Test").unwrap();
+        assert!(
+            result.sql.ends_with(r#"WHERE "tests"."status" = 'Open' AND ("tests"."note" = '' OR "tests"."note" IS NULL)"#),
+            "SQL: {}", result.sql
+        );
+
+        let query: Query = "Filter: note[IS NOT EMPTY]".parse().unwrap();
+        let result = compiler.compile(query, "Test").unwrap();
+        assert!(
+            result.sql.ends_with(r#"WHERE "tests"."note" <> '' AND "tests"."note" IS NOT NULL"#),
+            "SQL: {}", result.sql
+        );
+    }
+
+    #[test]
+    fn test_string_literal_escaping() {
+        let input = r#"Filter: name["O'Brien"]; path["C:\tmp"]; CrossFilter: <Test-Run> note["x' OR '1'='1"]"#;
+        let compile = |dialect, string_quote_style| {
+            let compiler = SqlCompiler::from_config(CompilerConfig {
+                dialect,
+                string_quote_style,
+                table_mapping: HashMap::from([("Test".to_string(), "te\"sts".to_string())]),
+                ..Default::default()
+            });
+            compiler.compile(input.parse().unwrap(), "Test").unwrap().sql
+        };
+
+        // 默认沿用方言后端的转义
+        let sql = compile(SqlDialect::PostgreSQL, StringQuoteStyle::Dialect);
+        assert!(sql.contains(r#""te""sts"."name" = E'O\'Brien'"#), "SQL: {}", sql);
+        assert!(sql.contains(r#""joined_table_1"."note" = E'x\' OR \'1\'=\'1'"#), "SQL: {}", sql);
+        let sql = compile(SqlDialect::SQLite, StringQuoteStyle::Dialect);
+        assert!(sql.contains(r#""name" = 'O''Brien'"#), "SQL: {}", sql);
+
+        let sql = compile(SqlDialect::PostgreSQL, StringQuoteStyle::Standard);
+        assert!(sql.contains(r#""te""sts"."name" = 'O''Brien'"#), "SQL: {}", sql);
+        assert!(sql.contains(r#""path" = 'C:\tmp'"#), "SQL: {}", sql);
+        assert!(sql.ends_with(r#""joined_table_1"."note" = 'x'' OR ''1''=''1'"#), "SQL: {}", sql);
+        assert!(!sql.contains("E'"), "SQL: {}", sql);
+
+        // MySQL 默认把反斜杠视为转义字符
+        let sql = compile(SqlDialect::MySQL, StringQuoteStyle::Standard);
+        assert!(sql.contains("`name` = 'O''Brien'"), "SQL: {}", sql);
+        assert!(sql.contains(r#"`path` = 'C:\\tmp'"#), "SQL: {}", sql);
+
+        // WHERE 片段、DELETE 与 UPDATE 使用同样的写法
+        let compiler = SqlCompiler::from_config(CompilerConfig {
+            string_quote_style: StringQuoteStyle::Standard,
+            ..Default::default()
+        });
+        let query: Query = r#"Filter: name["O'Brien"]"#.parse().unwrap();
+        assert_eq!(compiler.compile_where(query.clone(), "Test").unwrap(), r#""test"."name" = 'O''Brien'"#);
+        let update = compiler.compile_update(query, "Test", vec![("note".to_string(), "it's".into())]).unwrap();
+        assert!(update.sql.contains("\"note\" = 'it''s'"), "SQL: {}", update.sql);
+    }
+
+    #[test]
+    fn test_mine_recently_macro_expansion() {
+        let compiler = SqlCompiler::from_config(CompilerConfig {
+            mine_recently_fields: HashMap::from([(
+                "Bug".to_string(),
+                MineRecentlyFields { owner_field: "owner".to_string(), modified_field: "updated_at".to_string(), days: 3 },
+            )]),
+            ..Default::default()
+        });
+
+        let query: Query = r#"Filter: status["Open"]; MINE_RECENTLY"#.parse().unwrap();
+        let result = compiler.compile(query.clone(), "Test").unwrap();
+        assert_eq!(
+            result.sql,
+            r#"SELECT * FROM "test" WHERE "test"."status" = 'Open' AND "test"."assignee" = CURRENT_USER AND "test"."modified" > (CURRENT_DATE - INTERVAL '7 days')"#
+        );
+
+        // 按实体配置的字段和天数
+        let result = compiler.compile(query, "Bug").unwrap();
+        assert!(
+            result.sql.ends_with(r#""bug"."owner" = CURRENT_USER AND "bug"."updated_at" > (CURRENT_DATE - INTERVAL '3 days')"#),
+            "SQL: {}", result.sql
+        );
+
+        let query: Query = "Filter: MINE_RECENTLY".parse().unwrap();
+        assert_eq!(
+            compiler.compile_where(query.clone(), "Test").unwrap(),
+            r#""test"."assignee" = CURRENT_USER AND "test"."modified" > (CURRENT_DATE - INTERVAL '7 days')"#
+        );
+
+        // 引用的字段包括宏按实体展开的字段
+        let fields = |entity: &str, names: &[&str]| {
+            names.iter().map(|name| (entity.to_string(), String::from(*name))).collect::<Vec<_>>()
+        };
+        assert_eq!(compiler.referenced_fields(&query, "Test"), fields("Test", &["assignee", "modified"]));
+        assert_eq!(compiler.referenced_fields(&query, "Bug"), fields("Bug", &["owner", "updated_at"]));
+        assert!(query.referenced_fields("Test").is_empty());
+    }
+
+    #[test]
+    fn test_strict_entities_rejects_unmapped_cross_filter_target() {
+        let config = |strict_entities| CompilerConfig {
+            strict_entities,
+            table_mapping: HashMap::from([
+                ("Test".to_string(), "tests".to_string()),
+                ("Run".to_string(), "test_runs".to_string()),
+            ]),
+            ..Default::default()
+        };
+        let strict = SqlCompiler::from_config(config(true));
+
+        let mapped: Query = r#"CrossFilter: <Test-Run> result["FAIL"]"#.parse().unwrap();
+        assert!(strict.compile(mapped, "Test").is_ok());
+
+        let unmapped: Query = r#"Filter: status["Open"]; CrossFilter: <Test-Run-Bug> severity[>2]"#.parse().unwrap();
+        let err = strict.compile(unmapped.clone(), "Test").unwrap_err();
+        assert_eq!(err.message, "关联Filter <Test-Run-Bug> 中的实体 `Bug` 没有配置表映射");
+        assert!(strict.compile_delete(unmapped.clone(), "Test").is_err());
+
+        let err = strict.compile(r#"Filter: status["Open"]"#.parse().unwrap(), "Bug").unwrap_err();
+        assert_eq!(err.message, "实体 `Bug` 没有配置表映射");
+
+        // 未启用时按命名策略推导表名
+        let lenient = SqlCompiler::from_config(config(false));
+        let result = lenient.compile(unmapped, "Test").unwrap();
+        assert!(result.sql.contains(r#"INNER JOIN "bug" AS "joined_table_2""#), "SQL: {}", result.sql);
+    }
+
+    #[test]
+    fn test_replica_hint() {
+        let compile = |dialect, hint| {
+            let compiler = SqlCompiler::from_config(CompilerConfig {
+                dialect,
+                replica_hint: Some(hint),
+                ..Default::default()
+            });
+            compiler.compile(r#"Filter: status["Open"]"#.parse().unwrap(), "Test")
+        };
+
+        let sql = compile(SqlDialect::PostgreSQL, ReplicaHint::Comment("route=replica".to_string())).unwrap().sql;
+        assert_eq!(sql, r#"/* route=replica */ SELECT * FROM "test" WHERE "test"."status" = 'Open'"#);
+
+        let sql = compile(SqlDialect::MySQL, ReplicaHint::OptimizerHint("READ_REPLICA".to_string())).unwrap().sql;
+        assert_eq!(sql, "SELECT /*+ READ_REPLICA */ * FROM `test` WHERE `test`.`status` = 'Open'");
+
+        let err = compile(SqlDialect::PostgreSQL, ReplicaHint::OptimizerHint("READ_REPLICA".to_string())).unwrap_err();
+        assert_eq!(err.message, "PostgreSQL 方言不支持优化器提示");
+        let err = compile(SqlDialect::PostgreSQL, ReplicaHint::Comment("x */ DROP TABLE t; /*".to_string())).unwrap_err();
+        assert_eq!(err.message, "只读副本提示不能包含 `*/`");
+
+        // 参数化编译同样带有提示
+        let compiler = SqlCompiler::from_config(CompilerConfig {
+            replica_hint: Some(ReplicaHint::Comment("replica".to_string())),
+            ..Default::default()
+        });
+        let parameterized = compiler.compile_parameterized(r#"Filter: status["Open"]"#.parse().unwrap(), "Test").unwrap();
+        assert!(parameterized.sql.starts_with("/* replica */ SELECT"), "SQL: {}", parameterized.sql);
+    }
+
+    #[test]
+    fn test_parameterized_values_follow_placeholder_order() {
+        let compiler = create_test_compiler();
+        let query: Query = r#"Filter: priority[>3]; status["a" OR "b" OR "c" OR "d" OR "e"]; CrossFilter: <Test-Run> result["FAIL"]; project[IN (CrossFilter: <Project> owner["bob"])]; Sort: priority * 2 DESC"#
+            .parse()
+            .unwrap();
+        let result = compiler.compile_parameterized(query, "Test").unwrap();
+
+        // 占位符按 $1, $2, ... 从左到右出现，JOIN、WHERE、子查询和 ORDER BY 中的参数都不会乱序
+        let placeholders: Vec<usize> = result.sql.split('$').skip(1)
+            .map(|rest| rest.chars().take_while(char::is_ascii_digit).collect::<String>().parse().unwrap())
+            .collect();
+        assert_eq!(placeholders, (1..=result.values.len()).collect::<Vec<_>>(), "SQL: {}", result.sql);
+
+        let string = |s: &str| Value::String(Some(Box::new(s.to_string())));
+        let mut expected = vec![Value::BigInt(Some(3))];
+        expected.extend(["a", "b", "c", "d", "e"].map(string));
+        expected.extend([string("FAIL"), string("bob"), Value::BigInt(Some(2))]);
+        assert_eq!(result.values, expected, "SQL: {}", result.sql);
+        assert!(result.optimizations.iter().any(|o| matches!(o, Optimization::OrToIn { .. })));
+    }
+
+    #[test]
+    fn test_partition_pruning_friendly_dates() {
+        let compiler = |partition_pruning| SqlCompiler::from_config(CompilerConfig {
+            partition_keys: HashMap::from([("Test".to_string(), "created".to_string())]),
+            partition_pruning,
+            case_insensitive_strings: true,
+            ..Default::default()
+        });
+        let pruning = compiler(true);
+
+        // 范围比较本身就便于裁剪
+        let result = pruning.compile(r#"Filter: created[>= "2023-01-01"]"#.parse().unwrap(), "Test").unwrap();
+        assert!(result.sql.ends_with(r#"WHERE "test"."created" >= '2023-01-01'"#), "SQL: {}", result.sql);
+        assert!(result.diagnostics.is_empty());
+
+        // 单日相等比较展开为半开区间，不再包裹 LOWER()
+        let result = pruning.compile(r#"Filter: created["2024-02-28"]; name["x"]"#.parse().unwrap(), "Test").unwrap();
+        assert!(
+            result.sql.contains(r#""test"."created" >= '2024-02-28' AND "test"."created" < '2024-02-29'"#),
+            "SQL: {}", result.sql
+        );
+        assert!(result.sql.contains(r#"LOWER("test"."name")"#), "SQL: {}", result.sql);
+        assert!(result.diagnostics.is_empty());
+        let result = pruning.compile(r#"Filter: created[!= "2023-12-31"]"#.parse().unwrap(), "Test").unwrap();
+        assert!(
+            result.sql.ends_with(r#""test"."created" < '2023-12-31' OR "test"."created" >= '2024-01-01'"#),
+            "SQL: {}", result.sql
+        );
+
+        // WHERE 片段同样改写分区键
+        let fragment = pruning.compile_where(r#"Filter: created["2024-02-28"]"#.parse().unwrap(), "Test").unwrap();
+        assert_eq!(fragment, r#""test"."created" >= '2024-02-28' AND "test"."created" < '2024-02-29'"#);
+
+        // 未启用时分区键被函数包裹，给出警告
+        let result = compiler(false).compile(r#"Filter: created["2023-01-01"]"#.parse().unwrap(), "Test").unwrap();
+        assert!(result.sql.contains(r#"LOWER("test"."created")"#), "SQL: {}", result.sql);
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic::warning("实体 Test 的分区键 created 会被 LOWER() 包裹，无法利用分区裁剪".to_string())]
+        );
+
+        assert_eq!(next_day("2023-02-28"), Some("2023-03-01".to_string()));
+        assert_eq!(next_day("2023-02-29"), None);
+        assert_eq!(next_day("2023-1-01"), None);
+    }
+
+    #[test]
+    fn test_compile_paginated_shares_where_clause() {
+        let compiler = create_test_compiler();
+        let query: Query = r#"Filter: status["Open"]; CrossFilter: <Test-Run> result["FAIL"]; Sort: priority DESC"#.parse().unwrap();
+
+        let paginated = compiler.compile_paginated(query.clone(), "Test", 20, 40).unwrap();
+        assert_eq!(paginated.count_sql, compiler.compile_count(query.clone(), "Test").unwrap());
+
+        let data_body = paginated.data_sql.strip_prefix("SELECT * ").unwrap();
+        let count_body = paginated.count_sql.strip_prefix("SELECT COUNT(*) ").unwrap();
+        let (data_from, data_tail) = data_body.split_once(" ORDER BY ").unwrap();
+        assert_eq!(data_from, count_body);
+        assert!(count_body.contains(r#"WHERE "tests"."status" = 'Open'"#), "SQL: {}", count_body);
+        assert!(data_tail.ends_with(" LIMIT 20 OFFSET 40"), "SQL: {}", paginated.data_sql);
+
+        // 去掉分页后与普通编译结果一致
+        let compiled = compiler.compile(query, "Test").unwrap();
+        assert_eq!(paginated.data_sql.strip_suffix(" LIMIT 20 OFFSET 40"), Some(compiled.sql.as_str()));
+    }
+
+    #[test]
+    fn test_in_list_with_null_matches_null_rows() {
+        let compiler = create_test_compiler();
+
+        let result = compiler.compile(r#"Filter: status[IN ("Open", "Pending", null)]"#.parse().unwrap(), "Test").unwrap();
+        assert!(
+            result.sql.ends_with(r#"WHERE "tests"."status" IN ('Open', 'Pending') OR "tests"."status" IS NULL"#),
+            "SQL: {}", result.sql
+        );
+        assert_eq!(result.optimizations, vec![Optimization::InNullToIsNull { field: "tests.status".to_string() }]);
+
+        let result = compiler.compile(r#"Filter: status[NOT IN ("Open", null)]; priority[>1]"#.parse().unwrap(), "Test").unwrap();
+        assert!(
+            result.sql.contains(r#"NOT ("tests"."status" IN ('Open') OR "tests"."status" IS NULL)"#),
+            "SQL: {}", result.sql
+        );
+
+        let result = compiler.compile(r#"Filter: status[IN (null)]"#.parse().unwrap(), "Test").unwrap();
+        assert!(result.sql.ends_with(r#"WHERE "tests"."status" IS NULL"#), "SQL: {}", result.sql);
+    }
+
+    #[test]
+    fn test_raw_join_overrides_join_key() {
+        let mut compiler = SqlCompiler::from_config(CompilerConfig {
+            table_mapping: HashMap::from([
+                ("Test".to_string(), "tests".to_string()),
+                ("Run".to_string(), "test_runs".to_string()),
+            ]),
+            join_keys: HashMap::from([(("Test".to_string(), "Run".to_string()), JoinKey::new("latest_run_id", "id"))]),
+            ..Default::default()
+        });
+        let query: Query = r#"Filter: status["Open"]; CrossFilter: <Test-Run> result["FAIL"]"#.parse().unwrap();
+        let before = compiler.compile(query.clone(), "Test").unwrap();
+        assert!(before.sql.contains(r#""latest_run_id""#), "SQL: {}", before.sql);
+
+        let on = "tests.id = test_runs.test_id AND test_runs.active";
+        compiler.add_raw_join("Test", "Run", on);
+        let result = compiler.compile(query.clone(), "Test").unwrap();
+        assert!(
+            result.sql.contains(&format!(r#"INNER JOIN "test_runs" ON {} WHERE"#, on)),
+            "SQL: {}", result.sql
+        );
+        assert!(result.sql.contains(r#""test_runs"."result" = 'FAIL'"#), "SQL: {}", result.sql);
+        assert!(!result.sql.contains("latest_run_id") && !result.sql.contains("joined_table_"), "SQL: {}", result.sql);
+        assert!(result.diagnostics.is_empty());
+
+        // DELETE 的 EXISTS 子查询同样使用原始连接
+        let delete = compiler.compile_delete(query, "Test").unwrap();
+        assert!(
+            delete.sql.contains(&format!(r#"EXISTS(SELECT 1 FROM "test_runs" WHERE ({}) AND "test_runs"."result" = 'FAIL')"#, on)),
+            "SQL: {}", delete.sql
+        );
+    }
+
+    #[test]
+    fn test_repeated_raw_join_is_rejected() {
+        let mut compiler = create_test_compiler();
+        compiler.add_raw_join("Test", "Run", "tests.id = test_runs.test_id");
+        let query: Query = r#"CrossFilter: <Test-Run> result["FAIL"]; CrossFilter: <Test-Run> duration[>100]"#.parse().unwrap();
+        let err = compiler.compile(query, "Test").unwrap_err();
+        assert!(err.message.contains("test_runs"), "{}", err.message);
+
+        // 段落级 OR 的分支共用一条 SELECT，同样拒绝
+        let query: Query = r#"CrossFilter: <Test-Run> result["FAIL"] OR CrossFilter: <Test-Run> result["ERROR"]"#.parse().unwrap();
+        assert!(compiler.compile(query, "Test").is_err());
+
+        // DELETE 中每个关联Filter是独立的 EXISTS 子查询，不受影响
+        let query: Query = r#"CrossFilter: <Test-Run> result["FAIL"]; CrossFilter: <Test-Run> duration[>100]"#.parse().unwrap();
+        assert!(compiler.compile_delete(query, "Test").is_ok());
+
+        // 只出现一次的原始连接正常编译
+        let query: Query = r#"CrossFilter: <Test-Run> result["FAIL"]"#.parse().unwrap();
+        assert!(compiler.compile(query, "Test").is_ok());
+    }
+
+    #[test]
+    fn test_config_warnings_for_nonsensical_thresholds() {
+        assert!(SqlCompiler::new().config_warnings().is_empty());
+
+        let compiler = SqlCompiler::from_config(CompilerConfig {
+            optimization_config: OptimizationConfig { max_in_values: 0, ..Default::default() },
+            ..Default::default()
+        });
+        assert_eq!(
+            compiler.config_warnings(),
+            [Diagnostic::warning("max_in_values 为 0，编译任何非空的 IN 列表都会失败".to_string())]
+        );
+        let err = compiler.compile("Filter: id[IN (1, 2)]".parse().unwrap(), "Test").unwrap_err();
+        assert_eq!(err.message, "max_in_values 必须大于 0，无法拆分 IN 列表");
+
+        let compiler = SqlCompiler::from_config(CompilerConfig {
+            batch_config: BatchConfig { max_batch_size: 0, ..Default::default() },
+            ..Default::default()
+        });
+        assert_eq!(
+            compiler.config_warnings(),
+            [Diagnostic::warning("max_batch_size 为 0，批量编译任何查询都会失败".to_string())]
+        );
+        let err = compiler.compile_batch_query("Filter: id[IN (1, 2)]".parse().unwrap(), "Test").unwrap_err();
+        assert_eq!(err.message, "max_batch_size 必须大于 0");
+
+        let batch_config = BatchConfig { max_batch_size: 70000, ..Default::default() };
+        let compiler = SqlCompiler::from_config(CompilerConfig {
+            batch_config: batch_config.clone(),
+            dialect: SqlDialect::PostgreSQL,
+            ..Default::default()
+        });
+        assert_eq!(
+            compiler.config_warnings(),
+            [Diagnostic::warning(
+                "max_batch_size (70000) 超过 PostgreSQL 的参数数量上限 65535，单个批次可能无法执行".to_string()
+            )]
+        );
+
+        // 未启用批量处理时不检查批次大小
+        let batch_config = BatchConfig { enable_batch_processing: false, ..batch_config };
+        assert!(OptimizationConfig::default().validate(&batch_config, SqlDialect::PostgreSQL).is_empty());
+    }
+
+    #[test]
+    fn test_null_safe_eq_unsupported_dialects() {
+        for dialect in [SqlDialect::MsSQL, SqlDialect::Oracle] {
+            let compiler = SqlCompiler::from_config(CompilerConfig { dialect, ..Default::default() });
+            let query: Query = r#"Filter: owner[<=> "bob"]"#.parse().unwrap();
+            let err = compiler.compile(query, "Test").unwrap_err();
+            assert!(err.message.contains("<=>"), "{}", err.message);
+        }
+    }
+
+    #[test]
+    fn test_dialect_without_query_builder_is_rejected() {
+        for dialect in [SqlDialect::MsSQL, SqlDialect::Oracle] {
+            let compiler = SqlCompiler::from_config(CompilerConfig { dialect, ..Default::default() });
+            let query: Query = r#"Filter: status["Open"]"#.parse().unwrap();
+            let err = compiler.compile(query.clone(), "Test").unwrap_err();
+            assert!(err.message.contains(&format!("{:?}", dialect)), "{}", err.message);
+            assert!(compiler.compile_parameterized(query.clone(), "Test").is_err());
+            assert!(compiler.compile_where(query.clone(), "Test").is_err());
+            assert!(compiler.compile_delete(query, "Test").is_err());
+        }
+    }
+
+    #[test]
+    fn test_sample_clause_unsupported_dialect() {
+        let compiler = SqlCompiler::from_config(CompilerConfig {
+            dialect: SqlDialect::SQLite,
+            ..Default::default()
+        });
         let query = Query {
+            sample_percent: Some(10),
+            ..Default::default()
+        };
+
+        let err = compiler.compile(query, "Test").unwrap_err();
+        assert!(err.message.contains("TABLESAMPLE"));
+    }
+
+    #[test]
+    fn test_compile_cache_hit_returns_identical_sql() {
+        let compiler = SqlCompiler::from_config(CompilerConfig {
+            cache_config: CacheConfig {
+                enable_compile_cache: true,
+                max_entries: 2,
+            },
+            ..Default::default()
+        });
+        let status_query = |status: &str| Query {
             base_filters: vec![
                 FieldFilter {
-                    field: Identifier("priority".to_string()),
+                    field: Identifier("status".to_string()),
                     condition: Condition::Comparison {
                         op: CompOp::Eq,
-                        value: Literal::String("High".to_string()),
+                        value: Literal::String(status.to_string()),
                     },
+                    span: None,
                 }
             ],
-            cross_filters: vec![],
+            ..Default::default()
         };
 
-        // 测试编译
-        let result = compiler.compile(query.clone(), "Test").unwrap();
-        assert!(result.sql.contains("priority"));
-        
-        // 测试复杂度评估
-        let complexity = compiler.batch_processor().estimate_query_complexity(&query);
-        assert_eq!(complexity.join_count, 0);
-        assert_eq!(complexity.condition_count, 1);
-        assert!(complexity.complexity_score > 0.0);
-    }
+        let first = compiler.compile(status_query("Open"), "Test").unwrap();
+        let second = compiler.compile(status_query("Open"), "Test").unwrap();
+        assert_eq!(first.sql, second.sql);
+        assert_eq!(first.optimizations, second.optimizations);
 
-    #[test]
-    fn test_compiler_registry() {
-        let mut registry = CompilerRegistry::new();
-        
-        // 注册自定义编译器
-        registry.register("custom", || {
-            Box::new(CustomCompiler::new("RegisteredCustom".to_string(), SqlDialect::SQLite))
-        });
-        
-        // 测试默认编译器
-        let default_compiler = registry.create("default").unwrap();
-        assert_eq!(default_compiler.name(), "SeaQuerySqlCompiler");
-        
-        // 测试自定义编译器
-        let custom_compiler = registry.create("custom").unwrap();
-        assert_eq!(custom_compiler.name(), "CustomCompiler");
-        
-        // 测试可用编译器列表
-        let available = registry.available_compilers();
-        assert!(available.contains(&"default".to_string()));
-        assert!(available.contains(&"custom".to_string()));
-        assert!(available.contains(&"sql".to_string()));
+        let stats = compiler.compile_cache_stats().unwrap();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 1);
+
+        // 同一查询针对不同实体不能命中缓存
+        let other_entity = compiler.compile(status_query("Open"), "Run").unwrap();
+        assert_ne!(first.sql, other_entity.sql);
+
+        // 超出容量时淘汰最久未使用的条目
+        compiler.compile(status_query("Closed"), "Test").unwrap();
+        assert_eq!(compiler.compile_cache_stats().unwrap().entries, 2);
     }
 
     #[test]
-    fn test_compiler_factory() {
-        // 测试默认工厂
-        let compiler = SqlCompilerFactory::create_default();
-        assert_eq!(compiler.name(), "SeaQuerySqlCompiler");
-        
-        // 测试配置工厂
-        let config = CompilerConfig {
-            optimization_config: OptimizationConfig {
-                max_or_conditions_for_in: 10,
-                max_in_values: 2000,
-            },
-            batch_config: BatchConfig::default(),
-            table_mapping: {
-                let mut map = HashMap::new();
-                map.insert("Entity".to_string(), "entity_table".to_string());
-                map
-            },
-            dialect: SqlDialect::PostgreSQL,
-        };
-        
-        let compiler = SqlCompilerFactory::create_with_config(config.clone()).unwrap();
-        assert_eq!(compiler.optimizer().optimization_config().max_or_conditions_for_in, 10);
-        assert_eq!(compiler.optimizer().optimization_config().max_in_values, 2000);
-        assert_eq!(compiler.table_mapper().get_table_name("Entity"), "entity_table");
+    fn test_compile_cache_disabled_by_default() {
+        let compiler = SqlCompiler::new();
+        assert!(compiler.compile_cache_stats().is_none());
     }
 
     #[test]
@@ -1063,6 +5605,7 @@ mod tests {
             let query = Query {
                 base_filters: vec![],
                 cross_filters: vec![],
+                ..Default::default()
             };
             
             let result = compiler.compile(query, "Test").unwrap();
@@ -13,6 +13,8 @@ pub enum TokenKind<'a> {
     // 关键字
     Filter,      // "Filter:"
     CrossFilter, // "CrossFilter:"
+    Sample,      // "Sample:"
+    Sort,        // "Sort:"
     And,         // "AND"
     Or,          // "OR"
     Not,         // "NOT"
@@ -22,7 +24,8 @@ pub enum TokenKind<'a> {
 
     // 字面量
     Identifier(&'a str),
-    String(&'a str), // 原始字符串，包括引号
+    /// 字符串字面量：`value` 为去掉引号后的内容，`raw` 为源文本中包括引号的原始切片
+    String { value: &'a str, raw: &'a str },
     Number(i64),
 
     // 特殊值关键字
@@ -39,6 +42,12 @@ pub enum TokenKind<'a> {
     Semicolon, // ;
     Comma,     // ,
     Dash,      // -
+    Plus,      // +
+    At,        // @
+    Percent,   // %
+    Pipe,      // |
+    Star,      // *
+    Slash,     // /
 
     // 运算符
     Eq,    // =
@@ -47,6 +56,7 @@ pub enum TokenKind<'a> {
     Lt,    // <
     Gte,   // >=
     Lte,   // <=
+    NullSafeEq, // <=>
 
     // 特殊
     Illegal, // 非法/未知字符